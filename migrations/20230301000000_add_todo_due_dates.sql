@@ -0,0 +1,2 @@
+ALTER TABLE todos ADD COLUMN due_at TIMESTAMP;
+ALTER TABLE todos ADD COLUMN remind_at TIMESTAMP;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS tags (
+  id       INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id  INTEGER NOT NULL,
+  name     TEXT NOT NULL,
+  color    TEXT NOT NULL DEFAULT '#8a8886',
+  UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS todo_tags (
+  todo_id  INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  tag_id   INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+  PRIMARY KEY (todo_id, tag_id)
+);
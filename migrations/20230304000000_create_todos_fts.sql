@@ -0,0 +1,23 @@
+-- Full-text index over the searchable text of a todo. It is an external
+-- content table, so the triggers below have to mirror every change to `todos`.
+-- Further text columns get added here alongside `title`.
+CREATE VIRTUAL TABLE IF NOT EXISTS todos_fts USING fts5 (
+  title,
+  content = 'todos',
+  content_rowid = 'id'
+);
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_insert AFTER INSERT ON todos BEGIN
+  INSERT INTO todos_fts (rowid, title) VALUES (new.id, new.title);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_delete AFTER DELETE ON todos BEGIN
+  INSERT INTO todos_fts (todos_fts, rowid, title) VALUES ('delete', old.id, old.title);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_update AFTER UPDATE OF title ON todos BEGIN
+  INSERT INTO todos_fts (todos_fts, rowid, title) VALUES ('delete', old.id, old.title);
+  INSERT INTO todos_fts (rowid, title) VALUES (new.id, new.title);
+END;
+
+INSERT INTO todos_fts (todos_fts) VALUES ('rebuild');
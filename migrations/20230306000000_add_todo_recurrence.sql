@@ -0,0 +1,2 @@
+-- An RRULE such as `FREQ=WEEKLY;BYDAY=MO,WE`, see `todo_app::recurrence`.
+ALTER TABLE todos ADD COLUMN recurrence TEXT;
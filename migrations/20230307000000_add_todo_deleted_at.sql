@@ -0,0 +1,4 @@
+-- Deleted todos stay in the trash until they're restored or purged.
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS todos_deleted_at ON todos (deleted_at);
@@ -0,0 +1,15 @@
+ALTER TABLE todos ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+
+-- Manual order. Fractional so a todo can be moved between two others by
+-- giving it the midpoint of their positions, without renumbering the rest.
+ALTER TABLE todos ADD COLUMN position REAL;
+
+UPDATE todos SET position = id;
+
+-- New todos go to the end unless they are inserted with a position.
+CREATE TRIGGER IF NOT EXISTS todos_default_position AFTER INSERT ON todos
+WHEN new.position IS NULL BEGIN
+  UPDATE todos SET position = (SELECT COALESCE(MAX(position), 0) + 1 FROM todos) WHERE id = new.id;
+END;
+
+CREATE INDEX IF NOT EXISTS todos_position ON todos (position);
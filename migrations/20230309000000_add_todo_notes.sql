@@ -0,0 +1,29 @@
+ALTER TABLE todos ADD COLUMN notes TEXT NOT NULL DEFAULT '';
+
+-- Index the notes for full-text search along with the title.
+DROP TRIGGER IF EXISTS todos_fts_insert;
+DROP TRIGGER IF EXISTS todos_fts_delete;
+DROP TRIGGER IF EXISTS todos_fts_update;
+DROP TABLE IF EXISTS todos_fts;
+
+CREATE VIRTUAL TABLE IF NOT EXISTS todos_fts USING fts5 (
+  title,
+  notes,
+  content = 'todos',
+  content_rowid = 'id'
+);
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_insert AFTER INSERT ON todos BEGIN
+  INSERT INTO todos_fts (rowid, title, notes) VALUES (new.id, new.title, new.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_delete AFTER DELETE ON todos BEGIN
+  INSERT INTO todos_fts (todos_fts, rowid, title, notes) VALUES ('delete', old.id, old.title, old.notes);
+END;
+
+CREATE TRIGGER IF NOT EXISTS todos_fts_update AFTER UPDATE OF title, notes ON todos BEGIN
+  INSERT INTO todos_fts (todos_fts, rowid, title, notes) VALUES ('delete', old.id, old.title, old.notes);
+  INSERT INTO todos_fts (rowid, title, notes) VALUES (new.id, new.title, new.notes);
+END;
+
+INSERT INTO todos_fts (todos_fts) VALUES ('rebuild');
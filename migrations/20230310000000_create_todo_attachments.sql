@@ -0,0 +1,12 @@
+-- Files are stored on disk under the attachment directory, named by `id`.
+CREATE TABLE IF NOT EXISTS todo_attachments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  todo_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL,
+  file_name TEXT NOT NULL,
+  content_type TEXT NOT NULL,
+  size INTEGER NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS todo_attachments_todo_id ON todo_attachments (todo_id);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS todo_comments (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  todo_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL,
+  body TEXT NOT NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  edited_at DATETIME
+);
+
+CREATE INDEX IF NOT EXISTS todo_comments_todo_id ON todo_comments (todo_id);
+
+-- Users @mentioned in a comment, looked up by user so they can be notified.
+CREATE TABLE IF NOT EXISTS todo_comment_mentions (
+  comment_id INTEGER NOT NULL REFERENCES todo_comments (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS todo_comment_mentions_user_id ON todo_comment_mentions (user_id);
@@ -0,0 +1,27 @@
+-- Append-only log of every change, see `todo_app::audit`. `before` and
+-- `after` hold JSON objects with just the fields that changed.
+CREATE TABLE IF NOT EXISTS audit_events (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  actor_id INTEGER,
+  action TEXT NOT NULL,
+  target_type TEXT NOT NULL,
+  target_id INTEGER NOT NULL,
+  todo_id INTEGER,
+  before TEXT,
+  after TEXT,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_todo_id ON audit_events (todo_id);
+CREATE INDEX IF NOT EXISTS audit_events_actor_id ON audit_events (actor_id);
+CREATE INDEX IF NOT EXISTS audit_events_action ON audit_events (action);
+
+CREATE TRIGGER IF NOT EXISTS audit_events_no_update BEFORE UPDATE ON audit_events
+BEGIN
+  SELECT RAISE(ABORT, 'audit events are append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_events_no_delete BEFORE DELETE ON audit_events
+BEGIN
+  SELECT RAISE(ABORT, 'audit events are append-only');
+END;
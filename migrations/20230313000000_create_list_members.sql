@@ -0,0 +1,14 @@
+-- Who can see a list and what they can do with its todos. The user who created
+-- a list is its first owner.
+CREATE TABLE IF NOT EXISTS list_members (
+  list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  role TEXT NOT NULL CHECK (role IN ('viewer', 'editor', 'owner')),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (list_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS list_members_user_id ON list_members (user_id);
+
+INSERT OR IGNORE INTO list_members (list_id, user_id, role)
+SELECT id, user_id, 'owner' FROM lists;
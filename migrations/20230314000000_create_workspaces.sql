@@ -0,0 +1,44 @@
+-- Workspaces separate teams from each other. Every list and todo belongs to
+-- exactly one, and only its members can see what's in it.
+CREATE TABLE IF NOT EXISTS workspaces (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL,
+  created_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS workspace_members (
+  workspace_id INTEGER NOT NULL REFERENCES workspaces (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  role TEXT NOT NULL CHECK (role IN ('member', 'admin')),
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS workspace_members_user_id ON workspace_members (user_id);
+
+ALTER TABLE lists ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+ALTER TABLE todos ADD COLUMN workspace_id INTEGER REFERENCES workspaces (id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS lists_workspace_id ON lists (workspace_id);
+CREATE INDEX IF NOT EXISTS todos_workspace_id ON todos (workspace_id);
+
+-- Everyone starts out with a personal workspace holding what they had so far.
+INSERT INTO workspaces (name, created_by)
+SELECT username || '''s workspace', id FROM users;
+
+INSERT INTO workspace_members (workspace_id, user_id, role)
+SELECT id, created_by, 'admin' FROM workspaces;
+
+UPDATE lists SET workspace_id = (SELECT id FROM workspaces WHERE created_by = lists.user_id);
+
+-- Todos in shared lists move with their list, and so do the list's members.
+UPDATE todos SET workspace_id = COALESCE(
+  (SELECT workspace_id FROM lists WHERE id = todos.list_id),
+  (SELECT id FROM workspaces WHERE created_by = todos.user_id)
+);
+
+INSERT OR IGNORE INTO workspace_members (workspace_id, user_id, role)
+SELECT lists.workspace_id, list_members.user_id, 'member'
+FROM list_members JOIN lists ON lists.id = list_members.list_id
+WHERE lists.workspace_id IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Who is doing a todo, as opposed to `user_id`, who created it.
+ALTER TABLE todos ADD COLUMN assignee_id INTEGER REFERENCES users (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS todos_assignee_id ON todos (assignee_id);
@@ -0,0 +1,10 @@
+-- `todo_id` can't be completed until `blocker_id` is.
+CREATE TABLE IF NOT EXISTS todo_dependencies (
+  todo_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  blocker_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (todo_id, blocker_id),
+  CHECK (todo_id != blocker_id)
+);
+
+CREATE INDEX IF NOT EXISTS todo_dependencies_blocker_id ON todo_dependencies (blocker_id);
@@ -0,0 +1,32 @@
+-- The columns of a list's board, left to right. Todos in a status that
+-- `is_done` are completed, which keeps `todos.completed` meaningful.
+CREATE TABLE IF NOT EXISTS list_statuses (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  position INTEGER NOT NULL,
+  is_done BOOLEAN NOT NULL DEFAULT false,
+  UNIQUE (list_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS list_statuses_list_id ON list_statuses (list_id, position);
+
+ALTER TABLE todos ADD COLUMN status_id INTEGER REFERENCES list_statuses (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS todos_status_id ON todos (status_id);
+
+INSERT INTO list_statuses (list_id, name, position, is_done)
+SELECT lists.id, defaults.name, defaults.position, defaults.is_done
+FROM lists, (
+  SELECT 'Backlog' AS name, 0 AS position, false AS is_done
+  UNION ALL SELECT 'In Progress', 1, false
+  UNION ALL SELECT 'Review', 2, false
+  UNION ALL SELECT 'Done', 3, true
+) AS defaults;
+
+UPDATE todos SET status_id = (
+  SELECT id FROM list_statuses
+  WHERE list_statuses.list_id = todos.list_id AND list_statuses.is_done = todos.completed
+  ORDER BY position LIMIT 1
+)
+WHERE list_id IS NOT NULL;
@@ -0,0 +1,7 @@
+-- Secret tokens for the iCalendar feed at `/feeds/<token>.ics`, which calendar
+-- apps fetch without a session. One per user, gone once revoked.
+CREATE TABLE IF NOT EXISTS feed_tokens (
+  user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+  token TEXT NOT NULL UNIQUE,
+  created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- The UID and resource name a CalDAV client gave a todo it created, so it
+-- finds the todo under them again. Other todos are `<id>.ics` on the server.
+CREATE TABLE IF NOT EXISTS caldav_objects (
+  todo_id INTEGER PRIMARY KEY REFERENCES todos (id) ON DELETE CASCADE,
+  uid TEXT NOT NULL,
+  name TEXT NOT NULL UNIQUE
+);
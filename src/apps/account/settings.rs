@@ -4,6 +4,7 @@ use thaw_utils::mount_style;
 
 use crate::auth::*;
 use crate::apps::layout::AppLayout;
+use crate::todo_app::feeds::CalendarFeed;
 
 
 #[component]
@@ -23,6 +24,8 @@ pub fn Settings(logout_action: ServerAction<Logout>) -> impl IntoView {
             <CardHeader>
                 <Text class="card-header-title" tag=TextTag::H1>"Settings"</Text>
             </CardHeader>
+            <CalendarFeed />
+            <Divider />
             <Logout action=logout_action/>
         </Card>
     }
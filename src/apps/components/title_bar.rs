@@ -1,12 +1,14 @@
 use leptos::{
     prelude::*,
     either::Either,
-    ev::MouseEvent,
+    ev::{MouseEvent, SubmitEvent},
 };
-use leptos_router::hooks::use_navigate;
+use leptos_router::{hooks::use_navigate, location::Url};
 use thaw::*;
 use thaw_utils::mount_style;
 
+use crate::todo_app::workspaces::{change_workspace, WorkspaceMenuItems};
+
 
 #[component]
 pub fn TitleBar(
@@ -16,6 +18,18 @@ pub fn TitleBar(
     mount_style("title-bar", include_str!("./title_bar.css"));
     let navigate_home = use_navigate();
     let navigate = use_navigate();
+    let navigate_search = use_navigate();
+    let search = RwSignal::new(String::new());
+    let on_search = move |ev: SubmitEvent| {
+        ev.prevent_default();
+        let text = search.get_untracked();
+        if !text.trim().is_empty() {
+            navigate_search(
+                &format!("/search?q={}", Url::escape(text.trim())),
+                Default::default(),
+            );
+        }
+    };
 
     let theme = Theme::use_rw_theme();
     let theme_name = Memo::new(move |_| {
@@ -46,14 +60,35 @@ pub fn TitleBar(
                     <Icon icon=icondata::LuCheckCircle width="36px" height="36px" />
                     <Text class="title-text" tag=TextTag::H1>{title}</Text>
                 </Flex>
-                <Flex>
+                <Flex align=FlexAlign::Center>
+                    {is_guest.then(|| view! {
+                        <form on:submit=on_search>
+                            <Input value=search input_type=InputType::Search placeholder="Search todos">
+                                <InputPrefix slot>
+                                    <Icon icon=icondata::LuSearch />
+                                </InputPrefix>
+                            </Input>
+                        </form>
+                    })}
                     <Menu position=MenuPosition::BottomEnd on_select=move |value: String| match value.as_str() {
                         "settings" => navigate("/settings", Default::default()),
+                        "lists" => navigate("/lists", Default::default()),
+                        "assignments" => navigate("/assignments", Default::default()),
+                        "calendar" => navigate("/calendar", Default::default()),
+                        "import" => navigate("/import", Default::default()),
+                        "tags" => navigate("/tags", Default::default()),
+                        "trash" => navigate("/trash", Default::default()),
+                        "activity" => navigate("/activity", Default::default()),
+                        "workspace" => navigate("/workspace", Default::default()),
                         "login" => navigate("/login", Default::default()),
                         "signup" => navigate("/signup", Default::default()),
                         "Dark" => change_theme(MouseEvent::new("click").unwrap()),
                         "Light" => change_theme(MouseEvent::new("click").unwrap()),
-                        _ => {}
+                        value => {
+                            if let Some(id) = value.strip_prefix("workspace:").and_then(|id| id.parse().ok()) {
+                                change_workspace(id);
+                            }
+                        }
                     }>
                         <MenuTrigger slot>
                             <Button 
@@ -79,7 +114,20 @@ pub fn TitleBar(
                             })
                         }}
                         {match is_guest {
-                            true => Either::Left(view! { <MenuItem icon=icondata::LuSettings value="settings">Settings</MenuItem> }),
+                            true => Either::Left(view! {
+                                <>
+                                    <WorkspaceMenuItems />
+                                    <MenuItem icon=icondata::LuUsers value="workspace">Workspace</MenuItem>
+                                    <MenuItem icon=icondata::LuList value="lists">Lists</MenuItem>
+                                    <MenuItem icon=icondata::LuUserCheck value="assignments">"My assignments"</MenuItem>
+                                    <MenuItem icon=icondata::LuCalendarDays value="calendar">Calendar</MenuItem>
+                                    <MenuItem icon=icondata::LuTags value="tags">Tags</MenuItem>
+                                    <MenuItem icon=icondata::LuUpload value="import">Import</MenuItem>
+                                    <MenuItem icon=icondata::LuTrash2 value="trash">Trash</MenuItem>
+                                    <MenuItem icon=icondata::LuHistory value="activity">Activity</MenuItem>
+                                    <MenuItem icon=icondata::LuSettings value="settings">Settings</MenuItem>
+                                </>
+                            }),
                             false => Either::Right(view! {
                                 <>
                                     <MenuItem icon=icondata::LuLogIn value="login">Login</MenuItem>
@@ -0,0 +1,173 @@
+use leptos::{prelude::*, Params};
+use leptos_router::{
+    hooks::{use_params, use_query_map},
+    params::Params,
+};
+
+use thaw::{Flex, FlexJustify, Link};
+
+use crate::apps::layout::AppLayout;
+use crate::todo_app::{
+    assignees::Assignments, audit::AuditFeed, board::Board, calendar::TodoCalendar, imports::ImportTodos, lists::*, search::SearchResults, tags::Tags, trash::Trash,
+    workspaces::WorkspaceSettings, Todos,
+};
+
+#[derive(Params, Clone, Debug, PartialEq)]
+pub struct ListParams {
+    pub id: Option<i64>,
+}
+
+#[component]
+pub fn ListsPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Lists".to_owned()>
+            <Lists />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn TagsPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Tags".to_owned()>
+            <Tags />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn TrashPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Trash".to_owned()>
+            <Trash />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn ActivityPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Activity".to_owned()>
+            <AuditFeed />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn AssignmentsPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="My assignments".to_owned()>
+            <Assignments />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn CalendarPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Calendar".to_owned()>
+            <TodoCalendar />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn ImportPage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Import".to_owned()>
+            <ImportTodos />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn WorkspacePage(is_guest: bool) -> impl IntoView {
+    view! {
+        <AppLayout is_guest=is_guest title="Workspace".to_owned()>
+            <WorkspaceSettings />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn SearchPage(is_guest: bool) -> impl IntoView {
+    let query = use_query_map();
+    let text = Signal::derive(move || query.with(|query| query.get("q").unwrap_or_default()));
+
+    view! {
+        <AppLayout is_guest=is_guest title="Search".to_owned()>
+            <SearchResults text />
+        </AppLayout>
+    }
+}
+
+#[component]
+pub fn BoardPage(is_guest: bool) -> impl IntoView {
+    let params = use_params::<ListParams>();
+    let list = Resource::new(
+        move || params.get().ok().and_then(|params| params.id),
+        move |id| async move {
+            match id {
+                Some(id) => get_list(id).await.ok(),
+                None => None,
+            }
+        },
+    );
+
+    view! {
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            {move || {
+                list.get().map(|list| match list {
+                    Some(list) => view! {
+                        <AppLayout is_guest=is_guest title=list.name>
+                            <Flex justify=FlexJustify::End>
+                                <Link href=format!("/lists/{}", list.id)>"Table view"</Link>
+                            </Flex>
+                            <Board list_id=list.id role=list.role />
+                        </AppLayout>
+                    }
+                        .into_any(),
+                    None => view! {
+                        <AppLayout is_guest=is_guest title="Board".to_owned()>
+                            <p>"List not found."</p>
+                        </AppLayout>
+                    }
+                        .into_any(),
+                })
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+pub fn ListPage(is_guest: bool) -> impl IntoView {
+    let params = use_params::<ListParams>();
+    let list = Resource::new(
+        move || params.get().ok().and_then(|params| params.id),
+        move |id| async move {
+            match id {
+                Some(id) => get_list(id).await.ok(),
+                None => None,
+            }
+        },
+    );
+
+    view! {
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            {move || {
+                list.get().map(|list| match list {
+                    Some(list) => view! {
+                        <Todos is_guest=is_guest list_id=list.id title=list.name />
+                    }
+                        .into_any(),
+                    None => view! {
+                        <AppLayout is_guest=is_guest title="Todos".to_owned()>
+                            <p>"List not found."</p>
+                        </AppLayout>
+                    }
+                        .into_any(),
+                })
+            }}
+        </Transition>
+    }
+}
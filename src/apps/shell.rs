@@ -1,9 +1,10 @@
 use leptos::prelude::*;
 use leptos_meta::*;
 use leptos_router::{components::*, *};
-use thaw::{ssr::SSRMountStyleProvider, ConfigProvider};
+use thaw::{ssr::SSRMountStyleProvider, ConfigProvider, ToasterProvider};
 
 use crate::apps::account::*;
+use crate::apps::routes::*;
 use crate::auth::*;
 use crate::todo_app::*;
 
@@ -49,6 +50,7 @@ pub fn App() -> impl IntoView {
 
     view! {
         <ConfigProvider>
+            <ToasterProvider>
                 <Router>
                     <FlatRoutes fallback=|| "Not found.">
                         <Route path=path!("") view=move ||
@@ -64,6 +66,149 @@ pub fn App() -> impl IntoView {
                                 </Transition>
                             }
                         />
+                        <Route path=path!("lists") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<ListsPage is_guest=false />},
+                                        _ => view!{<ListsPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("tags") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<TagsPage is_guest=false />},
+                                        _ => view!{<TagsPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("trash") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<TrashPage is_guest=false />},
+                                        _ => view!{<TrashPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("activity") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<ActivityPage is_guest=false />},
+                                        _ => view!{<ActivityPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("assignments") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<AssignmentsPage is_guest=false />},
+                                        _ => view!{<AssignmentsPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("calendar") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<CalendarPage is_guest=false />},
+                                        _ => view!{<CalendarPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("import") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<ImportPage is_guest=false />},
+                                        _ => view!{<ImportPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("workspace") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<WorkspacePage is_guest=false />},
+                                        _ => view!{<WorkspacePage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("search") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<SearchPage is_guest=false />},
+                                        _ => view!{<SearchPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("lists/:id") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<ListPage is_guest=false />},
+                                        _ => view!{<ListPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
+                        <Route path=path!("lists/:id/board") view=move ||
+                            view! {
+                                <Transition fallback=move || view! {
+                                    <p>"Loading..."</p>
+                                }>
+                                   {move || match user.get().map(|r| r.ok().flatten().is_none()) {
+                                        Some(true) => view!{<BoardPage is_guest=false />},
+                                        _ => view!{<BoardPage is_guest=true />}
+                                    }
+                                }
+                                </Transition>
+                            }
+                        />
                         <ProtectedRoute
                             path=path!("signup")
                             condition=move || user.get().map(|r| r.ok().flatten().is_none())
@@ -84,6 +229,7 @@ pub fn App() -> impl IntoView {
                         />
                     </FlatRoutes>
                 </Router>
+            </ToasterProvider>
         </ConfigProvider>
     }
 }
@@ -42,6 +42,13 @@ pub mod ssr {
     pub use async_trait::async_trait;
     pub use bcrypt::{hash, verify, DEFAULT_COST};
 
+    impl UserPasshash {
+        /// Whether `password` is the one this hash was made from.
+        pub fn matches(&self, password: &str) -> bool {
+            verify(password, &self.0).unwrap_or(false)
+        }
+    }
+
     impl User {
         pub async fn get_with_passhash(
             id: i64,
@@ -197,6 +204,7 @@ pub async fn login(
     remember: Option<String>,
 ) -> Result<(), ServerFnError> {
     use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
 
     let pool = pool()?;
     let auth = auth()?;
@@ -208,6 +216,8 @@ pub async fn login(
 
     match verify(password, &expected_passhash)? {
         true => {
+            record(&pool, Some(user.id), AuditAction::UserLoggedIn, AuditTarget::user(user.id), None, None)
+                .await?;
             auth.login_user(user.id);
             auth.remember_user(remember.is_some());
             leptos_axum::redirect("/");
@@ -227,6 +237,8 @@ pub async fn signup(
     remember: Option<String>,
 ) -> Result<(), ServerFnError> {
     use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::workspaces::ssr::create_personal_workspace;
 
     let pool = pool()?;
     let auth = auth()?;
@@ -239,12 +251,26 @@ pub async fn signup(
 
     let password_hashed = hash(password, DEFAULT_COST).unwrap();
 
-    sqlx::query("INSERT INTO users (username, password) VALUES (?,?)")
+    let mut tx = pool.begin().await?;
+    let user_id = sqlx::query_scalar::<_, i64>("INSERT INTO users (username, password) VALUES (?,?) RETURNING id")
         .bind(username.clone())
         .bind(password_hashed)
-        .execute(&pool)
+        .fetch_one(&mut *tx)
         .await?;
 
+    create_personal_workspace(user_id, &username, &mut tx).await?;
+
+    record(
+        &mut *tx,
+        Some(user_id),
+        AuditAction::UserSignedUp,
+        AuditTarget::user(user_id),
+        None,
+        Some(serde_json::json!({ "username": username })),
+    )
+    .await?;
+    tx.commit().await?;
+
     let user =
         User::get_from_username(username, &pool)
             .await
@@ -263,9 +289,15 @@ pub async fn signup(
 #[server(Logout, "/api")]
 pub async fn logout() -> Result<(), ServerFnError> {
     use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
 
+    let pool = pool()?;
     let auth = auth()?;
 
+    if let Some(user) = &auth.current_user {
+        record(&pool, Some(user.id), AuditAction::UserLoggedOut, AuditTarget::user(user.id), None, None)
+            .await?;
+    }
     auth.logout_user();
     leptos_axum::redirect("/");
 
@@ -1,10 +1,14 @@
 use http::status::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::{convert::Infallible, str::FromStr};
 use thiserror::Error;
 
-#[derive(Debug, Clone, Error)]
+#[derive(Debug, Clone, Error, Serialize, Deserialize)]
 pub enum TodoAppError {
     #[error("Not Found")]
     NotFound,
+    #[error("Forbidden")]
+    Forbidden,
     #[error("Internal Server Error")]
     InternalServerError,
 }
@@ -13,9 +17,32 @@ impl TodoAppError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             TodoAppError::NotFound => StatusCode::NOT_FOUND,
+            TodoAppError::Forbidden => StatusCode::FORBIDDEN,
             TodoAppError::InternalServerError => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// Server functions send custom errors over the wire as their `Display` string,
+// so this has to parse back whatever the `#[error]` attributes above produce.
+impl FromStr for TodoAppError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "Not Found" => TodoAppError::NotFound,
+            "Forbidden" => TodoAppError::Forbidden,
+            _ => TodoAppError::InternalServerError,
+        })
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<sqlx::Error> for TodoAppError {
+    fn from(error: sqlx::Error) -> Self {
+        log::error!("{error:?}");
+        TodoAppError::InternalServerError
+    }
+}
@@ -1,9 +1,9 @@
 use axum::{
     body::Body as AxumBody,
-    extract::{Path, State},
+    extract::{DefaultBodyLimit, Path, State},
     http::Request,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{any, get, post},
     Router,
 };
 use axum_session::{SessionConfig, SessionLayer, SessionStore};
@@ -11,11 +11,22 @@ use axum_session_auth::{AuthConfig, AuthSessionLayer};
 use axum_session_sqlx::SessionSqlitePool;
 use leptos::{config::get_configuration, logging::log, prelude::provide_context};
 use leptos_axum::{generate_route_list, handle_server_fns_with_context, LeptosRoutes};
-use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
 use todo::{
     apps::shell::{app_shell, App} ,
     auth::{ssr::AuthSession, User},
     state::AppState,
+    todo_app::{
+        attachments::ssr::{download_attachment, upload_attachment, AttachmentConfig},
+        caldav::ssr::{caldav, caldav_well_known},
+        feeds::ssr::todo_feed,
+        imports::ssr::{import_todos, IMPORT_MAX_SIZE},
+        live::ssr::{todo_events, ChangeHub, PendingChanges},
+        trash::ssr::{retention_days, spawn_purge_task},
+    },
 };
 
 async fn server_fn_handler(
@@ -23,17 +34,31 @@ async fn server_fn_handler(
     auth_session: AuthSession,
     path: Path<String>,
     request: Request<AxumBody>,
-) -> impl IntoResponse {
+) -> Response {
     log!("{:?}", path);
 
-    handle_server_fns_with_context(
-        move || {
-            provide_context(auth_session.clone());
-            provide_context(app_state.pool.clone());
+    let pending = PendingChanges::default();
+    let response = handle_server_fns_with_context(
+        {
+            let app_state = app_state.clone();
+            let pending = pending.clone();
+            move || {
+                provide_context(auth_session.clone());
+                provide_context(app_state.pool.clone());
+                provide_context(app_state.attachments.clone());
+                provide_context(pending.clone());
+            }
         },
         request,
     )
     .await
+    .into_response();
+
+    // A server function that failed rolled back whatever it had queued.
+    if response.status().is_success() {
+        app_state.changes.publish_pending(&pending);
+    }
+    response
 }
 
 async fn leptos_routes_handler(
@@ -57,8 +82,12 @@ async fn leptos_routes_handler(
 async fn main() {
     simple_logger::init_with_level(log::Level::Info).expect("couldn't initialize logging");
 
+    // The database is created on first run; the migrations set it up.
+    let options = SqliteConnectOptions::new()
+        .filename("Todos.db")
+        .create_if_missing(true);
     let pool = SqlitePoolOptions::new()
-        .connect("sqlite:Todos.db")
+        .connect_with(options)
         .await
         .expect("Could not make pool.");
 
@@ -76,6 +105,10 @@ async fn main() {
         eprintln!("{e:?}");
     }
 
+    let attachments = AttachmentConfig::from_env();
+    let changes = ChangeHub::default();
+    spawn_purge_task(pool.clone(), retention_days(), attachments.clone(), changes.clone());
+
     // Setting this to None means we'll be using cargo-leptos and its env vars
     let conf = get_configuration(None).unwrap();
     let leptos_options = conf.leptos_options;
@@ -86,6 +119,8 @@ async fn main() {
         leptos_options,
         pool: pool.clone(),
         routes: routes.clone(),
+        attachments: attachments.clone(),
+        changes,
     };
 
     // build our application with a route
@@ -94,6 +129,22 @@ async fn main() {
             "/api/*fn_name",
             get(server_fn_handler).post(server_fn_handler),
         )
+        .route(
+            "/todos/:todo_id/attachments",
+            // Leave some room for the multipart framing around the file.
+            post(upload_attachment).layer(DefaultBodyLimit::max(attachments.max_size + 64 * 1024)),
+        )
+        .route("/attachments/:id", get(download_attachment))
+        .route("/todos/events", get(todo_events))
+        .route(
+            "/todos/import",
+            post(import_todos).layer(DefaultBodyLimit::max(IMPORT_MAX_SIZE + 64 * 1024)),
+        )
+        .route("/feeds/:file", get(todo_feed))
+        .route("/.well-known/caldav", any(caldav_well_known))
+        .route("/dav", any(caldav))
+        .route("/dav/", any(caldav))
+        .route("/dav/*path", any(caldav))
         .leptos_routes_with_handler(routes, get(leptos_routes_handler))
         .fallback(leptos_axum::file_and_error_handler::<AppState, _>(
             app_shell,
@@ -3,6 +3,8 @@ use leptos::prelude::LeptosOptions;
 use leptos_axum::AxumRouteListing;
 use sqlx::SqlitePool;
 
+use crate::todo_app::{attachments::ssr::AttachmentConfig, live::ssr::ChangeHub};
+
 /// This takes advantage of Axum's SubStates feature by deriving FromRef. This is the only way to have more than one
 /// item in Axum's State. Leptos requires you to have leptosOptions in your State struct for the leptos route handlers
 #[derive(FromRef, Debug, Clone)]
@@ -10,4 +12,6 @@ pub struct AppState {
     pub leptos_options: LeptosOptions,
     pub pool: SqlitePool,
     pub routes: Vec<AxumRouteListing>,
+    pub attachments: AttachmentConfig,
+    pub changes: ChangeHub,
 }
\ No newline at end of file
@@ -0,0 +1,289 @@
+use leptos::prelude::*;
+use leptos::reactive::wrappers::write::SignalSetter;
+use thaw::*;
+
+use super::dependencies::{set_completed, DependencyBadges};
+use super::live::{provide_todo_changes, use_todo_changes};
+use super::{Todo, UpdateTodo};
+use crate::{auth::User, error_template::ErrorTemplate, errors::TodoAppError};
+
+/// How many users the picker suggests at a time.
+pub const USER_SEARCH_LIMIT: i64 = 10;
+
+/// Members of the current workspace whose username starts with `text`.
+#[server(SearchUsers, "/api")]
+pub async fn search_users(text: String) -> Result<Vec<User>, ServerFnError<TodoAppError>> {
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+    use futures::future::join_all;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let text = text.trim().trim_start_matches('@');
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids = sqlx::query_scalar::<_, i64>(
+        "SELECT users.id FROM users
+        JOIN workspace_members ON workspace_members.user_id = users.id
+        WHERE workspace_members.workspace_id = ? AND users.username LIKE ? ESCAPE '\\'
+        ORDER BY users.username LIMIT ?",
+    )
+    .bind(current_workspace(&user, &pool).await?)
+    .bind(format!("{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")))
+    .bind(USER_SEARCH_LIMIT)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    let pool = &pool;
+    Ok(join_all(ids.into_iter().map(|id| User::get(id, pool)))
+        .await
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// Assigns a todo to someone who can see it, or unassigns it with `None`.
+#[server(AssignTodo, "/api")]
+pub async fn assign_todo(
+    id: u32,
+    assignee_id: Option<i64>,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    if let Some(assignee_id) = assignee_id {
+        let assignee = User::get(assignee_id, &pool)
+            .await
+            .ok_or(TodoAppError::NotFound)?;
+        if todo_role(&todo, &assignee, &pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .is_none()
+        {
+            return Err(ServerFnError::ServerError(format!(
+                "{} can't see this todo. Share its list with them first.",
+                assignee.username
+            )));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todos SET assignee_id = ? WHERE id = ?")
+        .bind(assignee_id)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoUpdated, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Everything assigned to the current user in the current workspace, across
+/// all lists, open todos first and then by due date.
+#[server(GetAssignedTodos, "/api")]
+pub async fn get_assigned_todos() -> Result<Vec<Todo>, ServerFnError<TodoAppError>> {
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+    use futures::future::join_all;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(Vec::new());
+    };
+
+    let todos = sqlx::query_as::<_, SqlTodo>(
+        "SELECT * FROM todos
+        WHERE assignee_id = ?1 AND workspace_id = ?2 AND deleted_at IS NULL
+        AND ((list_id IS NULL AND user_id = ?1)
+            OR list_id IN (SELECT list_id FROM list_members WHERE user_id = ?1))
+        ORDER BY completed, due_at IS NULL, due_at, id",
+    )
+    .bind(user.id)
+    .bind(current_workspace(&user, &pool).await?)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(join_all(todos.into_iter().map(|todo| todo.into_todo(&pool))).await)
+}
+
+/// Picks the assignee of a todo, searching the workspace's members by username.
+#[component]
+pub fn AssigneePicker(
+    todo_id: u32,
+    assignee: Option<User>,
+    assign_todo: ServerAction<AssignTodo>,
+) -> impl IntoView {
+    let text = RwSignal::new(String::new());
+    // Suggestions are only needed once someone types, which happens in the browser.
+    let users = LocalResource::new(move || {
+        let text = text.get();
+        async move {
+            if text.trim().is_empty() {
+                Ok(Vec::new())
+            } else {
+                search_users(text).await
+            }
+        }
+    });
+    let is_assigned = assignee.is_some();
+    let placeholder = assignee
+        .map(|assignee| assignee.username)
+        .unwrap_or_else(|| "Unassigned".to_string());
+
+    view! {
+        <Flex align=FlexAlign::Center gap=FlexGap::Small>
+            <AutoComplete
+                value=text
+                placeholder
+                clear_after_select=true
+                blur_after_select=true
+                on_select=move |value: String| {
+                    if let Ok(assignee_id) = value.parse() {
+                        assign_todo.dispatch(AssignTodo { id: todo_id, assignee_id: Some(assignee_id) });
+                    }
+                }
+            >
+                {move || {
+                    users
+                        .get()
+                        .and_then(|users| users.take().ok())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|user| view! {
+                            <AutoCompleteOption value=user.id.to_string()>{user.username}</AutoCompleteOption>
+                        })
+                        .collect_view()
+                }}
+            </AutoComplete>
+            <Show when=move || is_assigned>
+                <span title="Unassign">
+                    <Button
+                        appearance=ButtonAppearance::Subtle
+                        icon=icondata::LuUserX
+                        on_click=move |_| {
+                            assign_todo.dispatch(AssignTodo { id: todo_id, assignee_id: None });
+                        }
+                    />
+                </span>
+            </Show>
+        </Flex>
+    }
+}
+
+/// The "My assignments" view.
+#[component]
+pub fn Assignments() -> impl IntoView {
+    let update_todo = ServerAction::<UpdateTodo>::new();
+    let assign_todo = ServerAction::<AssignTodo>::new();
+
+    provide_todo_changes();
+    let changes = use_todo_changes();
+
+    let todos = Resource::new(
+        move || (update_todo.version().get(), assign_todo.version().get(), changes.get()),
+        move |_| get_assigned_todos(),
+    );
+
+    view! {
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            <ErrorBoundary fallback=|errors| {
+                view! { <ErrorTemplate errors=errors/> }
+            }>
+                <Card>
+                    <Table>
+                        <TableHeader>
+                            <TableRow>
+                                <TableHeaderCell max_width=60.0>"Done"</TableHeaderCell>
+                                <TableHeaderCell resizable=true min_width=200.0>"Title"</TableHeaderCell>
+                                <TableHeaderCell max_width=120.0>"Due"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"Creator"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"List"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"Unassign"</TableHeaderCell>
+                            </TableRow>
+                        </TableHeader>
+                        <TableBody>
+                            {move || {
+                                todos.get().map(|todos| match todos {
+                                    Err(e) => {
+                                        view! {
+                                            <pre class="error">"Server Error: " {e.to_string()}</pre>
+                                        }
+                                            .into_any()
+                                    }
+                                    Ok(todos) => {
+                                        if todos.is_empty() {
+                                            view! { <p>"Nothing is assigned to you."</p> }.into_any()
+                                        } else {
+                                            todos
+                                                .into_iter()
+                                                .map(|todo| {
+                                                    let id = todo.id;
+                                                    let completed = todo.completed;
+                                                    let dependencies = todo.dependencies;
+                                                    view! {
+                                                        <TableRow>
+                                                            <TableCell>
+                                                                <Checkbox checked=(
+                                                                    Signal::derive(move || completed),
+                                                                    SignalSetter::map(move |completed| {
+                                                                        set_completed(id, completed, dependencies, update_todo);
+                                                                    }),
+                                                                ) />
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <TableCellLayout truncate=true>
+                                                                    {todo.title}
+                                                                    <DependencyBadges dependencies=todo.dependencies />
+                                                                </TableCellLayout>
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                {todo.due_at.map(|due| due.format("%Y-%m-%d").to_string())}
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <TableCellLayout truncate=true>
+                                                                    {todo.user.unwrap_or_default().username}
+                                                                </TableCellLayout>
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                {todo.list_id.map(|list_id| view! {
+                                                                    <Link href=format!("/lists/{list_id}")>"Open"</Link>
+                                                                })}
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <Button
+                                                                    on_click=move |_| {
+                                                                        assign_todo.dispatch(AssignTodo { id, assignee_id: None });
+                                                                    }
+                                                                    icon=icondata::LuUserX
+                                                                />
+                                                            </TableCell>
+                                                        </TableRow>
+                                                    }
+                                                })
+                                                .collect_view()
+                                                .into_any()
+                                        }
+                                    }
+                                })
+                            }}
+                        </TableBody>
+                    </Table>
+                </Card>
+            </ErrorBoundary>
+        </Transition>
+    }
+}
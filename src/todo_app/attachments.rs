@@ -0,0 +1,609 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use super::live::use_todo_changes;
+use crate::errors::TodoAppError;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoAttachment {
+    pub id: i64,
+    pub todo_id: u32,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: String,
+}
+
+impl TodoAttachment {
+    /// Where the file can be downloaded from, see `ssr::download_attachment`.
+    pub fn url(&self) -> String {
+        format!("/attachments/{}", self.id)
+    }
+
+    pub fn display_size(&self) -> String {
+        match self.size {
+            size if size < 1024 => format!("{size} B"),
+            size if size < 1024 * 1024 => format!("{:.1} KB", size as f64 / 1024.0),
+            size => format!("{:.1} MB", size as f64 / (1024.0 * 1024.0)),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::TodoAttachment;
+    use crate::auth::ssr::AuthSession;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::live::{ssr::ChangeHub, TodoChange};
+    use crate::todo_app::ssr::{accessible_todo, ListRole};
+    use axum::{
+        extract::{multipart::MultipartError, Multipart, Path, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use leptos::prelude::*;
+    use sqlx::SqlitePool;
+    use std::path::PathBuf;
+
+    /// Where attachments are stored and what is accepted. Read from the
+    /// `TODO_ATTACHMENTS_*` environment variables.
+    #[derive(Clone, Debug)]
+    pub struct AttachmentConfig {
+        pub dir: PathBuf,
+        pub max_size: usize,
+        /// Accepted content types. `image/*` style wildcards match a whole family.
+        pub allowed_types: Vec<String>,
+    }
+
+    impl AttachmentConfig {
+        pub const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+        pub const DEFAULT_ALLOWED_TYPES: &'static str = "image/*,text/plain,text/markdown,text/csv,application/pdf,application/zip,application/json";
+
+        pub fn from_env() -> Self {
+            let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+            Self {
+                dir: var("TODO_ATTACHMENTS_DIR")
+                    .unwrap_or_else(|| "attachments".to_string())
+                    .into(),
+                max_size: var("TODO_ATTACHMENTS_MAX_SIZE")
+                    .and_then(|size| size.parse().ok())
+                    .unwrap_or(Self::DEFAULT_MAX_SIZE),
+                allowed_types: var("TODO_ATTACHMENTS_TYPES")
+                    .unwrap_or_else(|| Self::DEFAULT_ALLOWED_TYPES.to_string())
+                    .split(',')
+                    .map(|content_type| content_type.trim().to_ascii_lowercase())
+                    .filter(|content_type| !content_type.is_empty())
+                    .collect(),
+            }
+        }
+
+        pub fn allows(&self, content_type: &str) -> bool {
+            let content_type = content_type.to_ascii_lowercase();
+            self.allowed_types.iter().any(|allowed| match allowed.strip_suffix("/*") {
+                Some(family) => content_type.split('/').next() == Some(family),
+                None => *allowed == content_type,
+            })
+        }
+
+        /// The type `data` really has, if it's one that can be attached. Files
+        /// with a signature go by it rather than by what the upload claimed, so
+        /// a program doesn't get in as `image/png`. Those without one are only
+        /// taken as text, and only if they are.
+        pub fn checked_type(&self, claimed: &str, data: &[u8]) -> Option<String> {
+            let claimed = claimed.to_ascii_lowercase();
+            let content_type = match infer::get(data) {
+                Some(kind) => kind.mime_type().to_string(),
+                None if (claimed.starts_with("text/") || claimed == "application/json")
+                    && std::str::from_utf8(data).is_ok() =>
+                {
+                    claimed
+                }
+                None => return None,
+            };
+            self.allows(&content_type).then_some(content_type)
+        }
+
+        /// Files are named after their attachment id, so they never depend on
+        /// anything the uploader picked.
+        pub fn path(&self, id: i64) -> PathBuf {
+            self.dir.join(id.to_string())
+        }
+    }
+
+    pub fn attachment_config() -> Result<AttachmentConfig, ServerFnError<TodoAppError>> {
+        use_context::<AttachmentConfig>()
+            .ok_or_else(|| ServerFnError::ServerError("Attachment config missing.".into()))
+    }
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlTodoAttachment {
+        pub id: i64,
+        pub todo_id: u32,
+        pub user_id: i64,
+        pub file_name: String,
+        pub content_type: String,
+        pub size: i64,
+        pub created_at: String,
+    }
+
+    impl SqlTodoAttachment {
+        pub fn into_attachment(self) -> TodoAttachment {
+            TodoAttachment {
+                id: self.id,
+                todo_id: self.todo_id,
+                file_name: self.file_name,
+                content_type: self.content_type,
+                size: self.size,
+                created_at: self.created_at,
+            }
+        }
+    }
+
+    /// Loads an attachment, failing unless `user` has at least `role` for the todo it belongs to.
+    pub async fn accessible_attachment(
+        id: i64,
+        user: &crate::auth::User,
+        role: ListRole,
+        pool: &SqlitePool,
+    ) -> Result<SqlTodoAttachment, ServerFnError<TodoAppError>> {
+        let attachment = sqlx::query_as::<_, SqlTodoAttachment>(
+            "SELECT * FROM todo_attachments WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(TodoAppError::from)?
+        .ok_or(TodoAppError::NotFound)?;
+
+        accessible_todo(attachment.todo_id, user, role, pool).await?;
+        Ok(attachment)
+    }
+
+    /// Deletes files whose attachment rows are gone. Rows go away with their
+    /// todo through `ON DELETE CASCADE`, which can't reach the disk.
+    pub async fn remove_orphaned_files(
+        pool: &SqlitePool,
+        config: &AttachmentConfig,
+    ) -> std::io::Result<usize> {
+        let mut entries = match tokio::fs::read_dir(&config.dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => return Err(error),
+        };
+
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            // Uploads in progress are written to `<id>.part` and skipped here.
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<i64>().ok())
+            else {
+                continue;
+            };
+            let exists = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM todo_attachments WHERE id = ?")
+                .bind(id)
+                .fetch_one(pool)
+                .await
+                .map_err(std::io::Error::other)?
+                > 0;
+            if !exists {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Like [`remove_orphaned_files`], for server functions that just deleted todos.
+    pub async fn remove_orphaned_files_logged(pool: &SqlitePool) {
+        let Ok(config) = attachment_config() else {
+            return;
+        };
+        if let Err(error) = remove_orphaned_files(pool, &config).await {
+            log::error!("Removing attachment files failed: {error:?}");
+        }
+    }
+
+    pub fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+        (status, message.into()).into_response()
+    }
+
+    pub fn server_fn_error_response(error: ServerFnError<TodoAppError>) -> Response {
+        match error {
+            ServerFnError::WrappedServerError(error) => {
+                error_response(error.status_code(), error.to_string())
+            }
+            error => error_response(StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+        }
+    }
+
+    fn too_large_response(config: &AttachmentConfig) -> Response {
+        error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Attachments can be at most {} bytes.", config.max_size),
+        )
+    }
+
+    /// The body limit on the route cuts off oversized uploads before the size
+    /// check in [`upload_attachment`] sees them, so they end up here.
+    fn multipart_error_response(error: MultipartError, config: &AttachmentConfig) -> Response {
+        match error.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => too_large_response(config),
+            status => error_response(status, error.body_text()),
+        }
+    }
+
+    /// `POST /todos/:todo_id/attachments` with the file in a multipart `file` field.
+    pub async fn upload_attachment(
+        State(pool): State<SqlitePool>,
+        State(config): State<AttachmentConfig>,
+        State(changes): State<ChangeHub>,
+        auth_session: AuthSession,
+        Path(todo_id): Path<u32>,
+        mut multipart: Multipart,
+    ) -> Response {
+        let Some(user) = auth_session.current_user else {
+            return server_fn_error_response(TodoAppError::Forbidden.into());
+        };
+        let todo = match accessible_todo(todo_id, &user, ListRole::Editor, &pool).await {
+            Ok(todo) if todo.deleted_at.is_none() => todo,
+            Ok(_) => return server_fn_error_response(TodoAppError::NotFound.into()),
+            Err(error) => return server_fn_error_response(error),
+        };
+
+        let mut field = loop {
+            match multipart.next_field().await {
+                Ok(Some(field)) if field.name() == Some("file") => break field,
+                Ok(Some(_)) => continue,
+                Ok(None) => return error_response(StatusCode::BAD_REQUEST, "No file was uploaded."),
+                Err(error) => return multipart_error_response(error, &config),
+            }
+        };
+
+        let file_name = field
+            .file_name()
+            .map(|name| name.rsplit(['/', '\\']).next().unwrap_or(name).trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "attachment".to_string());
+        let content_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        if !config.allows(&content_type) {
+            return error_response(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Files of type {content_type} can't be attached."),
+            );
+        }
+
+        let mut data = Vec::new();
+        loop {
+            match field.chunk().await {
+                Ok(Some(chunk)) => {
+                    if data.len() + chunk.len() > config.max_size {
+                        return too_large_response(&config);
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(error) => return multipart_error_response(error, &config),
+            }
+        }
+        let Some(content_type) = config.checked_type(&content_type, &data) else {
+            return error_response(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("The file isn't the {content_type} it claims to be."),
+            );
+        };
+
+        match store_attachment(&pool, &config, todo_id, user.id, &file_name, &content_type, &data).await {
+            Ok(attachment) => {
+                // This isn't a server function, so nothing publishes the change for us.
+                changes.publish(TodoChange {
+                    action: AuditAction::AttachmentAdded,
+                    target_type: "attachment".to_string(),
+                    target_id: attachment.id,
+                    todo_id: Some(todo_id),
+                    owner_id: Some(todo.user_id),
+                    list_id: todo.list_id,
+                    workspace_id: todo.workspace_id,
+                });
+                axum::Json(attachment).into_response()
+            }
+            Err(error) => {
+                log::error!("Storing attachment failed: {error:?}");
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "The file could not be stored.")
+            }
+        }
+    }
+
+    async fn store_attachment(
+        pool: &SqlitePool,
+        config: &AttachmentConfig,
+        todo_id: u32,
+        user_id: i64,
+        file_name: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Result<TodoAttachment, Box<dyn std::error::Error + Send + Sync>> {
+        let mut tx = pool.begin().await?;
+        let attachment = sqlx::query_as::<_, SqlTodoAttachment>(
+            "INSERT INTO todo_attachments (todo_id, user_id, file_name, content_type, size) VALUES (?, ?, ?, ?, ?) RETURNING *",
+        )
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(file_name)
+        .bind(content_type)
+        .bind(data.len() as i64)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tokio::fs::create_dir_all(&config.dir).await?;
+        let path = config.path(attachment.id);
+        let partial = path.with_extension("part");
+        tokio::fs::write(&partial, data).await?;
+        record(
+            &mut *tx,
+            Some(user_id),
+            AuditAction::AttachmentAdded,
+            AuditTarget::attachment(attachment.id, todo_id),
+            None,
+            Some(serde_json::json!({
+                "file_name": file_name,
+                "content_type": content_type,
+                "size": attachment.size,
+            })),
+        )
+        .await?;
+        tx.commit().await?;
+        tokio::fs::rename(&partial, &path).await?;
+
+        Ok(attachment.into_attachment())
+    }
+
+    /// `GET /attachments/:id`, always as a download so uploads can't run as pages.
+    pub async fn download_attachment(
+        State(pool): State<SqlitePool>,
+        State(config): State<AttachmentConfig>,
+        auth_session: AuthSession,
+        Path(id): Path<i64>,
+    ) -> Response {
+        let Some(user) = auth_session.current_user else {
+            return server_fn_error_response(TodoAppError::Forbidden.into());
+        };
+        let attachment = match accessible_attachment(id, &user, ListRole::Viewer, &pool).await {
+            Ok(attachment) => attachment,
+            Err(error) => return server_fn_error_response(error),
+        };
+
+        match tokio::fs::read(config.path(attachment.id)).await {
+            Ok(data) => {
+                let file_name = attachment.file_name.replace(['"', '\\', '\r', '\n'], "_");
+                (
+                    [
+                        (header::CONTENT_TYPE, attachment.content_type),
+                        (
+                            header::CONTENT_DISPOSITION,
+                            format!("attachment; filename=\"{file_name}\""),
+                        ),
+                        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+                    ],
+                    data,
+                )
+                    .into_response()
+            }
+            Err(error) => {
+                log::error!("Reading attachment {id} failed: {error:?}");
+                server_fn_error_response(TodoAppError::NotFound.into())
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        fn config() -> AttachmentConfig {
+            AttachmentConfig {
+                dir: PathBuf::from("attachments"),
+                max_size: AttachmentConfig::DEFAULT_MAX_SIZE,
+                allowed_types: AttachmentConfig::DEFAULT_ALLOWED_TYPES.split(',').map(str::to_string).collect(),
+            }
+        }
+
+        #[test]
+        fn allows_configured_types() {
+            let config = config();
+            assert!(config.allows("image/png"));
+            assert!(config.allows("IMAGE/WebP"));
+            assert!(config.allows("application/pdf"));
+            assert!(!config.allows("application/x-msdownload"));
+            assert!(!config.allows("imagex/png"));
+        }
+
+        #[test]
+        fn goes_by_the_file_signature() {
+            let config = config();
+            assert_eq!(config.checked_type("image/png", PNG).as_deref(), Some("image/png"));
+            // Whatever it's called, a PNG is a PNG.
+            assert_eq!(config.checked_type("application/pdf", PNG).as_deref(), Some("image/png"));
+            assert_eq!(config.checked_type("image/png", b"MZ\x90\0\x03\0\0\0"), None);
+            assert_eq!(config.checked_type("image/png", b"\x7fELF\x02\x01\x01\0"), None);
+            assert_eq!(config.checked_type("image/png", b"not really an image"), None);
+        }
+
+        #[test]
+        fn takes_text_only_when_it_is_text() {
+            let config = config();
+            assert_eq!(config.checked_type("text/plain", "Grüße".as_bytes()).as_deref(), Some("text/plain"));
+            assert_eq!(config.checked_type("application/json", b"{}").as_deref(), Some("application/json"));
+            assert_eq!(config.checked_type("text/plain", b"").as_deref(), Some("text/plain"));
+            assert_eq!(config.checked_type("text/plain", b"\xff\xfe\0binary"), None);
+            assert_eq!(config.checked_type("text/html", b"<script></script>"), None);
+        }
+    }
+}
+
+#[server(GetAttachments, "/api")]
+pub async fn get_attachments(
+    todo_id: u32,
+) -> Result<Vec<TodoAttachment>, ServerFnError<TodoAppError>> {
+    use self::ssr::SqlTodoAttachment;
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(todo_id, &user, ListRole::Viewer, &pool).await?;
+
+    Ok(sqlx::query_as::<_, SqlTodoAttachment>(
+        "SELECT * FROM todo_attachments WHERE todo_id = ? ORDER BY created_at, id",
+    )
+    .bind(todo_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .into_iter()
+    .map(SqlTodoAttachment::into_attachment)
+    .collect())
+}
+
+#[server(DeleteAttachment, "/api")]
+pub async fn delete_attachment(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+    let config = attachment_config()?;
+
+    let attachment = accessible_attachment(id, &user, ListRole::Editor, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM todo_attachments WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::AttachmentDeleted,
+        AuditTarget::attachment(id, attachment.todo_id),
+        Some(serde_json::json!({
+            "file_name": attachment.file_name,
+            "content_type": attachment.content_type,
+            "size": attachment.size,
+        })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    tx.commit().await.map_err(TodoAppError::from)?;
+
+    if let Err(error) = tokio::fs::remove_file(config.path(id)).await {
+        log::error!("Removing attachment {id} failed: {error:?}");
+    }
+    Ok(())
+}
+
+/// Posts `file` to the upload endpoint, returning the error message on failure.
+#[cfg(feature = "hydrate")]
+async fn upload(todo_id: u32, file: web_sys::File) -> Result<(), String> {
+    let form = web_sys::FormData::new().map_err(|_| "Could not read the file.".to_string())?;
+    form.append_with_blob_and_filename("file", &file, &file.name())
+        .map_err(|_| "Could not read the file.".to_string())?;
+
+    let response = gloo_net::http::Request::post(&format!("/todos/{todo_id}/attachments"))
+        .body(form)
+        .map_err(|error| error.to_string())?
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+
+    if response.ok() {
+        Ok(())
+    } else {
+        Err(response.text().await.unwrap_or_else(|_| response.status_text()))
+    }
+}
+
+#[component]
+pub fn TodoAttachments(todo_id: u32) -> impl IntoView {
+    let delete_attachment = ServerAction::<DeleteAttachment>::new();
+    let uploads = RwSignal::new(0);
+    let upload_error = RwSignal::new(None::<String>);
+
+    let changes = use_todo_changes();
+    let attachments = Resource::new(
+        move || (delete_attachment.version().get(), uploads.get(), changes.get()),
+        move |_| get_attachments(todo_id),
+    );
+
+    let custom_request = move |files: web_sys::FileList| {
+        upload_error.set(None);
+        #[cfg(feature = "hydrate")]
+        for index in 0..files.length() {
+            if let Some(file) = files.item(index) {
+                leptos::task::spawn_local(async move {
+                    if let Err(error) = upload(todo_id, file).await {
+                        upload_error.set(Some(error));
+                    }
+                    uploads.update(|uploads| *uploads += 1);
+                });
+            }
+        }
+        #[cfg(not(feature = "hydrate"))]
+        let _ = files;
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    attachments.get().map(|attachments| match attachments {
+                        Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                        Ok(attachments) if attachments.is_empty() => view! { <Text>"No attachments."</Text> }.into_any(),
+                        Ok(attachments) => {
+                            attachments
+                                .into_iter()
+                                .map(|attachment| {
+                                    let id = attachment.id;
+                                    let url = attachment.url();
+                                    let size = attachment.display_size();
+                                    view! {
+                                        <Flex align=FlexAlign::Center justify=FlexJustify::SpaceBetween>
+                                            <Link href=url>{attachment.file_name}</Link>
+                                            <Flex align=FlexAlign::Center>
+                                                <Caption1>{size}</Caption1>
+                                                <Button
+                                                    appearance=ButtonAppearance::Subtle
+                                                    icon=icondata::AiCloseCircleOutlined
+                                                    on_click=move |_| {
+                                                        delete_attachment.dispatch(DeleteAttachment { id });
+                                                    }
+                                                />
+                                            </Flex>
+                                        </Flex>
+                                    }
+                                })
+                                .collect_view()
+                                .into_any()
+                        }
+                    })
+                }}
+            </Transition>
+            {move || upload_error.get().map(|error| view! { <Text class="error">{error}</Text> })}
+            <Upload multiple=true custom_request>
+                <Button icon=icondata::LuPaperclip>"Attach files"</Button>
+            </Upload>
+        </Flex>
+    }
+}
@@ -0,0 +1,671 @@
+use chrono::NaiveDateTime;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use super::live::{provide_todo_changes, use_todo_changes};
+use crate::{auth::User, errors::TodoAppError};
+
+/// How many events the activity feed loads at a time.
+pub const AUDIT_PAGE_SIZE: i64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "snake_case"))]
+pub enum AuditAction {
+    TodoCreated,
+    TodoUpdated,
+    TodoDeleted,
+    TodoRestored,
+    TodoPurged,
+    TagAttached,
+    TagDetached,
+    DependencyAdded,
+    DependencyRemoved,
+    CommentAdded,
+    CommentEdited,
+    CommentDeleted,
+    AttachmentAdded,
+    AttachmentDeleted,
+    ListCreated,
+    ListRenamed,
+    ListDeleted,
+    ListMemberAdded,
+    ListMemberUpdated,
+    ListMemberRemoved,
+    StatusCreated,
+    StatusUpdated,
+    StatusDeleted,
+    TagCreated,
+    TagUpdated,
+    TagDeleted,
+    WorkspaceCreated,
+    WorkspaceMemberAdded,
+    WorkspaceMemberRemoved,
+    UserSignedUp,
+    UserLoggedIn,
+    UserLoggedOut,
+    FeedTokenCreated,
+    FeedTokenRevoked,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 34] = [
+        AuditAction::TodoCreated,
+        AuditAction::TodoUpdated,
+        AuditAction::TodoDeleted,
+        AuditAction::TodoRestored,
+        AuditAction::TodoPurged,
+        AuditAction::TagAttached,
+        AuditAction::TagDetached,
+        AuditAction::DependencyAdded,
+        AuditAction::DependencyRemoved,
+        AuditAction::CommentAdded,
+        AuditAction::CommentEdited,
+        AuditAction::CommentDeleted,
+        AuditAction::AttachmentAdded,
+        AuditAction::AttachmentDeleted,
+        AuditAction::ListCreated,
+        AuditAction::ListRenamed,
+        AuditAction::ListDeleted,
+        AuditAction::ListMemberAdded,
+        AuditAction::ListMemberUpdated,
+        AuditAction::ListMemberRemoved,
+        AuditAction::StatusCreated,
+        AuditAction::StatusUpdated,
+        AuditAction::StatusDeleted,
+        AuditAction::TagCreated,
+        AuditAction::TagUpdated,
+        AuditAction::TagDeleted,
+        AuditAction::WorkspaceCreated,
+        AuditAction::WorkspaceMemberAdded,
+        AuditAction::WorkspaceMemberRemoved,
+        AuditAction::UserSignedUp,
+        AuditAction::UserLoggedIn,
+        AuditAction::UserLoggedOut,
+        AuditAction::FeedTokenCreated,
+        AuditAction::FeedTokenRevoked,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::TodoCreated => "todo_created",
+            AuditAction::TodoUpdated => "todo_updated",
+            AuditAction::TodoDeleted => "todo_deleted",
+            AuditAction::TodoRestored => "todo_restored",
+            AuditAction::TodoPurged => "todo_purged",
+            AuditAction::TagAttached => "tag_attached",
+            AuditAction::TagDetached => "tag_detached",
+            AuditAction::DependencyAdded => "dependency_added",
+            AuditAction::DependencyRemoved => "dependency_removed",
+            AuditAction::CommentAdded => "comment_added",
+            AuditAction::CommentEdited => "comment_edited",
+            AuditAction::CommentDeleted => "comment_deleted",
+            AuditAction::AttachmentAdded => "attachment_added",
+            AuditAction::AttachmentDeleted => "attachment_deleted",
+            AuditAction::ListCreated => "list_created",
+            AuditAction::ListRenamed => "list_renamed",
+            AuditAction::ListDeleted => "list_deleted",
+            AuditAction::ListMemberAdded => "list_member_added",
+            AuditAction::ListMemberUpdated => "list_member_updated",
+            AuditAction::ListMemberRemoved => "list_member_removed",
+            AuditAction::StatusCreated => "status_created",
+            AuditAction::StatusUpdated => "status_updated",
+            AuditAction::StatusDeleted => "status_deleted",
+            AuditAction::TagCreated => "tag_created",
+            AuditAction::TagUpdated => "tag_updated",
+            AuditAction::TagDeleted => "tag_deleted",
+            AuditAction::WorkspaceCreated => "workspace_created",
+            AuditAction::WorkspaceMemberAdded => "workspace_member_added",
+            AuditAction::WorkspaceMemberRemoved => "workspace_member_removed",
+            AuditAction::UserSignedUp => "user_signed_up",
+            AuditAction::UserLoggedIn => "user_logged_in",
+            AuditAction::UserLoggedOut => "user_logged_out",
+            AuditAction::FeedTokenCreated => "feed_token_created",
+            AuditAction::FeedTokenRevoked => "feed_token_revoked",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == value)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditAction::TodoCreated => "Created todo",
+            AuditAction::TodoUpdated => "Updated todo",
+            AuditAction::TodoDeleted => "Moved todo to trash",
+            AuditAction::TodoRestored => "Restored todo",
+            AuditAction::TodoPurged => "Deleted todo permanently",
+            AuditAction::TagAttached => "Tagged todo",
+            AuditAction::TagDetached => "Untagged todo",
+            AuditAction::DependencyAdded => "Added blocker",
+            AuditAction::DependencyRemoved => "Removed blocker",
+            AuditAction::CommentAdded => "Commented",
+            AuditAction::CommentEdited => "Edited comment",
+            AuditAction::CommentDeleted => "Deleted comment",
+            AuditAction::AttachmentAdded => "Attached file",
+            AuditAction::AttachmentDeleted => "Removed attachment",
+            AuditAction::ListCreated => "Created list",
+            AuditAction::ListRenamed => "Renamed list",
+            AuditAction::ListDeleted => "Deleted list",
+            AuditAction::ListMemberAdded => "Shared list",
+            AuditAction::ListMemberUpdated => "Changed list role",
+            AuditAction::ListMemberRemoved => "Removed list member",
+            AuditAction::StatusCreated => "Added status",
+            AuditAction::StatusUpdated => "Changed status",
+            AuditAction::StatusDeleted => "Deleted status",
+            AuditAction::TagCreated => "Created tag",
+            AuditAction::TagUpdated => "Updated tag",
+            AuditAction::TagDeleted => "Deleted tag",
+            AuditAction::WorkspaceCreated => "Created workspace",
+            AuditAction::WorkspaceMemberAdded => "Added workspace member",
+            AuditAction::WorkspaceMemberRemoved => "Removed workspace member",
+            AuditAction::UserSignedUp => "Signed up",
+            AuditAction::UserLoggedIn => "Logged in",
+            AuditAction::UserLoggedOut => "Logged out",
+            AuditAction::FeedTokenCreated => "Created calendar feed",
+            AuditAction::FeedTokenRevoked => "Revoked calendar feed",
+        }
+    }
+}
+
+/// One field that an event changed. Missing values didn't exist on that side.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    /// Missing for changes the server makes on its own, like purging the trash.
+    pub actor: Option<User>,
+    pub action: AuditAction,
+    pub target_type: String,
+    pub target_id: i64,
+    pub todo_id: Option<u32>,
+    pub changes: Vec<AuditChange>,
+    pub created_at: NaiveDateTime,
+}
+
+impl AuditEvent {
+    pub fn target(&self) -> String {
+        format!("{} #{}", self.target_type, self.target_id)
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{AuditAction, AuditChange, AuditEvent};
+    use crate::auth::User;
+    use crate::todo_app::live::{ssr::queue_change, TodoChange};
+    use crate::todo_app::ssr::SqlTodo;
+    use chrono::NaiveDateTime;
+    use serde_json::{Map, Value};
+    use sqlx::{Executor, Sqlite, SqliteConnection, SqlitePool};
+
+    /// What an event happened to. Events about things that belong to a todo
+    /// also carry the todo, so they show up in its history.
+    #[derive(Clone, Copy, Debug)]
+    pub struct AuditTarget {
+        pub kind: &'static str,
+        pub id: i64,
+        pub todo_id: Option<u32>,
+    }
+
+    impl AuditTarget {
+        pub fn todo(id: u32) -> Self {
+            Self { kind: "todo", id: id.into(), todo_id: Some(id) }
+        }
+
+        pub fn comment(id: i64, todo_id: u32) -> Self {
+            Self { kind: "comment", id, todo_id: Some(todo_id) }
+        }
+
+        pub fn attachment(id: i64, todo_id: u32) -> Self {
+            Self { kind: "attachment", id, todo_id: Some(todo_id) }
+        }
+
+        pub fn list(id: i64) -> Self {
+            Self { kind: "list", id, todo_id: None }
+        }
+
+        pub fn tag(id: i64) -> Self {
+            Self { kind: "tag", id, todo_id: None }
+        }
+
+        pub fn user(id: i64) -> Self {
+            Self { kind: "user", id, todo_id: None }
+        }
+
+        pub fn workspace(id: i64) -> Self {
+            Self { kind: "workspace", id, todo_id: None }
+        }
+    }
+
+    /// Drops the fields that are the same on both sides, so only the diff is stored.
+    fn diff(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+        match (before, after) {
+            (Some(Value::Object(mut before)), Some(Value::Object(mut after))) => {
+                let unchanged: Vec<String> = before
+                    .iter()
+                    .filter(|(key, value)| after.get(*key) == Some(*value))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in unchanged {
+                    before.remove(&key);
+                    after.remove(&key);
+                }
+                (Some(Value::Object(before)), Some(Value::Object(after)))
+            }
+            sides => sides,
+        }
+    }
+
+    /// Appends an event to the audit log. `before` and `after` are JSON objects
+    /// describing the target, either of which is left out when it didn't exist.
+    ///
+    /// Every change is recorded here, so this also queues the [`TodoChange`]
+    /// that tells connected clients about it.
+    pub async fn record<'c, E>(
+        executor: E,
+        actor_id: Option<i64>,
+        action: AuditAction,
+        target: AuditTarget,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Result<(), sqlx::Error>
+    where
+        E: Executor<'c, Database = Sqlite>,
+    {
+        let (before, after) = diff(before, after);
+        // Whoever owns the target, falling back to the actor once it's gone,
+        // and the list and workspace it belongs to so their members are told as well.
+        let (owner_id, list_id, workspace_id) = sqlx::query_as::<_, (Option<i64>, Option<i64>, Option<i64>)>(
+            "INSERT INTO audit_events (actor_id, action, target_type, target_id, todo_id, before, after)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            RETURNING COALESCE(CASE ?3
+                WHEN 'list' THEN (SELECT user_id FROM lists WHERE id = ?4)
+                WHEN 'tag' THEN (SELECT user_id FROM tags WHERE id = ?4)
+                WHEN 'user' THEN ?4
+                ELSE (SELECT user_id FROM todos WHERE id = ?5)
+            END, ?1), CASE ?3
+                WHEN 'list' THEN ?4
+                WHEN 'tag' THEN NULL
+                WHEN 'user' THEN NULL
+                WHEN 'workspace' THEN NULL
+                ELSE (SELECT list_id FROM todos WHERE id = ?5)
+            END, CASE ?3
+                WHEN 'list' THEN (SELECT workspace_id FROM lists WHERE id = ?4)
+                WHEN 'workspace' THEN ?4
+                WHEN 'tag' THEN NULL
+                WHEN 'user' THEN NULL
+                ELSE (SELECT workspace_id FROM todos WHERE id = ?5)
+            END",
+        )
+        .bind(actor_id)
+        .bind(action)
+        .bind(target.kind)
+        .bind(target.id)
+        .bind(target.todo_id)
+        .bind(before.map(|before| before.to_string()))
+        .bind(after.map(|after| after.to_string()))
+        .fetch_one(executor)
+        .await?;
+
+        queue_change(TodoChange {
+            action,
+            target_type: target.kind.to_string(),
+            target_id: target.id,
+            todo_id: target.todo_id,
+            owner_id,
+            list_id,
+            workspace_id,
+        });
+        Ok(())
+    }
+
+    pub fn todo_snapshot(todo: &SqlTodo) -> Option<Value> {
+        serde_json::to_value(todo).ok()
+    }
+
+    /// How the todo `id` looks right now, or `None` once it's gone.
+    pub async fn current_todo_snapshot<'c, E>(id: u32, executor: E) -> Result<Option<Value>, sqlx::Error>
+    where
+        E: Executor<'c, Database = Sqlite>,
+    {
+        Ok(sqlx::query_as::<_, SqlTodo>("SELECT * FROM todos WHERE id = ?")
+            .bind(id)
+            .fetch_optional(executor)
+            .await?
+            .as_ref()
+            .and_then(todo_snapshot))
+    }
+
+    /// Records a change to the todo `id`, comparing `before` with how the todo
+    /// looks now. Pass the transaction that made the change, so it's only kept
+    /// along with its record.
+    pub async fn record_todo(
+        conn: &mut SqliteConnection,
+        actor_id: Option<i64>,
+        action: AuditAction,
+        id: u32,
+        before: Option<&SqlTodo>,
+    ) -> Result<(), sqlx::Error> {
+        let after = current_todo_snapshot(id, &mut *conn).await?;
+        record(conn, actor_id, action, AuditTarget::todo(id), before.and_then(todo_snapshot), after).await
+    }
+
+    fn display_value(value: Option<&Value>) -> Option<String> {
+        match value? {
+            Value::Null => None,
+            Value::String(value) => Some(value.clone()),
+            value => Some(value.to_string()),
+        }
+    }
+
+    fn changes(before: Option<&str>, after: Option<&str>) -> Vec<AuditChange> {
+        let parse = |side: Option<&str>| match side.and_then(|side| serde_json::from_str(side).ok()) {
+            Some(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        let (before, after) = (parse(before), parse(after));
+
+        let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+        fields.sort();
+        fields.dedup();
+        fields
+            .into_iter()
+            .map(|field| AuditChange {
+                field: field.clone(),
+                before: display_value(before.get(field)),
+                after: display_value(after.get(field)),
+            })
+            .collect()
+    }
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlAuditEvent {
+        pub id: i64,
+        pub actor_id: Option<i64>,
+        pub action: AuditAction,
+        pub target_type: String,
+        pub target_id: i64,
+        pub todo_id: Option<u32>,
+        pub before: Option<String>,
+        pub after: Option<String>,
+        pub created_at: NaiveDateTime,
+    }
+
+    impl SqlAuditEvent {
+        pub async fn into_event(self, pool: &SqlitePool) -> AuditEvent {
+            let actor = match self.actor_id {
+                Some(id) => User::get(id, pool).await,
+                None => None,
+            };
+            AuditEvent {
+                id: self.id,
+                actor,
+                action: self.action,
+                target_type: self.target_type,
+                target_id: self.target_id,
+                todo_id: self.todo_id,
+                changes: changes(self.before.as_deref(), self.after.as_deref()),
+                created_at: self.created_at,
+            }
+        }
+    }
+}
+
+/// Everything that happened to a todo and the things attached to it, newest first.
+#[server(GetTodoHistory, "/api")]
+pub async fn get_todo_history(todo_id: u32) -> Result<Vec<AuditEvent>, ServerFnError<TodoAppError>> {
+    use self::ssr::SqlAuditEvent;
+    use crate::todo_app::ssr::*;
+    use futures::future::join_all;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(todo_id, &user, ListRole::Viewer, &pool).await?;
+
+    let events = sqlx::query_as::<_, SqlAuditEvent>(
+        "SELECT * FROM audit_events WHERE todo_id = ? ORDER BY id DESC",
+    )
+    .bind(todo_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(join_all(events.into_iter().map(|event| event.into_event(&pool))).await)
+}
+
+/// The global activity feed for admins, newest first. `before` is the id of
+/// the oldest event already shown, to load the page after it.
+#[server(GetAuditEvents, "/api")]
+pub async fn get_audit_events(
+    username: Option<String>,
+    action: Option<AuditAction>,
+    before: Option<i64>,
+) -> Result<Vec<AuditEvent>, ServerFnError<TodoAppError>> {
+    use self::ssr::SqlAuditEvent;
+    use crate::todo_app::ssr::*;
+    use futures::future::join_all;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if !is_todos_admin(&user, &pool).await {
+        return Err(TodoAppError::Forbidden.into());
+    }
+
+    let username = username.filter(|username| !username.trim().is_empty());
+    let events = sqlx::query_as::<_, SqlAuditEvent>(
+        "SELECT * FROM audit_events
+        WHERE (?1 IS NULL OR actor_id = (SELECT id FROM users WHERE username = ?1))
+        AND (?2 IS NULL OR action = ?2)
+        AND (?3 IS NULL OR id < ?3)
+        ORDER BY id DESC
+        LIMIT ?4",
+    )
+    .bind(username.map(|username| username.trim().to_string()))
+    .bind(action)
+    .bind(before)
+    .bind(AUDIT_PAGE_SIZE)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(join_all(events.into_iter().map(|event| event.into_event(&pool))).await)
+}
+
+#[component]
+fn AuditChanges(changes: Vec<AuditChange>) -> impl IntoView {
+    changes
+        .into_iter()
+        .map(|change| {
+            view! {
+                <div class="audit-change">
+                    <Caption1Strong>{change.field}": "</Caption1Strong>
+                    <Caption1>
+                        <del>{change.before}</del>
+                        " → "
+                        {change.after}
+                    </Caption1>
+                </div>
+            }
+        })
+        .collect_view()
+}
+
+/// The History tab of the todo detail drawer.
+#[component]
+pub fn TodoHistory(todo_id: u32) -> impl IntoView {
+    let changes = use_todo_changes();
+    let history = Resource::new(move || changes.get(), move |_| get_todo_history(todo_id));
+
+    view! {
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            {move || {
+                history.get().map(|events| match events {
+                    Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                    Ok(events) if events.is_empty() => view! { <Text>"Nothing happened yet."</Text> }.into_any(),
+                    Ok(events) => {
+                        view! {
+                            <Flex vertical=true>
+                                {events
+                                    .into_iter()
+                                    .map(|event| {
+                                        view! {
+                                            <Flex vertical=true gap=FlexGap::Small>
+                                                <Caption1Strong>
+                                                    {format!(
+                                                        "{} · {} · {}",
+                                                        event.action.label(),
+                                                        event.actor.map(|actor| actor.username).unwrap_or_else(|| "System".to_string()),
+                                                        event.created_at.format("%Y-%m-%d %H:%M"),
+                                                    )}
+                                                </Caption1Strong>
+                                                <AuditChanges changes=event.changes />
+                                            </Flex>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </Flex>
+                        }
+                            .into_any()
+                    }
+                })
+            }}
+        </Transition>
+    }
+}
+
+/// Where the page after `events` starts, if it was full and there may be more.
+fn next_cursor(events: Option<Result<Vec<AuditEvent>, ServerFnError<TodoAppError>>>) -> Option<i64> {
+    events
+        .and_then(Result::ok)
+        .filter(|events| events.len() as i64 == AUDIT_PAGE_SIZE)
+        .and_then(|events| events.last().map(|event| event.id))
+}
+
+/// The global activity feed, for admins only.
+#[component]
+pub fn AuditFeed() -> impl IntoView {
+    let username = RwSignal::new(String::new());
+    let action = RwSignal::new(String::new());
+    // Cursors of the pages before the current one, so "Previous" can go back.
+    let cursors = RwSignal::new(Vec::<i64>::new());
+    let cursor = RwSignal::new(None::<i64>);
+
+    // Any change to the filters starts over at the first page.
+    Effect::watch(
+        move || (username.get(), action.get()),
+        move |_, _, _| {
+            cursors.set(Vec::new());
+            cursor.set(None);
+        },
+        false,
+    );
+
+    provide_todo_changes();
+    let changes = use_todo_changes();
+
+    let events = Resource::new(
+        move || (username.get(), action.get(), cursor.get(), changes.get()),
+        move |(username, action, before, _)| {
+            get_audit_events(Some(username), AuditAction::parse(&action), before)
+        },
+    );
+
+    view! {
+        <Flex vertical=true>
+            <Flex align=FlexAlign::Center>
+                <Input value=username placeholder="Username" />
+                <Select value=action>
+                    <option value="">"All actions"</option>
+                    {AuditAction::ALL
+                        .into_iter()
+                        .map(|action| view! { <option value=action.as_str()>{action.label()}</option> })
+                        .collect_view()}
+                </Select>
+            </Flex>
+            <Card>
+                <Table>
+                    <TableHeader>
+                        <TableRow>
+                            <TableHeaderCell max_width=160.0>"When"</TableHeaderCell>
+                            <TableHeaderCell max_width=120.0>"Who"</TableHeaderCell>
+                            <TableHeaderCell max_width=200.0>"Action"</TableHeaderCell>
+                            <TableHeaderCell max_width=140.0>"Target"</TableHeaderCell>
+                            <TableHeaderCell resizable=true min_width=200.0>"Changes"</TableHeaderCell>
+                        </TableRow>
+                    </TableHeader>
+                    <TableBody>
+                        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                            {move || {
+                                events.get().map(|events| match events {
+                                    Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                                    Ok(events) if events.is_empty() => view! { <p>"No activity found."</p> }.into_any(),
+                                    Ok(events) => {
+                                        events
+                                            .into_iter()
+                                            .map(|event| {
+                                                let target = event.target();
+                                                view! {
+                                                    <TableRow>
+                                                        <TableCell>{event.created_at.format("%Y-%m-%d %H:%M").to_string()}</TableCell>
+                                                        <TableCell>
+                                                            <TableCellLayout truncate=true>
+                                                                {event.actor.map(|actor| actor.username).unwrap_or_else(|| "System".to_string())}
+                                                            </TableCellLayout>
+                                                        </TableCell>
+                                                        <TableCell>{event.action.label()}</TableCell>
+                                                        <TableCell>{target}</TableCell>
+                                                        <TableCell>
+                                                            <AuditChanges changes=event.changes />
+                                                        </TableCell>
+                                                    </TableRow>
+                                                }
+                                            })
+                                            .collect_view()
+                                            .into_any()
+                                    }
+                                })
+                            }}
+                        </Transition>
+                    </TableBody>
+                </Table>
+            </Card>
+            <Flex justify=FlexJustify::Center gap=FlexGap::Small>
+                <Button
+                    disabled=Signal::derive(move || cursor.with(Option::is_none))
+                    on_click=move |_| {
+                        let previous = cursors.try_update(Vec::pop).flatten();
+                        cursor.set(previous);
+                    }
+                >
+                    "Previous"
+                </Button>
+                <Button
+                    disabled=Signal::derive(move || next_cursor(events.get()).is_none())
+                    on_click=move |_| {
+                        if let Some(next) = next_cursor(events.get_untracked()) {
+                            if let Some(current) = cursor.get_untracked() {
+                                cursors.update(|cursors| cursors.push(current));
+                            }
+                            cursor.set(Some(next));
+                        }
+                    }
+                >
+                    "Next"
+                </Button>
+            </Flex>
+        </Flex>
+    }
+}
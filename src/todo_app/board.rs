@@ -0,0 +1,332 @@
+use leptos::ev::DragEvent;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+use thaw_utils::mount_style;
+
+use super::dependencies::DependencyBadges;
+use super::live::{provide_todo_changes, use_todo_changes};
+use super::members::ListRole;
+use super::priority::Priority;
+use super::statuses::*;
+use super::subtasks::SubtaskBadge;
+use super::Todo;
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+/// A list's statuses and the todos on its board, each in one of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoBoard {
+    pub statuses: Vec<TodoStatus>,
+    pub todos: Vec<Todo>,
+}
+
+/// The board of a list. Subtasks stay with their parents, as in the table.
+#[server(GetBoard, "/api")]
+pub async fn get_board(list_id: i64) -> Result<TodoBoard, ServerFnError<TodoAppError>> {
+    use crate::todo_app::lists::ssr::accessible_list;
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::statuses::ssr::list_statuses;
+    use futures::future::join_all;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_list(list_id, &user, ListRole::Viewer, &pool).await?;
+
+    let statuses = list_statuses(list_id, &pool)
+        .await
+        .map_err(TodoAppError::from)?
+        .into_iter()
+        .map(|status| status.into_status())
+        .collect::<Vec<_>>();
+
+    let rows = sqlx::query_as::<_, SqlTodo>(
+        "SELECT * FROM todos WHERE list_id = ? AND parent_id IS NULL AND deleted_at IS NULL
+        ORDER BY COALESCE(position, id), id",
+    )
+    .bind(list_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    let mut todos = join_all(rows.into_iter().map(|row| row.into_todo(&pool))).await;
+    // Todos from before the list had statuses go by whether they're done.
+    for todo in &mut todos {
+        if !statuses.iter().any(|status| Some(status.id) == todo.status_id) {
+            todo.status_id = statuses
+                .iter()
+                .find(|status| status.is_done == todo.completed)
+                .map(|status| status.id);
+        }
+    }
+
+    Ok(TodoBoard { statuses, todos })
+}
+
+/// Moves a todo to another column of its list's board. Moving it into a done
+/// status completes it, with everything that checking it off in the table does.
+#[server(MoveTodo, "/api")]
+pub async fn move_todo(id: u32, status_id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::*, AuditAction};
+    use crate::todo_app::dependencies::ssr::ensure_unblocked;
+    use crate::todo_app::recurrence::ssr::repeat_completed;
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::statuses::ssr::accessible_status;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+    let status = accessible_status(status_id, &user, ListRole::Viewer, &pool).await?;
+
+    if todo.list_id != Some(status.list_id) {
+        return Err(ServerFnError::ServerError(
+            "Todos can only move between the statuses of their own list.".to_string(),
+        ));
+    }
+
+    let completes = status.is_done && !todo.completed;
+    if completes {
+        ensure_unblocked(id, &pool).await?;
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+
+    sqlx::query("UPDATE todos SET status_id = ?, completed = ? WHERE id = ?")
+        .bind(status_id)
+        .bind(status.is_done)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    if completes {
+        repeat_completed(&todo, user.id, &mut tx)
+            .await
+            .map_err(TodoAppError::from)?;
+    }
+
+    let updated = current_todo_snapshot(id, &mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    record(&mut *tx, Some(user.id), AuditAction::TodoUpdated, AuditTarget::todo(id), todo_snapshot(&todo), updated)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// A todo on the board, dragged by the whole card.
+#[component]
+fn BoardCard(todo: Todo, draggable: bool, dragging: RwSignal<Option<u32>>) -> impl IntoView {
+    let id = todo.id;
+
+    view! {
+        <div
+            class="todo-board-card"
+            draggable=draggable.then_some("true")
+            on:dragstart=move |_| dragging.set(Some(id))
+            on:dragend=move |_| dragging.set(None)
+        >
+            <Card>
+                <Flex vertical=true gap=FlexGap::Small>
+                    <Body1>{todo.title}</Body1>
+                    <Flex align=FlexAlign::Center gap=FlexGap::Small>
+                        {(todo.priority != Priority::None).then(|| view! {
+                            <Badge color=todo.priority.badge_color()>{todo.priority.label()}</Badge>
+                        })}
+                        {todo.progress.map(|progress| view! { <SubtaskBadge progress /> })}
+                        <DependencyBadges dependencies=todo.dependencies />
+                    </Flex>
+                    <Flex justify=FlexJustify::SpaceBetween>
+                        <Caption1>{todo.due_at.map(|due| due.format("%Y-%m-%d").to_string())}</Caption1>
+                        <Caption1>{todo.assignee.map(|assignee| assignee.username)}</Caption1>
+                    </Flex>
+                </Flex>
+            </Card>
+        </div>
+    }
+}
+
+/// The todos of a list as columns, one per status. Editors drag todos between
+/// columns, owners rename, reorder, add and delete the columns themselves.
+#[component]
+pub fn Board(list_id: i64, role: ListRole) -> impl IntoView {
+    mount_style("todos", include_str!("./todos.css"));
+    provide_todo_changes();
+    let changes = use_todo_changes();
+    let move_todo = ServerAction::<MoveTodo>::new();
+    let add_status = ServerAction::<AddStatus>::new();
+    let rename_status = ServerAction::<RenameStatus>::new();
+    let move_status = ServerAction::<MoveStatus>::new();
+    let delete_status = ServerAction::<DeleteStatus>::new();
+    let dragging = RwSignal::new(None::<u32>);
+    let drop_target = RwSignal::new(None::<i64>);
+    let can_edit = role >= ListRole::Editor;
+    let is_owner = role >= ListRole::Owner;
+
+    let board = Resource::new(
+        move || {
+            (
+                move_todo.version().get(),
+                add_status.version().get(),
+                rename_status.version().get(),
+                move_status.version().get(),
+                delete_status.version().get(),
+                changes.get(),
+            )
+        },
+        move |_| get_board(list_id),
+    );
+
+    let error = move || {
+        [
+            move_todo.value().get().and_then(Result::err),
+            add_status.value().get().and_then(Result::err),
+            rename_status.value().get().and_then(Result::err),
+            move_status.value().get().and_then(Result::err),
+            delete_status.value().get().and_then(Result::err),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    let new_status = RwSignal::new(String::new());
+    let new_status_done = RwSignal::new(false);
+
+    view! {
+        <Flex vertical=true>
+            {error}
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                <ErrorBoundary fallback=|errors| {
+                    view! { <ErrorTemplate errors=errors/> }
+                }>
+                    <div class="todo-board">
+                        {move || {
+                            board.get().map(|board| board.map(|TodoBoard { statuses, todos }| {
+                                statuses
+                                    .into_iter()
+                                    .map(|status| {
+                                        let status_id = status.id;
+                                        let cards = todos
+                                            .iter()
+                                            .filter(|todo| todo.status_id == Some(status_id))
+                                            .cloned()
+                                            .collect::<Vec<_>>();
+                                        let count = cards.len();
+                                        let name = RwSignal::new(status.name.clone());
+                                        let saved_name = status.name.clone();
+                                        view! {
+                                            <div
+                                                class="todo-board-column"
+                                                class:todo-board-drop=move || drop_target.get() == Some(status_id)
+                                                on:dragover=move |ev: DragEvent| {
+                                                    if dragging.get_untracked().is_some() {
+                                                        ev.prevent_default();
+                                                        drop_target.set(Some(status_id));
+                                                    }
+                                                }
+                                                on:dragleave=move |_| drop_target.set(None)
+                                                on:drop=move |ev: DragEvent| {
+                                                    ev.prevent_default();
+                                                    if let Some(id) = dragging.get_untracked() {
+                                                        move_todo.dispatch(MoveTodo { id, status_id });
+                                                    }
+                                                    dragging.set(None);
+                                                    drop_target.set(None);
+                                                }
+                                            >
+                                                <Card>
+                                                    <CardHeader>
+                                                        {if is_owner {
+                                                            view! {
+                                                                <Input
+                                                                    value=name
+                                                                    on_blur=move |_| {
+                                                                        let new_name = name.get_untracked();
+                                                                        if new_name != saved_name {
+                                                                            rename_status.dispatch(RenameStatus { id: status_id, name: new_name });
+                                                                        }
+                                                                    }
+                                                                />
+                                                            }
+                                                                .into_any()
+                                                        } else {
+                                                            view! { <Caption1Strong>{status.name}</Caption1Strong> }.into_any()
+                                                        }}
+                                                        <CardHeaderDescription slot>
+                                                            <Caption1>
+                                                                {format!("{count} {}", if count == 1 { "todo" } else { "todos" })}
+                                                                {status.is_done.then_some(" · done")}
+                                                            </Caption1>
+                                                        </CardHeaderDescription>
+                                                        <CardHeaderAction slot>
+                                                            <Show when=move || is_owner>
+                                                                <Menu on_select=move |value: String| match value.as_str() {
+                                                                    "left" => {
+                                                                        move_status.dispatch(MoveStatus { id: status_id, left: true });
+                                                                    }
+                                                                    "right" => {
+                                                                        move_status.dispatch(MoveStatus { id: status_id, left: false });
+                                                                    }
+                                                                    "delete" => {
+                                                                        delete_status.dispatch(DeleteStatus { id: status_id });
+                                                                    }
+                                                                    _ => {}
+                                                                }>
+                                                                    <MenuTrigger slot>
+                                                                        <Button appearance=ButtonAppearance::Subtle icon=icondata::LuMoreHorizontal />
+                                                                    </MenuTrigger>
+                                                                    <MenuItem value="left" icon=icondata::LuArrowLeft>"Move left"</MenuItem>
+                                                                    <MenuItem value="right" icon=icondata::LuArrowRight>"Move right"</MenuItem>
+                                                                    <MenuItem value="delete" icon=icondata::LuTrash2>"Delete"</MenuItem>
+                                                                </Menu>
+                                                            </Show>
+                                                        </CardHeaderAction>
+                                                    </CardHeader>
+                                                    <Flex vertical=true gap=FlexGap::Small>
+                                                        {cards
+                                                            .into_iter()
+                                                            .map(|todo| view! { <BoardCard todo draggable=can_edit dragging /> })
+                                                            .collect_view()}
+                                                    </Flex>
+                                                </Card>
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view()
+                            }))
+                        }}
+                        <Show when=move || is_owner>
+                            <div class="todo-board-column">
+                                <Card>
+                                    <Flex vertical=true gap=FlexGap::Small>
+                                        <Input value=new_status placeholder="New status" />
+                                        <Checkbox checked=new_status_done label="Counts as done" />
+                                        <Button
+                                            appearance=ButtonAppearance::Primary
+                                            icon=icondata::LuPlus
+                                            on_click=move |_| {
+                                                add_status.dispatch(AddStatus {
+                                                    list_id,
+                                                    name: new_status.get_untracked(),
+                                                    is_done: new_status_done.get_untracked(),
+                                                });
+                                                new_status.set(String::new());
+                                                new_status_done.set(false);
+                                            }
+                                        >
+                                            "Add status"
+                                        </Button>
+                                    </Flex>
+                                </Card>
+                            </div>
+                        </Show>
+                    </div>
+                </ErrorBoundary>
+            </Transition>
+        </Flex>
+    }
+}
@@ -0,0 +1,1080 @@
+/// A minimal CalDAV server, RFC 4791, for task apps to sync todos both ways.
+///
+/// Each workspace a user belongs to is a calendar of VTODOs at
+/// `/dav/<username>/<workspace id>/`, holding the todos they can see there.
+/// Todos a client creates go into that workspace, without a list.
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use crate::auth::User;
+    use crate::todo_app::audit::{ssr::*, AuditAction};
+    use crate::todo_app::dependencies::ssr::ensure_unblocked;
+    use crate::todo_app::feeds::ssr::{default_uid, dtstamp, push_line, push_vtodo, PRODID};
+    use crate::todo_app::live::{ssr::ChangeHub, TodoChange};
+    use crate::todo_app::priority::Priority;
+    use crate::todo_app::recurrence::{ssr::repeat_completed, Recurrence};
+    use crate::todo_app::ssr::{todo_role, ListRole, SqlTodo};
+    use crate::todo_app::trash::ssr::TODO_SUBTREE;
+    use crate::todo_app::workspaces::ssr::workspace_role;
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{header, HeaderMap, Method, StatusCode, Uri},
+        response::{IntoResponse, Redirect, Response},
+    };
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+    use leptos::prelude::ServerFnError;
+    use sqlx::SqlitePool;
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    const ALLOW: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+
+    /// Todos with the UID and resource name a client gave them, if any.
+    const DAV_TODOS: &str = "SELECT todos.*, caldav_objects.uid AS caldav_uid, caldav_objects.name AS caldav_name
+        FROM todos LEFT JOIN caldav_objects ON caldav_objects.todo_id = todos.id";
+
+    #[derive(sqlx::FromRow)]
+    struct SqlDavTodo {
+        #[sqlx(flatten)]
+        todo: SqlTodo,
+        caldav_uid: Option<String>,
+        caldav_name: Option<String>,
+    }
+
+    impl SqlDavTodo {
+        fn uid(&self) -> String {
+            self.caldav_uid.clone().unwrap_or_else(|| default_uid(self.todo.id))
+        }
+
+        fn name(&self) -> String {
+            self.caldav_name.clone().unwrap_or_else(|| format!("{}.ics", self.todo.id))
+        }
+
+        /// Changes whenever anything about the todo does. The hash only has to
+        /// be stable while the server runs; after that, clients just refetch.
+        fn etag(&self) -> String {
+            let mut hasher = DefaultHasher::new();
+            serde_json::to_string(&self.todo).unwrap_or_default().hash(&mut hasher);
+            format!("\"{:016x}\"", hasher.finish())
+        }
+
+        fn ics(&self) -> String {
+            let mut ics = String::new();
+            for line in ["BEGIN:VCALENDAR", "VERSION:2.0", &format!("PRODID:{PRODID}")] {
+                push_line(&mut ics, line);
+            }
+            push_vtodo(&mut ics, &self.todo, &self.uid(), &dtstamp());
+            push_line(&mut ics, "END:VCALENDAR");
+            ics
+        }
+    }
+
+    /// What a client sent for a todo. Properties the app has no use for are dropped.
+    #[derive(Default)]
+    struct VTodo {
+        uid: String,
+        summary: String,
+        description: String,
+        due: Option<NaiveDateTime>,
+        completed: bool,
+        priority: Priority,
+        rrule: Option<String>,
+    }
+
+    /// Reads the first VTODO of an iCalendar object, skipping the alarms and
+    /// other components inside it.
+    fn parse_vtodo(ics: &str) -> Option<VTodo> {
+        // Unfolds the content lines, RFC 5545 section 3.1.
+        let ics = ics.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+        let mut lines = ics.lines();
+        lines.find(|line| *line == "BEGIN:VTODO")?;
+
+        let mut todo = VTodo::default();
+        let mut nested = 0;
+        for line in lines {
+            if line == "END:VTODO" && nested == 0 {
+                return (!todo.uid.is_empty()).then_some(todo);
+            } else if line.starts_with("BEGIN:") {
+                nested += 1;
+            } else if line.starts_with("END:") {
+                nested -= 1;
+            } else if nested == 0 {
+                let Some((name, value)) = line.split_once(':') else {
+                    continue;
+                };
+                match name.split(';').next().unwrap_or_default().to_ascii_uppercase().as_str() {
+                    "UID" => todo.uid = value.to_string(),
+                    "SUMMARY" => todo.summary = unescape(value),
+                    "DESCRIPTION" => todo.description = unescape(value),
+                    "DUE" => todo.due = parse_date_time(value),
+                    "STATUS" => todo.completed = value.eq_ignore_ascii_case("COMPLETED"),
+                    "PRIORITY" => todo.priority = parse_priority(value.parse().unwrap_or_default()),
+                    "RRULE" => todo.rrule = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    fn unescape(text: &str) -> String {
+        let mut unescaped = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n' | 'N') => unescaped.push('\n'),
+                    Some(c) => unescaped.push(c),
+                    None => {}
+                },
+                c => unescaped.push(c),
+            }
+        }
+        unescaped
+    }
+
+    /// A date or date-time as the floating local time the app keeps. Times
+    /// in UTC are converted, those in other zones are taken as they are.
+    fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+        if let Some(utc) = value.strip_suffix('Z') {
+            let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            return Some(Utc.from_utc_datetime(&utc).with_timezone(&Local).naive_local());
+        }
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok().or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+    }
+
+    /// The other way around from the feed, RFC 5545 section 3.8.1.9.
+    fn parse_priority(value: u8) -> Priority {
+        match value {
+            0 => Priority::None,
+            1..=2 => Priority::Urgent,
+            3..=4 => Priority::High,
+            5 => Priority::Medium,
+            _ => Priority::Low,
+        }
+    }
+
+    fn xml_escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+
+    /// Percent-encodes everything but unreserved characters, RFC 3986 section 2.3.
+    fn encode_segment(segment: &str) -> String {
+        segment
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+                byte => format!("%{byte:02X}"),
+            })
+            .collect()
+    }
+
+    fn decode_segment(segment: &str) -> String {
+        let mut bytes = Vec::with_capacity(segment.len());
+        let mut rest = segment.as_bytes();
+        while let [byte, tail @ ..] = rest {
+            let decoded = (*byte == b'%')
+                .then(|| std::str::from_utf8(tail.get(..2)?).ok())
+                .flatten()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            match decoded {
+                Some(decoded) => {
+                    bytes.push(decoded);
+                    rest = &tail[2..];
+                }
+                None => {
+                    bytes.push(*byte);
+                    rest = tail;
+                }
+            }
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    /// The contents of the `href` elements in a request body, whatever
+    /// namespace prefix the client uses for them.
+    fn hrefs(xml: &str) -> Vec<String> {
+        xml.split('<')
+            .filter_map(|element| {
+                let (tag, text) = element.split_once('>')?;
+                // Closing tags are followed by whatever comes after the element.
+                if tag.starts_with('/') {
+                    return None;
+                }
+                let name = tag.split_whitespace().next()?.rsplit(':').next()?;
+                (name == "href").then(|| text.trim().replace("&amp;", "&"))
+            })
+            .collect()
+    }
+
+    fn error_response(status: StatusCode, message: impl Into<String>) -> Response {
+        (status, message.into()).into_response()
+    }
+
+    fn internal_error(error: sqlx::Error) -> Response {
+        log::error!("Handling a CalDAV request failed: {error:?}");
+        error_response(StatusCode::INTERNAL_SERVER_ERROR, "The request could not be handled.")
+    }
+
+    fn multistatus(responses: String) -> Response {
+        (
+            StatusCode::MULTI_STATUS,
+            [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+            format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"DAV:\" \
+                xmlns:c=\"urn:ietf:params:xml:ns:caldav\" xmlns:cs=\"http://calendarserver.org/ns/\">\
+                {responses}</d:multistatus>"
+            ),
+        )
+            .into_response()
+    }
+
+    fn push_response(xml: &mut String, href: &str, props: &str) {
+        xml.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:propstat><d:prop>{props}</d:prop>\
+            <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+            xml_escape(href)
+        ));
+    }
+
+    /// What a request is about, from the path below `/dav/`.
+    #[derive(Debug, PartialEq)]
+    enum Resource {
+        Root,
+        /// The user's principal, which is also their calendar home.
+        Home(String),
+        Calendar(String, i64),
+        Todo(String, i64, String),
+    }
+
+    impl Resource {
+        fn parse(path: &str) -> Option<Self> {
+            let segments = path
+                .strip_prefix("/dav")?
+                .split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(decode_segment)
+                .collect::<Vec<_>>();
+            match segments.as_slice() {
+                [] => Some(Resource::Root),
+                [username] => Some(Resource::Home(username.clone())),
+                [username, workspace] => Some(Resource::Calendar(username.clone(), workspace.parse().ok()?)),
+                [username, workspace, name] => {
+                    Some(Resource::Todo(username.clone(), workspace.parse().ok()?, name.clone()))
+                }
+                _ => None,
+            }
+        }
+
+        fn username(&self) -> Option<&str> {
+            match self {
+                Resource::Root => None,
+                Resource::Home(username) | Resource::Calendar(username, _) | Resource::Todo(username, ..) => {
+                    Some(username)
+                }
+            }
+        }
+    }
+
+    /// The user of an `Authorization: Basic` header, if their password is right.
+    async fn authenticate(headers: &HeaderMap, pool: &SqlitePool) -> Option<User> {
+        let credentials = headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Basic ")?;
+        let credentials = String::from_utf8(STANDARD.decode(credentials.trim()).ok()?).ok()?;
+        let (username, password) = credentials.split_once(':')?;
+        let (user, passhash) = User::get_from_username_with_passhash(username.to_string(), pool).await?;
+        // bcrypt is slow on purpose, and clients send the password with every
+        // request, so it mustn't hold up the other requests meanwhile.
+        let password = password.to_string();
+        tokio::task::spawn_blocking(move || passhash.matches(&password))
+            .await
+            .unwrap_or(false)
+            .then_some(user)
+    }
+
+    /// `/.well-known/caldav`, where clients look for the server, RFC 6764.
+    pub async fn caldav_well_known() -> Redirect {
+        Redirect::permanent("/dav/")
+    }
+
+    /// Everything below `/dav/`. Task apps don't keep a session, so they
+    /// send the username and password along with every request.
+    pub async fn caldav(
+        State(pool): State<SqlitePool>,
+        State(changes): State<ChangeHub>,
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Response {
+        if method == Method::OPTIONS {
+            return (
+                [
+                    (header::ALLOW, ALLOW),
+                    (header::HeaderName::from_static("dav"), "1, 3, calendar-access"),
+                ],
+                (),
+            )
+                .into_response();
+        }
+
+        let Some(user) = authenticate(&headers, &pool).await else {
+            return (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Basic realm=\"Todos\", charset=\"UTF-8\"")],
+                "Log in with your username and password.",
+            )
+                .into_response();
+        };
+        let Some(resource) = Resource::parse(uri.path()) else {
+            return error_response(StatusCode::NOT_FOUND, "Not found.");
+        };
+        if resource.username().is_some_and(|username| username != user.username) {
+            return error_response(StatusCode::FORBIDDEN, "That's someone else's.");
+        }
+
+        let dav = Dav { pool, changes, user };
+        let result = match method.as_str() {
+            "PROPFIND" => dav.propfind(resource, &headers).await,
+            "REPORT" => dav.report(resource, &body).await,
+            "GET" | "HEAD" => dav.get(resource).await,
+            "PUT" => dav.put(resource, &headers, &body).await,
+            "DELETE" => dav.delete(resource, &headers).await,
+            _ => Err((StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, ALLOW)]).into_response()),
+        };
+        result.unwrap_or_else(|response| response)
+    }
+
+    struct Dav {
+        pool: SqlitePool,
+        changes: ChangeHub,
+        user: User,
+    }
+
+    impl Dav {
+        fn home(&self) -> String {
+            format!("/dav/{}/", encode_segment(&self.user.username))
+        }
+
+        fn calendar_href(&self, workspace_id: i64) -> String {
+            format!("{}{workspace_id}/", self.home())
+        }
+
+        fn todo_href(&self, workspace_id: i64, todo: &SqlDavTodo) -> String {
+            format!("{}{}", self.calendar_href(workspace_id), encode_segment(&todo.name()))
+        }
+
+        fn principal_props(&self) -> String {
+            format!(
+                "<d:current-user-principal><d:href>{home}</d:href></d:current-user-principal>\
+                <c:calendar-home-set><d:href>{home}</d:href></c:calendar-home-set>",
+                home = xml_escape(&self.home())
+            )
+        }
+
+        async fn workspaces(&self) -> Result<Vec<(i64, String)>, Response> {
+            sqlx::query_as::<_, (i64, String)>(
+                "SELECT workspaces.id, workspaces.name FROM workspaces
+                JOIN workspace_members ON workspace_members.workspace_id = workspaces.id
+                WHERE workspace_members.user_id = ? ORDER BY workspaces.id",
+            )
+            .bind(self.user.id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error)
+        }
+
+        /// The user's own todos in the workspace and those in lists shared with them.
+        async fn calendar_todos(&self, workspace_id: i64) -> Result<Vec<SqlDavTodo>, Response> {
+            if workspace_role(workspace_id, &self.user, &self.pool)
+                .await
+                .map_err(internal_error)?
+                .is_none()
+            {
+                return Err(error_response(StatusCode::NOT_FOUND, "Not found."));
+            }
+            sqlx::query_as::<_, SqlDavTodo>(&format!(
+                "{DAV_TODOS}
+                WHERE ((todos.list_id IS NULL AND todos.user_id = ?1)
+                    OR todos.list_id IN (SELECT list_id FROM list_members WHERE user_id = ?1))
+                AND todos.workspace_id = ?2 AND todos.deleted_at IS NULL
+                ORDER BY todos.id"
+            ))
+            .bind(self.user.id)
+            .bind(workspace_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error)
+        }
+
+        /// The todo called `name` in the workspace, with the user's role for it.
+        async fn find_todo(
+            &self,
+            workspace_id: i64,
+            name: &str,
+        ) -> Result<Option<(SqlDavTodo, ListRole)>, Response> {
+            let todo = sqlx::query_as::<_, SqlDavTodo>(&format!(
+                "{DAV_TODOS}
+                WHERE (caldav_objects.name = ?1 OR (caldav_objects.name IS NULL AND todos.id || '.ics' = ?1))
+                AND todos.workspace_id = ?2 AND todos.deleted_at IS NULL"
+            ))
+            .bind(name)
+            .bind(workspace_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal_error)?;
+
+            let Some(todo) = todo else {
+                return Ok(None);
+            };
+            let role = todo_role(&todo.todo, &self.user, &self.pool)
+                .await
+                .map_err(internal_error)?;
+            Ok(role.map(|role| (todo, role)))
+        }
+
+        async fn reload(&self, id: u32) -> Result<SqlDavTodo, Response> {
+            sqlx::query_as::<_, SqlDavTodo>(&format!("{DAV_TODOS} WHERE todos.id = ?"))
+                .bind(id)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(internal_error)
+        }
+
+        /// Tells connected browsers. This isn't a server function, so nothing
+        /// publishes the change for us.
+        fn publish(&self, action: AuditAction, todo: &SqlTodo) {
+            self.changes.publish(TodoChange {
+                action,
+                target_type: "todo".to_string(),
+                target_id: todo.id.into(),
+                todo_id: Some(todo.id),
+                owner_id: Some(todo.user_id),
+                list_id: todo.list_id,
+                workspace_id: todo.workspace_id,
+            });
+        }
+
+        /// Answers with every property the server knows about, whichever ones
+        /// were asked for. `Depth: infinity` only goes one level down.
+        async fn propfind(&self, resource: Resource, headers: &HeaderMap) -> Result<Response, Response> {
+            let children = headers.get("depth").and_then(|depth| depth.to_str().ok()) != Some("0");
+            let mut xml = String::new();
+            match resource {
+                Resource::Root => {
+                    push_response(
+                        &mut xml,
+                        "/dav/",
+                        &format!("<d:resourcetype><d:collection/></d:resourcetype>{}", self.principal_props()),
+                    );
+                    if children {
+                        self.push_home(&mut xml);
+                    }
+                }
+                Resource::Home(_) => {
+                    self.push_home(&mut xml);
+                    if children {
+                        for (workspace_id, name) in self.workspaces().await? {
+                            let todos = self.calendar_todos(workspace_id).await?;
+                            self.push_calendar(&mut xml, workspace_id, &name, &todos);
+                        }
+                    }
+                }
+                Resource::Calendar(_, workspace_id) => {
+                    let name = self
+                        .workspaces()
+                        .await?
+                        .into_iter()
+                        .find(|(id, _)| *id == workspace_id)
+                        .map(|(_, name)| name)
+                        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Not found."))?;
+                    let todos = self.calendar_todos(workspace_id).await?;
+                    self.push_calendar(&mut xml, workspace_id, &name, &todos);
+                    if children {
+                        for todo in &todos {
+                            self.push_todo(&mut xml, workspace_id, todo, false);
+                        }
+                    }
+                }
+                Resource::Todo(_, workspace_id, name) => {
+                    let (todo, _) = self
+                        .find_todo(workspace_id, &name)
+                        .await?
+                        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Not found."))?;
+                    self.push_todo(&mut xml, workspace_id, &todo, false);
+                }
+            }
+            Ok(multistatus(xml))
+        }
+
+        fn push_home(&self, xml: &mut String) {
+            push_response(
+                xml,
+                &self.home(),
+                &format!(
+                    "<d:resourcetype><d:collection/><d:principal/></d:resourcetype>\
+                    <d:displayname>{}</d:displayname>{}",
+                    xml_escape(&self.user.username),
+                    self.principal_props()
+                ),
+            );
+        }
+
+        fn push_calendar(&self, xml: &mut String, workspace_id: i64, name: &str, todos: &[SqlDavTodo]) {
+            // Changes whenever any of the todos does, or one comes or goes.
+            let mut hasher = DefaultHasher::new();
+            for todo in todos {
+                (todo.name(), todo.etag()).hash(&mut hasher);
+            }
+            push_response(
+                xml,
+                &self.calendar_href(workspace_id),
+                &format!(
+                    "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+                    <d:displayname>{}</d:displayname>\
+                    <c:supported-calendar-component-set><c:comp name=\"VTODO\"/></c:supported-calendar-component-set>\
+                    <d:supported-report-set>\
+                    <d:supported-report><d:report><c:calendar-multiget/></d:report></d:supported-report>\
+                    <d:supported-report><d:report><c:calendar-query/></d:report></d:supported-report>\
+                    </d:supported-report-set>\
+                    <cs:getctag>{:016x}</cs:getctag>{}",
+                    xml_escape(name),
+                    hasher.finish(),
+                    self.principal_props()
+                ),
+            );
+        }
+
+        fn push_todo(&self, xml: &mut String, workspace_id: i64, todo: &SqlDavTodo, with_data: bool) {
+            let mut props = format!(
+                "<d:resourcetype/><d:getetag>{}</d:getetag>\
+                <d:getcontenttype>text/calendar; charset=utf-8; component=VTODO</d:getcontenttype>",
+                xml_escape(&todo.etag())
+            );
+            if with_data {
+                props.push_str(&format!("<c:calendar-data>{}</c:calendar-data>", xml_escape(&todo.ics())));
+            }
+            push_response(xml, &self.todo_href(workspace_id, todo), &props);
+        }
+
+        /// `calendar-multiget` answers for the todos whose hrefs it lists,
+        /// `calendar-query` for all of them, as they all match its VTODO filter.
+        async fn report(&self, resource: Resource, body: &[u8]) -> Result<Response, Response> {
+            let Resource::Calendar(_, workspace_id) = resource else {
+                return Err(error_response(StatusCode::FORBIDDEN, "Reports are only supported on calendars."));
+            };
+            let body = String::from_utf8_lossy(body);
+            let mut todos = self.calendar_todos(workspace_id).await?;
+            if body.contains("calendar-multiget") {
+                let names = hrefs(&body)
+                    .iter()
+                    .filter_map(|href| href.trim_end_matches('/').rsplit('/').next().map(decode_segment))
+                    .collect::<Vec<_>>();
+                todos.retain(|todo| names.contains(&todo.name()));
+            } else if !body.contains("calendar-query") {
+                return Err(error_response(StatusCode::FORBIDDEN, "Unsupported report."));
+            }
+
+            let mut xml = String::new();
+            for todo in &todos {
+                self.push_todo(&mut xml, workspace_id, todo, true);
+            }
+            Ok(multistatus(xml))
+        }
+
+        async fn get(&self, resource: Resource) -> Result<Response, Response> {
+            let Resource::Todo(_, workspace_id, name) = resource else {
+                return Err(error_response(StatusCode::METHOD_NOT_ALLOWED, "Use PROPFIND on collections."));
+            };
+            let (todo, _) = self
+                .find_todo(workspace_id, &name)
+                .await?
+                .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Not found."))?;
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+                    (header::ETAG, todo.etag()),
+                ],
+                todo.ics(),
+            )
+                .into_response())
+        }
+
+        /// Creates or replaces the todo called `name`. Clients send the ETag they
+        /// last saw, so they don't overwrite changes made in the meantime.
+        async fn put(&self, resource: Resource, headers: &HeaderMap, body: &[u8]) -> Result<Response, Response> {
+            let Resource::Todo(_, workspace_id, name) = resource else {
+                return Err(error_response(StatusCode::METHOD_NOT_ALLOWED, "Only todos can be written."));
+            };
+            let vtodo = std::str::from_utf8(body)
+                .ok()
+                .and_then(parse_vtodo)
+                .ok_or_else(|| error_response(StatusCode::BAD_REQUEST, "Expected a VTODO with a UID."))?;
+            if vtodo.summary.trim().is_empty() {
+                return Err(error_response(StatusCode::BAD_REQUEST, "Title cannot be empty."));
+            }
+
+            let existing = self.find_todo(workspace_id, &name).await?;
+            if !preconditions_hold(headers, existing.as_ref().map(|(todo, _)| todo.etag()).as_deref()) {
+                return Err(precondition_failed());
+            }
+
+            let (id, status) = match existing {
+                Some((todo, role)) => {
+                    if role < ListRole::Editor {
+                        return Err(error_response(StatusCode::FORBIDDEN, "You can only view this todo."));
+                    }
+                    let id = todo.todo.id;
+                    self.update(todo.todo, vtodo).await?;
+                    (id, StatusCode::NO_CONTENT)
+                }
+                None => (self.create(workspace_id, &name, vtodo).await?, StatusCode::CREATED),
+            };
+
+            let todo = self.reload(id).await?;
+            Ok((status, [(header::ETAG, todo.etag())]).into_response())
+        }
+
+        async fn update(&self, todo: SqlTodo, vtodo: VTodo) -> Result<(), Response> {
+            let id = todo.id;
+            let completes = vtodo.completed && !todo.completed;
+            if completes {
+                ensure_unblocked(id, &self.pool).await.map_err(|error| match error {
+                    ServerFnError::ServerError(message) => error_response(StatusCode::CONFLICT, message),
+                    error => error_response(StatusCode::INTERNAL_SERVER_ERROR, error.to_string()),
+                })?;
+            }
+            // Rules the app can't repeat by are left alone rather than dropped.
+            let recurrence = match vtodo.rrule {
+                Some(rule) => rule
+                    .parse::<Recurrence>()
+                    .map(|recurrence| recurrence.to_string())
+                    .ok()
+                    .or(todo.recurrence.clone()),
+                None => None,
+            };
+
+            let mut tx = self.pool.begin().await.map_err(internal_error)?;
+            // The status follows `completed` as in `update_todo`.
+            sqlx::query(
+                "UPDATE todos SET title = ?1, notes = ?2, due_at = ?3, priority = ?4, recurrence = ?5, completed = ?6,
+                status_id = CASE WHEN ?6 = completed THEN status_id ELSE COALESCE(
+                    (SELECT id FROM list_statuses WHERE list_id = todos.list_id AND is_done = ?6 ORDER BY position LIMIT 1),
+                    status_id
+                ) END
+                WHERE id = ?7",
+            )
+            .bind(vtodo.summary.trim())
+            .bind(&vtodo.description)
+            .bind(vtodo.due)
+            .bind(vtodo.priority)
+            .bind(recurrence)
+            .bind(vtodo.completed)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+
+            if completes {
+                repeat_completed(&todo, self.user.id, &mut tx)
+                    .await
+                    .map_err(internal_error)?;
+            }
+
+            let updated = current_todo_snapshot(id, &mut *tx).await.map_err(internal_error)?;
+            record(&mut *tx, Some(self.user.id), AuditAction::TodoUpdated, AuditTarget::todo(id), todo_snapshot(&todo), updated)
+                .await
+                .map_err(internal_error)?;
+            tx.commit().await.map_err(internal_error)?;
+
+            self.publish(AuditAction::TodoUpdated, &todo);
+            Ok(())
+        }
+
+        async fn create(&self, workspace_id: i64, name: &str, vtodo: VTodo) -> Result<u32, Response> {
+            if workspace_role(workspace_id, &self.user, &self.pool)
+                .await
+                .map_err(internal_error)?
+                .is_none()
+            {
+                return Err(error_response(StatusCode::NOT_FOUND, "Not found."));
+            }
+            // `<id>.ics` names are the server's, and taken names someone else's.
+            let reserved = name.strip_suffix(".ics").is_some_and(|id| id.parse::<u32>().is_ok());
+            let taken = sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM caldav_objects WHERE name = ?)")
+                .bind(name)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(internal_error)?;
+            if reserved || taken {
+                return Err(error_response(StatusCode::CONFLICT, "That name is already taken."));
+            }
+
+            let mut tx = self.pool.begin().await.map_err(internal_error)?;
+            let id = sqlx::query_scalar::<_, u32>(
+                "INSERT INTO todos (title, notes, user_id, completed, due_at, priority, recurrence, workspace_id)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+            )
+            .bind(vtodo.summary.trim())
+            .bind(&vtodo.description)
+            .bind(self.user.id)
+            .bind(vtodo.completed)
+            .bind(vtodo.due)
+            .bind(vtodo.priority)
+            .bind(
+                vtodo
+                    .rrule
+                    .and_then(|rule| rule.parse::<Recurrence>().ok())
+                    .map(|recurrence| recurrence.to_string()),
+            )
+            .bind(workspace_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+
+            sqlx::query("INSERT INTO caldav_objects (todo_id, uid, name) VALUES (?, ?, ?)")
+                .bind(id)
+                .bind(&vtodo.uid)
+                .bind(name)
+                .execute(&mut *tx)
+                .await
+                .map_err(internal_error)?;
+
+            let created = current_todo_snapshot(id, &mut *tx).await.map_err(internal_error)?;
+            record(&mut *tx, Some(self.user.id), AuditAction::TodoCreated, AuditTarget::todo(id), None, created)
+                .await
+                .map_err(internal_error)?;
+            tx.commit().await.map_err(internal_error)?;
+
+            let todo = self.reload(id).await?;
+            self.publish(AuditAction::TodoCreated, &todo.todo);
+            Ok(id)
+        }
+
+        /// Moves the todo and its subtasks to the trash, like `delete_todo`.
+        async fn delete(&self, resource: Resource, headers: &HeaderMap) -> Result<Response, Response> {
+            let Resource::Todo(_, workspace_id, name) = resource else {
+                return Err(error_response(StatusCode::METHOD_NOT_ALLOWED, "Only todos can be deleted."));
+            };
+            let (todo, role) = self
+                .find_todo(workspace_id, &name)
+                .await?
+                .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "Not found."))?;
+            if !preconditions_hold(headers, Some(&todo.etag())) {
+                return Err(precondition_failed());
+            }
+            if role < ListRole::Owner {
+                return Err(error_response(StatusCode::FORBIDDEN, "Only owners can delete this todo."));
+            }
+
+            let id = todo.todo.id;
+            let mut tx = self.pool.begin().await.map_err(internal_error)?;
+            sqlx::query(&format!(
+                "{TODO_SUBTREE} UPDATE todos SET deleted_at = CURRENT_TIMESTAMP
+                WHERE id IN (SELECT id FROM tree) AND deleted_at IS NULL"
+            ))
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+
+            record_todo(&mut tx, Some(self.user.id), AuditAction::TodoDeleted, id, Some(&todo.todo))
+                .await
+                .map_err(internal_error)?;
+            tx.commit().await.map_err(internal_error)?;
+            self.publish(AuditAction::TodoDeleted, &todo.todo);
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+    }
+
+    /// Whether the `If-Match` and `If-None-Match: *` headers allow writing to
+    /// the todo with the ETag `existing`, or a new one if there is none.
+    fn preconditions_hold(headers: &HeaderMap, existing: Option<&str>) -> bool {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        match (header(header::IF_MATCH), header(header::IF_NONE_MATCH), existing) {
+            (Some(etag), _, Some(existing)) => etag == "*" || etag == existing,
+            (Some(_), _, None) => false,
+            (_, Some("*"), Some(_)) => false,
+            _ => true,
+        }
+    }
+
+    fn precondition_failed() -> Response {
+        error_response(StatusCode::PRECONDITION_FAILED, "The todo has changed in the meantime.")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::todo_app::ssr::test_pool;
+        use axum::http::HeaderValue;
+
+        const VTODO: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTODO\r\nUID:abc@client\r\n\
+            SUMMARY:Buy milk\\, eggs\\; and a very long title that the client\r\n  folded onto a second line\r\n\
+            DESCRIPTION:First\\nSecond \\\\ done\r\nDUE;VALUE=DATE:20240131\r\nSTATUS:COMPLETED\r\n\
+            PRIORITY:1\r\nRRULE:FREQ=WEEKLY;BYDAY=MO\r\nBEGIN:VALARM\r\nACTION:DISPLAY\r\n\
+            DESCRIPTION:Reminder\r\nEND:VALARM\r\nX-CLIENT-THING:kept out\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+
+        #[test]
+        fn parses_vtodos() {
+            let todo = parse_vtodo(VTODO).unwrap();
+            assert_eq!(todo.uid, "abc@client");
+            assert_eq!(
+                todo.summary,
+                "Buy milk, eggs; and a very long title that the client folded onto a second line"
+            );
+            // The alarm's description is the alarm's.
+            assert_eq!(todo.description, "First\nSecond \\ done");
+            assert_eq!(todo.due, NaiveDate::from_ymd_opt(2024, 1, 31).map(|date| date.and_time(NaiveTime::MIN)));
+            assert!(todo.completed);
+            assert_eq!(todo.priority, Priority::Urgent);
+            assert_eq!(todo.rrule.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO"));
+        }
+
+        #[test]
+        fn rejects_calendars_without_a_usable_vtodo() {
+            assert!(parse_vtodo("BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nEND:VCALENDAR").is_none());
+            assert!(parse_vtodo("BEGIN:VTODO\nSUMMARY:No UID\nEND:VTODO").is_none());
+            assert!(parse_vtodo("BEGIN:VTODO\nUID:1\nSUMMARY:Never ends").is_none());
+        }
+
+        #[test]
+        fn unescapes_text() {
+            assert_eq!(unescape(r"a\,b\;c\nd\Ne\\f"), "a,b;c\nd\ne\\f");
+            assert_eq!(unescape(r"trailing\"), "trailing");
+            assert_eq!(unescape("plain: text"), "plain: text");
+        }
+
+        #[test]
+        fn parses_date_times() {
+            let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+            assert_eq!(parse_date_time("20240131"), Some(date.and_time(NaiveTime::MIN)));
+            assert_eq!(parse_date_time("20240131T093000"), date.and_hms_opt(9, 30, 0));
+            let utc = Utc.from_utc_datetime(&date.and_hms_opt(9, 30, 0).unwrap());
+            assert_eq!(parse_date_time("20240131T093000Z"), Some(utc.with_timezone(&Local).naive_local()));
+            assert_eq!(parse_date_time("2024-01-31"), None);
+            assert_eq!(parse_priority(0), Priority::None);
+            assert_eq!(parse_priority(5), Priority::Medium);
+            assert_eq!(parse_priority(9), Priority::Low);
+        }
+
+        #[test]
+        fn parses_resources() {
+            assert_eq!(Resource::parse("/dav"), Some(Resource::Root));
+            assert_eq!(Resource::parse("/dav/"), Some(Resource::Root));
+            assert_eq!(Resource::parse("/dav/alice/"), Some(Resource::Home("alice".to_string())));
+            assert_eq!(Resource::parse("/dav/al%20ice/4/"), Some(Resource::Calendar("al ice".to_string(), 4)));
+            assert_eq!(
+                Resource::parse("/dav/alice/4/a%2Fb%zz.ics"),
+                Some(Resource::Todo("alice".to_string(), 4, "a/b%zz.ics".to_string()))
+            );
+            assert_eq!(Resource::parse("/dav/alice/home/"), None);
+            assert_eq!(Resource::parse("/dav/alice/4/todo.ics/more"), None);
+            assert_eq!(Resource::parse("/api/dav"), None);
+        }
+
+        #[test]
+        fn round_trips_segments() {
+            for segment in ["alice", "a b/c?d", "ünïcode", "100%"] {
+                assert_eq!(decode_segment(&encode_segment(segment)), segment);
+            }
+            assert_eq!(encode_segment("a b~c"), "a%20b~c");
+        }
+
+        #[test]
+        fn finds_hrefs_whatever_the_prefix() {
+            let body = "<C:calendar-multiget xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">\
+                <D:prop><D:getetag/></D:prop><D:href>/dav/alice/4/1.ics</D:href>\
+                <href xmlns=\"DAV:\"> /dav/alice/4/a&amp;b.ics </href></C:calendar-multiget>";
+            assert_eq!(hrefs(body), ["/dav/alice/4/1.ics", "/dav/alice/4/a&b.ics"]);
+        }
+
+        #[test]
+        fn checks_preconditions() {
+            let headers = |pairs: &[(header::HeaderName, &'static str)]| {
+                pairs
+                    .iter()
+                    .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+                    .collect::<HeaderMap>()
+            };
+            let none = headers(&[]);
+            assert!(preconditions_hold(&none, None));
+            assert!(preconditions_hold(&none, Some("\"1\"")));
+
+            let if_match = headers(&[(header::IF_MATCH, "\"1\"")]);
+            assert!(preconditions_hold(&if_match, Some("\"1\"")));
+            assert!(!preconditions_hold(&if_match, Some("\"2\"")));
+            assert!(!preconditions_hold(&if_match, None));
+
+            let any = headers(&[(header::IF_MATCH, "*")]);
+            assert!(preconditions_hold(&any, Some("\"2\"")));
+            assert!(!preconditions_hold(&any, None));
+
+            let create_only = headers(&[(header::IF_NONE_MATCH, "*")]);
+            assert!(preconditions_hold(&create_only, None));
+            assert!(!preconditions_hold(&create_only, Some("\"1\"")));
+        }
+
+        struct Client {
+            pool: SqlitePool,
+            changes: ChangeHub,
+            workspace_id: i64,
+        }
+
+        impl Client {
+            async fn new() -> Self {
+                let pool = test_pool().await;
+                let user_id = sqlx::query_scalar::<_, i64>(
+                    "INSERT INTO users (username, password) VALUES ('alice', ?) RETURNING id",
+                )
+                .bind(bcrypt::hash("secret", 4).unwrap())
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+                let workspace_id = sqlx::query_scalar::<_, i64>(
+                    "INSERT INTO workspaces (name, created_by) VALUES ('Home & <work>', ?) RETURNING id",
+                )
+                .bind(user_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+                sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES (?, ?, 'admin')")
+                    .bind(workspace_id)
+                    .bind(user_id)
+                    .execute(&pool)
+                    .await
+                    .unwrap();
+                Self {
+                    pool,
+                    changes: ChangeHub::default(),
+                    workspace_id,
+                }
+            }
+
+            async fn send(
+                &self,
+                method: &str,
+                path: &str,
+                extra: &[(&'static str, String)],
+                body: &str,
+            ) -> (StatusCode, HeaderMap, String) {
+                let mut headers = HeaderMap::new();
+                let credentials = format!("Basic {}", STANDARD.encode("alice:secret"));
+                headers.insert(header::AUTHORIZATION, credentials.parse().unwrap());
+                for (name, value) in extra {
+                    headers.insert(*name, value.parse().unwrap());
+                }
+                let response = caldav(
+                    State(self.pool.clone()),
+                    State(self.changes.clone()),
+                    method.parse().unwrap(),
+                    path.replace("{workspace}", &self.workspace_id.to_string()).parse().unwrap(),
+                    headers,
+                    Bytes::from(body.to_string()),
+                )
+                .await;
+                let status = response.status();
+                let headers = response.headers().clone();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, headers, String::from_utf8(body.to_vec()).unwrap())
+            }
+        }
+
+        #[tokio::test]
+        async fn asks_for_the_right_password() {
+            let client = Client::new().await;
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, format!("Basic {}", STANDARD.encode("alice:wrong")).parse().unwrap());
+            assert!(authenticate(&headers, &client.pool).await.is_none());
+            headers.insert(header::AUTHORIZATION, format!("Basic {}", STANDARD.encode("alice:secret")).parse().unwrap());
+            assert_eq!(authenticate(&headers, &client.pool).await.map(|user| user.username), Some("alice".to_string()));
+            assert!(authenticate(&HeaderMap::new(), &client.pool).await.is_none());
+        }
+
+        #[tokio::test]
+        async fn syncs_todos() {
+            let client = Client::new().await;
+            let href = "/dav/alice/{workspace}/new%20todo.ics";
+
+            let (status, headers, _) = client
+                .send("PUT", href, &[("if-none-match", "*".to_string())], VTODO)
+                .await;
+            assert_eq!(status, StatusCode::CREATED);
+            let etag = headers[header::ETAG].to_str().unwrap().to_string();
+
+            let (status, ..) = client.send("PUT", href, &[("if-none-match", "*".to_string())], VTODO).await;
+            assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+
+            let (status, headers, ics) = client.send("GET", href, &[], "").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(headers[header::ETAG], etag.as_str());
+            assert!(ics.contains("UID:abc@client\r\n"));
+            assert!(ics.contains("STATUS:COMPLETED\r\n"));
+
+            let (status, _, xml) = client
+                .send("PROPFIND", "/dav/alice/{workspace}/", &[("depth", "1".to_string())], "")
+                .await;
+            assert_eq!(status, StatusCode::MULTI_STATUS);
+            assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"DAV:\""));
+            assert!(xml.contains("<d:displayname>Home &amp; &lt;work&gt;</d:displayname>"));
+            assert!(xml.contains(&format!(
+                "<d:response><d:href>/dav/alice/{}/new%20todo.ics</d:href><d:propstat><d:prop>\
+                <d:resourcetype/><d:getetag>{etag}</d:getetag>",
+                client.workspace_id
+            )));
+            assert_eq!(xml.matches("<d:response>").count(), 2);
+
+            let (status, _, xml) = client
+                .send("PROPFIND", "/dav/alice/{workspace}/", &[("depth", "0".to_string())], "")
+                .await;
+            assert_eq!(status, StatusCode::MULTI_STATUS);
+            assert_eq!(xml.matches("<d:response>").count(), 1);
+
+            let multiget = format!(
+                "<c:calendar-multiget xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\">\
+                <d:href>/dav/alice/{0}/new%20todo.ics</d:href><d:href>/dav/alice/{0}/gone.ics</d:href>\
+                </c:calendar-multiget>",
+                client.workspace_id
+            );
+            let (status, _, xml) = client.send("REPORT", "/dav/alice/{workspace}/", &[], &multiget).await;
+            assert_eq!(status, StatusCode::MULTI_STATUS);
+            assert_eq!(xml.matches("<d:response>").count(), 1);
+            assert!(xml.contains("<c:calendar-data>BEGIN:VCALENDAR\r\n"));
+            assert!(xml.contains("SUMMARY:Buy milk\\, eggs\\; and a very long title"));
+
+            let stale = [("if-match", "\"0000000000000000\"".to_string())];
+            let (status, ..) = client.send("PUT", href, &stale, &VTODO.replace("PRIORITY:1", "PRIORITY:9")).await;
+            assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+            let (status, headers, _) = client
+                .send("PUT", href, &[("if-match", etag.clone())], &VTODO.replace("PRIORITY:1", "PRIORITY:9"))
+                .await;
+            assert_eq!(status, StatusCode::NO_CONTENT);
+            assert_ne!(headers[header::ETAG], etag.as_str());
+
+            let (status, ..) = client.send("DELETE", href, &[("if-match", etag)], "").await;
+            assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+            let (status, ..) = client.send("DELETE", href, &[], "").await;
+            assert_eq!(status, StatusCode::NO_CONTENT);
+            let (status, ..) = client.send("GET", href, &[], "").await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+
+        #[tokio::test]
+        async fn keeps_to_the_users_own_calendars() {
+            let client = Client::new().await;
+            let (status, ..) = client.send("PROPFIND", "/dav/bob/", &[], "").await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            let (status, ..) = client.send("PUT", "/dav/alice/{workspace}/7.ics", &[], VTODO).await;
+            assert_eq!(status, StatusCode::CONFLICT);
+            let (status, ..) = client.send("PUT", "/dav/alice/999/new.ics", &[], VTODO).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use leptos::ev::DragEvent;
+use leptos::prelude::*;
+use thaw::*;
+use thaw_utils::mount_style;
+
+use super::live::{provide_todo_changes, use_todo_changes};
+use super::Todo;
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+/// Longest range of days the calendar loads at once, enough for six weeks.
+pub const CALENDAR_MAX_DAYS: i64 = 42;
+
+/// Most undated todos the sidebar lists.
+pub const UNDATED_LIMIT: i64 = 100;
+
+/// Whether the calendar shows a whole month or a single week.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalendarView {
+    Month,
+    Week,
+}
+
+impl CalendarView {
+    /// The days shown around `date`, whole weeks from Monday to Sunday.
+    pub fn days(&self, date: NaiveDate) -> Vec<NaiveDate> {
+        let (first, weeks) = match self {
+            CalendarView::Month => (date.with_day(1).unwrap_or(date), 6),
+            CalendarView::Week => (date, 1),
+        };
+        let monday = first - Days::new(first.weekday().num_days_from_monday().into());
+        monday.iter_days().take(weeks * 7).collect()
+    }
+
+    /// Where "Previous" and "Next" go from `date`.
+    pub fn step(&self, date: NaiveDate, forward: bool) -> NaiveDate {
+        let moved = match (self, forward) {
+            (CalendarView::Month, true) => date.checked_add_months(Months::new(1)),
+            (CalendarView::Month, false) => date.checked_sub_months(Months::new(1)),
+            (CalendarView::Week, true) => date.checked_add_days(Days::new(7)),
+            (CalendarView::Week, false) => date.checked_sub_days(Days::new(7)),
+        };
+        moved.unwrap_or(date)
+    }
+
+    pub fn title(&self, date: NaiveDate) -> String {
+        match self {
+            CalendarView::Month => date.format("%B %Y").to_string(),
+            CalendarView::Week => {
+                let days = self.days(date);
+                format!("Week {} of {}", days[0].iso_week().week(), days[0].iso_week().year())
+            }
+        }
+    }
+}
+
+/// The todos the current user can see in the current workspace that are due
+/// on or after `from` and before `to`.
+#[server(GetCalendarTodos, "/api")]
+pub async fn get_calendar_todos(
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<Todo>, ServerFnError<TodoAppError>> {
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+    use chrono::NaiveTime;
+    use futures::future::join_all;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(Vec::new());
+    };
+    if (to - from).num_days() > CALENDAR_MAX_DAYS {
+        return Err(ServerFnError::ServerError(format!(
+            "The calendar shows at most {CALENDAR_MAX_DAYS} days at a time."
+        )));
+    }
+
+    let todos = sqlx::query_as::<_, SqlTodo>(
+        "SELECT * FROM todos
+        WHERE (?1 OR (list_id IS NULL AND user_id = ?2)
+            OR list_id IN (SELECT list_id FROM list_members WHERE user_id = ?2))
+        AND workspace_id = ?3 AND deleted_at IS NULL AND due_at >= ?4 AND due_at < ?5
+        ORDER BY due_at, id",
+    )
+    .bind(is_todos_admin(&user, &pool).await)
+    .bind(user.id)
+    .bind(current_workspace(&user, &pool).await?)
+    .bind(from.and_time(NaiveTime::MIN))
+    .bind(to.and_time(NaiveTime::MIN))
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(join_all(todos.into_iter().map(|todo| todo.into_todo(&pool))).await)
+}
+
+/// Open todos without a due date, newest first, to be dropped onto the calendar.
+#[server(GetUndatedTodos, "/api")]
+pub async fn get_undated_todos() -> Result<Vec<Todo>, ServerFnError<TodoAppError>> {
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+    use futures::future::join_all;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(Vec::new());
+    };
+
+    let todos = sqlx::query_as::<_, SqlTodo>(
+        "SELECT * FROM todos
+        WHERE (?1 OR (list_id IS NULL AND user_id = ?2)
+            OR list_id IN (SELECT list_id FROM list_members WHERE user_id = ?2))
+        AND workspace_id = ?3 AND deleted_at IS NULL AND due_at IS NULL AND NOT completed
+        ORDER BY id DESC LIMIT ?4",
+    )
+    .bind(is_todos_admin(&user, &pool).await)
+    .bind(user.id)
+    .bind(current_workspace(&user, &pool).await?)
+    .bind(UNDATED_LIMIT)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(join_all(todos.into_iter().map(|todo| todo.into_todo(&pool))).await)
+}
+
+/// Moves the due date of a todo to another day, keeping its time of day.
+/// A reminder moves along with it.
+#[server(RescheduleTodo, "/api")]
+pub async fn reschedule_todo(id: u32, date: NaiveDate) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+    use chrono::NaiveTime;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    let due_at = date.and_time(todo.due_at.map(|due| due.time()).unwrap_or(NaiveTime::MIN));
+    let remind_at = match (todo.remind_at, todo.due_at) {
+        (Some(remind), Some(due)) => Some(due_at + (remind - due)),
+        (remind, _) => remind,
+    };
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todos SET due_at = ?, remind_at = ? WHERE id = ?")
+        .bind(due_at)
+        .bind(remind_at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoUpdated, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// A todo on the calendar or in the sidebar, dragged onto a day to reschedule it.
+#[component]
+fn CalendarTodo(todo: Todo, dragging: RwSignal<Option<u32>>) -> impl IntoView {
+    let id = todo.id;
+    let title = todo.title.clone();
+    let today = Local::now().date_naive();
+    let class = if todo.completed {
+        "todo-calendar-todo todo-calendar-done"
+    } else if todo.is_overdue(today) {
+        "todo-calendar-todo todo-overdue"
+    } else {
+        "todo-calendar-todo"
+    };
+
+    view! {
+        <div
+            class=class
+            title=title
+            draggable="true"
+            on:dragstart=move |_| dragging.set(Some(id))
+            on:dragend=move |_| dragging.set(None)
+        >
+            {todo.title}
+        </div>
+    }
+}
+
+/// Todos laid out on a month or week by their due dates, with the undated
+/// ones in a sidebar. Dropping a todo on a day reschedules it.
+#[component]
+pub fn TodoCalendar() -> impl IntoView {
+    mount_style("todos", include_str!("./todos.css"));
+    provide_todo_changes();
+    let changes = use_todo_changes();
+    let reschedule_todo = ServerAction::<RescheduleTodo>::new();
+    let today = Local::now().date_naive();
+    let date = RwSignal::new(today);
+    let tab = RwSignal::new("month".to_string());
+    let dragging = RwSignal::new(None::<u32>);
+    let drop_target = RwSignal::new(None::<NaiveDate>);
+
+    let view_kind = Memo::new(move |_| {
+        if tab.get() == "week" {
+            CalendarView::Week
+        } else {
+            CalendarView::Month
+        }
+    });
+    let days = Memo::new(move |_| view_kind.get().days(date.get()));
+
+    let todos = Resource::new(
+        move || (days.get(), reschedule_todo.version().get(), changes.get()),
+        move |(days, ..)| {
+            let from = days.first().copied().unwrap_or(today);
+            let to = days.last().copied().unwrap_or(today) + Days::new(1);
+            get_calendar_todos(from, to)
+        },
+    );
+    let undated = Resource::new(
+        move || (reschedule_todo.version().get(), changes.get()),
+        move |_| get_undated_todos(),
+    );
+
+    let error = move || {
+        reschedule_todo
+            .value()
+            .get()
+            .and_then(Result::err)
+            .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Flex align=FlexAlign::Center justify=FlexJustify::SpaceBetween>
+                <Flex align=FlexAlign::Center>
+                    <Button
+                        appearance=ButtonAppearance::Subtle
+                        icon=icondata::LuChevronLeft
+                        on_click=move |_| date.update(|date| *date = view_kind.get_untracked().step(*date, false))
+                    />
+                    <Button on_click=move |_| date.set(Local::now().date_naive())>"Today"</Button>
+                    <Button
+                        appearance=ButtonAppearance::Subtle
+                        icon=icondata::LuChevronRight
+                        on_click=move |_| date.update(|date| *date = view_kind.get_untracked().step(*date, true))
+                    />
+                    <Text>{move || view_kind.get().title(date.get())}</Text>
+                </Flex>
+                <TabList selected_value=tab>
+                    <Tab value="month">"Month"</Tab>
+                    <Tab value="week">"Week"</Tab>
+                </TabList>
+            </Flex>
+            {error}
+            <div class="todo-calendar-layout">
+                <Card class="todo-calendar-sidebar">
+                    <Caption1Strong>"No due date"</Caption1Strong>
+                    <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                        {move || {
+                            undated.get().map(|todos| match todos {
+                                Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                                Ok(todos) if todos.is_empty() => view! { <Text>"Everything has a due date."</Text> }.into_any(),
+                                Ok(todos) => todos
+                                    .into_iter()
+                                    .map(|todo| view! { <CalendarTodo todo dragging /> })
+                                    .collect_view()
+                                    .into_any(),
+                            })
+                        }}
+                    </Transition>
+                </Card>
+                <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                    <ErrorBoundary fallback=|errors| {
+                        view! { <ErrorTemplate errors=errors/> }
+                    }>
+                        <div class="todo-calendar">
+                            {["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+                                .map(|weekday| view! { <Caption1Strong class="todo-calendar-weekday">{weekday}</Caption1Strong> })
+                                .into_iter()
+                                .collect_view()}
+                            {move || {
+                                todos.get().map(|todos| todos.map(|todos| {
+                                    let month = date.get().month();
+                                    let is_month = view_kind.get() == CalendarView::Month;
+                                    days.get()
+                                        .into_iter()
+                                        .map(|day| {
+                                            let class = if day == today {
+                                                "todo-calendar-day todo-calendar-today"
+                                            } else if is_month && day.month() != month {
+                                                "todo-calendar-day todo-calendar-outside"
+                                            } else {
+                                                "todo-calendar-day"
+                                            };
+                                            let due = todos
+                                                .iter()
+                                                .filter(|todo| todo.due_at.is_some_and(|due| due.date() == day))
+                                                .cloned()
+                                                .collect::<Vec<_>>();
+                                            view! {
+                                                <div
+                                                    class=class
+                                                    class:todo-calendar-drop=move || drop_target.get() == Some(day)
+                                                    on:dragover=move |ev: DragEvent| {
+                                                        if dragging.get_untracked().is_some() {
+                                                            ev.prevent_default();
+                                                            drop_target.set(Some(day));
+                                                        }
+                                                    }
+                                                    on:dragleave=move |_| drop_target.set(None)
+                                                    on:drop=move |ev: DragEvent| {
+                                                        ev.prevent_default();
+                                                        if let Some(id) = dragging.get_untracked() {
+                                                            reschedule_todo.dispatch(RescheduleTodo { id, date: day });
+                                                        }
+                                                        dragging.set(None);
+                                                        drop_target.set(None);
+                                                    }
+                                                >
+                                                    <Caption1>{day.day()}</Caption1>
+                                                    {due
+                                                        .into_iter()
+                                                        .map(|todo| view! { <CalendarTodo todo dragging /> })
+                                                        .collect_view()}
+                                                </div>
+                                            }
+                                        })
+                                        .collect_view()
+                                }))
+                            }}
+                        </div>
+                    </ErrorBoundary>
+                </Transition>
+            </div>
+        </Flex>
+    }
+}
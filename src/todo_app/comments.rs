@@ -0,0 +1,554 @@
+use chrono::NaiveDateTime;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use thaw::*;
+
+use super::live::use_todo_changes;
+use crate::{auth::User, errors::TodoAppError};
+
+/// Longest comment that can be posted, in characters.
+pub const MAX_COMMENT_LEN: usize = 5_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoComment {
+    pub id: i64,
+    pub todo_id: u32,
+    pub author: Option<User>,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub edited_at: Option<NaiveDateTime>,
+    /// Usernames mentioned in `body` that belong to someone who can see the todo.
+    pub mentions: Vec<String>,
+    /// Whether the current user wrote it, and so may edit it.
+    pub is_own: bool,
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+/// Byte ranges of the `@username` mentions in `text`, including the `@`.
+/// An `@` inside a word, as in an email address, doesn't start a mention.
+pub fn mention_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut previous = None;
+    for (start, c) in text.char_indices() {
+        if c == '@' && !previous.is_some_and(is_username_char) {
+            let name = &text[start + 1..];
+            let len = name.find(|c| !is_username_char(c)).unwrap_or(name.len());
+            // Punctuation right after a name ends the sentence, not the name.
+            let name = name[..len].trim_end_matches(['.', '-']);
+            if !name.is_empty() {
+                spans.push(start..start + 1 + name.len());
+            }
+        }
+        previous = Some(c);
+    }
+    spans
+}
+
+/// The distinct usernames mentioned in `text`, in order of appearance.
+pub fn mentioned_usernames(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for span in mention_spans(text) {
+        let name = &text[span.start + 1..span.end];
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{mentioned_usernames, TodoComment, MAX_COMMENT_LEN};
+    use crate::auth::User;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::ssr::{todo_role, SqlTodo};
+    use chrono::NaiveDateTime;
+    use leptos::prelude::*;
+    use sqlx::{Sqlite, SqlitePool, Transaction};
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlTodoComment {
+        pub id: i64,
+        pub todo_id: u32,
+        pub user_id: i64,
+        pub body: String,
+        pub created_at: NaiveDateTime,
+        pub edited_at: Option<NaiveDateTime>,
+    }
+
+    impl SqlTodoComment {
+        pub async fn into_comment(self, current_user: &User, pool: &SqlitePool) -> TodoComment {
+            let mentions = sqlx::query_scalar::<_, String>(
+                "SELECT users.username FROM todo_comment_mentions
+                JOIN users ON users.id = todo_comment_mentions.user_id
+                WHERE todo_comment_mentions.comment_id = ?",
+            )
+            .bind(self.id)
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default();
+
+            TodoComment {
+                id: self.id,
+                todo_id: self.todo_id,
+                author: User::get(self.user_id, pool).await,
+                body: self.body,
+                created_at: self.created_at,
+                edited_at: self.edited_at,
+                mentions,
+                is_own: self.user_id == current_user.id,
+            }
+        }
+    }
+
+    pub fn validate_body(body: &str) -> Result<(), ServerFnError<TodoAppError>> {
+        if body.trim().is_empty() {
+            Err(ServerFnError::ServerError("A comment can't be empty.".to_string()))
+        } else if body.chars().count() > MAX_COMMENT_LEN {
+            Err(ServerFnError::ServerError(format!(
+                "Comments can be at most {MAX_COMMENT_LEN} characters long."
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn comment(id: i64, pool: &SqlitePool) -> Result<SqlTodoComment, ServerFnError<TodoAppError>> {
+        Ok(sqlx::query_as::<_, SqlTodoComment>("SELECT * FROM todo_comments WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .ok_or(TodoAppError::NotFound)?)
+    }
+
+    /// The users mentioned in `body` who can see `todo`. Mentions notify
+    /// people, so nobody outside the todo's list or workspace is mentioned.
+    pub async fn mentioned_users(
+        todo: &SqlTodo,
+        body: &str,
+        pool: &SqlitePool,
+    ) -> Result<Vec<User>, sqlx::Error> {
+        let mut users = Vec::new();
+        for username in mentioned_usernames(body) {
+            // Names that don't belong to anyone simply don't match a user.
+            let Some(user) = User::get_from_username(username, pool).await else {
+                continue;
+            };
+            if todo_role(todo, &user, pool).await?.is_some() {
+                users.push(user);
+            }
+        }
+        Ok(users)
+    }
+
+    /// Brings the stored mentions of a comment in line with `mentioned`, see
+    /// [`mentioned_users`]. Mentions that were already there keep their row, so
+    /// they aren't notified twice.
+    pub async fn store_mentions(
+        comment_id: i64,
+        mentioned: &[User],
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), sqlx::Error> {
+        let stored = sqlx::query_scalar::<_, i64>(
+            "SELECT user_id FROM todo_comment_mentions WHERE comment_id = ?",
+        )
+        .bind(comment_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        for user_id in stored {
+            if !mentioned.iter().any(|user| user.id == user_id) {
+                sqlx::query("DELETE FROM todo_comment_mentions WHERE comment_id = ? AND user_id = ?")
+                    .bind(comment_id)
+                    .bind(user_id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+        }
+
+        for user in mentioned {
+            sqlx::query("INSERT OR IGNORE INTO todo_comment_mentions (comment_id, user_id) VALUES (?, ?)")
+                .bind(comment_id)
+                .bind(user.id)
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::todo_app::ssr::test_pool;
+
+        async fn add_user(username: &str, workspace_id: Option<i64>, pool: &SqlitePool) -> i64 {
+            let id = sqlx::query_scalar("INSERT INTO users (username, password) VALUES (?, '') RETURNING id")
+                .bind(username)
+                .fetch_one(pool)
+                .await
+                .unwrap();
+            let workspace_id = match workspace_id {
+                Some(workspace_id) => workspace_id,
+                None => sqlx::query_scalar("INSERT INTO workspaces (name, created_by) VALUES (?, ?) RETURNING id")
+                    .bind(username)
+                    .bind(id)
+                    .fetch_one(pool)
+                    .await
+                    .unwrap(),
+            };
+            sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES (?, ?, 'member')")
+                .bind(workspace_id)
+                .bind(id)
+                .execute(pool)
+                .await
+                .unwrap();
+            id
+        }
+
+        fn names(users: Vec<User>) -> Vec<String> {
+            users.into_iter().map(|user| user.username).collect()
+        }
+
+        #[tokio::test]
+        async fn mentions_only_who_can_see_the_todo() {
+            let pool = test_pool().await;
+            let alice = add_user("alice", None, &pool).await;
+            let workspace_id: i64 = sqlx::query_scalar("SELECT id FROM workspaces").fetch_one(&pool).await.unwrap();
+            let bob = add_user("bob", Some(workspace_id), &pool).await;
+            add_user("carol", None, &pool).await;
+
+            let list_id: i64 = sqlx::query_scalar("INSERT INTO lists (user_id, name, workspace_id) VALUES (?, 'Shared', ?) RETURNING id")
+                .bind(alice)
+                .bind(workspace_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO list_members (list_id, user_id, role) VALUES (?, ?, 'owner')")
+                .bind(list_id)
+                .bind(alice)
+                .execute(&pool)
+                .await
+                .unwrap();
+            let todo = sqlx::query_as::<_, SqlTodo>(
+                "INSERT INTO todos (user_id, title, completed, list_id, workspace_id) VALUES (?, 'a', false, ?, ?) RETURNING *",
+            )
+            .bind(alice)
+            .bind(list_id)
+            .bind(workspace_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+            let body = "@alice @bob @carol @nobody";
+            assert_eq!(names(mentioned_users(&todo, body, &pool).await.unwrap()), ["alice"]);
+
+            // Bob is in the workspace already, sharing the list lets him see it.
+            sqlx::query("INSERT INTO list_members (list_id, user_id, role) VALUES (?, ?, 'viewer')")
+                .bind(list_id)
+                .bind(bob)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert_eq!(names(mentioned_users(&todo, body, &pool).await.unwrap()), ["alice", "bob"]);
+        }
+    }
+}
+
+/// The comments on a todo, oldest first.
+#[server(GetComments, "/api")]
+pub async fn get_comments(todo_id: u32) -> Result<Vec<TodoComment>, ServerFnError<TodoAppError>> {
+    use self::ssr::SqlTodoComment;
+    use crate::todo_app::ssr::*;
+    use futures::future::join_all;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(todo_id, &user, ListRole::Viewer, &pool).await?;
+
+    let comments = sqlx::query_as::<_, SqlTodoComment>(
+        "SELECT * FROM todo_comments WHERE todo_id = ? ORDER BY created_at, id",
+    )
+    .bind(todo_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    let (user, pool) = (&user, &pool);
+    Ok(join_all(comments.into_iter().map(|comment| comment.into_comment(user, pool))).await)
+}
+
+#[server(AddComment, "/api")]
+pub async fn add_comment(todo_id: u32, body: String) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    validate_body(&body)?;
+    let todo = accessible_todo(todo_id, &user, ListRole::Editor, &pool).await?;
+    let mentioned = mentioned_users(&todo, &body, &pool)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let id = sqlx::query_scalar::<_, i64>(
+        "INSERT INTO todo_comments (todo_id, user_id, body) VALUES (?, ?, ?) RETURNING id",
+    )
+    .bind(todo_id)
+    .bind(user.id)
+    .bind(&body)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    store_mentions(id, &mentioned, &mut tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::CommentAdded,
+        AuditTarget::comment(id, todo_id),
+        None,
+        Some(serde_json::json!({ "body": body })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Only the author of a comment can change what it says.
+#[server(EditComment, "/api")]
+pub async fn edit_comment(id: i64, body: String) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    validate_body(&body)?;
+    let comment = comment(id, &pool).await?;
+    if comment.user_id != user.id {
+        return Err(TodoAppError::Forbidden.into());
+    }
+    let todo = accessible_todo(comment.todo_id, &user, ListRole::Editor, &pool).await?;
+    let mentioned = mentioned_users(&todo, &body, &pool)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todo_comments SET body = ?, edited_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&body)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    store_mentions(id, &mentioned, &mut tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::CommentEdited,
+        AuditTarget::comment(id, comment.todo_id),
+        Some(serde_json::json!({ "body": comment.body })),
+        Some(serde_json::json!({ "body": body })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Comments can be deleted by their author, or by an owner of the todo to moderate.
+#[server(DeleteComment, "/api")]
+pub async fn delete_comment(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let comment = comment(id, &pool).await?;
+    let required = if comment.user_id == user.id { ListRole::Editor } else { ListRole::Owner };
+    accessible_todo(comment.todo_id, &user, required, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM todo_comments WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::CommentDeleted,
+        AuditTarget::comment(id, comment.todo_id),
+        Some(serde_json::json!({ "body": comment.body })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// The comment text, with mentions of existing users highlighted.
+#[component]
+fn CommentBody(body: String, mentions: Vec<String>) -> impl IntoView {
+    let mut parts = Vec::new();
+    let mut rest = 0;
+    for span in mention_spans(&body) {
+        if mentions.iter().any(|name| *name == body[span.start + 1..span.end]) {
+            parts.push(body[rest..span.start].to_string().into_any());
+            parts.push(view! { <strong class="todo-mention">{body[span.clone()].to_string()}</strong> }.into_any());
+            rest = span.end;
+        }
+    }
+    parts.push(body[rest..].to_string().into_any());
+
+    view! { <p class="todo-comment-body">{parts}</p> }
+}
+
+#[component]
+pub fn TodoComments(todo_id: u32) -> impl IntoView {
+    let add_comment = ServerAction::<AddComment>::new();
+    let edit_comment = ServerAction::<EditComment>::new();
+    let delete_comment = ServerAction::<DeleteComment>::new();
+
+    let changes = use_todo_changes();
+    let comments = Resource::new(
+        move || {
+            (
+                add_comment.version().get(),
+                edit_comment.version().get(),
+                delete_comment.version().get(),
+                changes.get(),
+            )
+        },
+        move |_| get_comments(todo_id),
+    );
+
+    let body = RwSignal::new(String::new());
+    let editing = RwSignal::new(None::<i64>);
+    let draft = RwSignal::new(String::new());
+
+    let error = move || {
+        [
+            add_comment.value().get().and_then(Result::err),
+            edit_comment.value().get().and_then(Result::err),
+            delete_comment.value().get().and_then(Result::err),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    comments.get().map(|comments| match comments {
+                        Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                        Ok(comments) if comments.is_empty() => view! { <Text>"No comments yet."</Text> }.into_any(),
+                        Ok(comments) => {
+                            comments
+                                .into_iter()
+                                .map(|comment| {
+                                    let id = comment.id;
+                                    let is_own = comment.is_own;
+                                    let text = comment.body.clone();
+                                    let header = format!(
+                                        "{} · {}{}",
+                                        comment.author.unwrap_or_default().username,
+                                        comment.created_at.format("%Y-%m-%d %H:%M"),
+                                        if comment.edited_at.is_some() { " (edited)" } else { "" },
+                                    );
+                                    view! {
+                                        <Flex vertical=true gap=FlexGap::Small class="todo-comment">
+                                            <Flex align=FlexAlign::Center justify=FlexJustify::SpaceBetween>
+                                                <Caption1Strong>{header}</Caption1Strong>
+                                                <Flex>
+                                                    <Show when=move || is_own>
+                                                        <Button
+                                                            appearance=ButtonAppearance::Subtle
+                                                            icon=icondata::LuPencil
+                                                            on_click={
+                                                                let text = text.clone();
+                                                                move |_| {
+                                                                    draft.set(text.clone());
+                                                                    editing.set(Some(id));
+                                                                }
+                                                            }
+                                                        />
+                                                    </Show>
+                                                    <Button
+                                                        appearance=ButtonAppearance::Subtle
+                                                        icon=icondata::AiCloseCircleOutlined
+                                                        on_click=move |_| {
+                                                            delete_comment.dispatch(DeleteComment { id });
+                                                        }
+                                                    />
+                                                </Flex>
+                                            </Flex>
+                                            <Show
+                                                when=move || editing.get() == Some(id)
+                                                fallback={
+                                                    let body = comment.body.clone();
+                                                    let mentions = comment.mentions.clone();
+                                                    move || view! { <CommentBody body=body.clone() mentions=mentions.clone() /> }
+                                                }
+                                            >
+                                                <Textarea value=draft resize=TextareaResize::Vertical />
+                                                <Flex>
+                                                    <Button
+                                                        appearance=ButtonAppearance::Primary
+                                                        on_click=move |_| {
+                                                            edit_comment.dispatch(EditComment { id, body: draft.get_untracked() });
+                                                            editing.set(None);
+                                                        }
+                                                    >
+                                                        "Save"
+                                                    </Button>
+                                                    <Button on_click=move |_| editing.set(None)>"Cancel"</Button>
+                                                </Flex>
+                                            </Show>
+                                        </Flex>
+                                    }
+                                })
+                                .collect_view()
+                                .into_any()
+                        }
+                    })
+                }}
+            </Transition>
+            {error}
+            <Textarea value=body placeholder="Write a comment, @mention people to loop them in" resize=TextareaResize::Vertical />
+            <Flex>
+                <Button
+                    appearance=ButtonAppearance::Primary
+                    icon=icondata::LuSend
+                    on_click=move |_| {
+                        if !body.get_untracked().trim().is_empty() {
+                            add_comment.dispatch(AddComment { todo_id, body: body.get_untracked() });
+                            body.set(String::new());
+                        }
+                    }
+                >
+                    "Comment"
+                </Button>
+            </Flex>
+        </Flex>
+    }
+}
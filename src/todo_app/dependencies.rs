@@ -0,0 +1,706 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use thaw::*;
+
+use super::live::use_todo_changes;
+use super::search::search_todos;
+use super::UpdateTodo;
+use crate::errors::TodoAppError;
+
+/// How far the dependency graph reaches out from the todo it's drawn for.
+pub const DEPENDENCY_GRAPH_DEPTH: i32 = 3;
+
+const NODE_WIDTH: i32 = 150;
+const NODE_HEIGHT: i32 = 28;
+const COLUMN_GAP: i32 = 40;
+const ROW_GAP: i32 = 12;
+/// Longest title shown inside a node before it's cut short.
+const NODE_TITLE_LEN: usize = 20;
+
+/// Open todos on either side of a todo's dependencies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyCounts {
+    /// Open todos this one is waiting for.
+    pub blocked_by: u32,
+    /// Open todos waiting for this one.
+    pub blocking: u32,
+}
+
+impl DependencyCounts {
+    pub fn is_blocked(&self) -> bool {
+        self.blocked_by > 0
+    }
+}
+
+/// A todo in the dependency graph. `level` is negative for the todos it is
+/// waiting for, positive for those waiting for it and 0 for the todo itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyNode {
+    pub id: u32,
+    pub title: String,
+    pub completed: bool,
+    pub level: i32,
+}
+
+/// `todo_id` is blocked by `blocker_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyEdge {
+    pub todo_id: u32,
+    pub blocker_id: u32,
+}
+
+/// The todos around one todo, following dependencies both ways up to
+/// [`DEPENDENCY_GRAPH_DEPTH`] steps.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    pub nodes: Vec<DependencyNode>,
+    pub edges: Vec<DependencyEdge>,
+}
+
+impl DependencyGraph {
+    /// The todos `todo_id` is directly waiting for.
+    pub fn blockers(&self, todo_id: u32) -> Vec<DependencyNode> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.todo_id == todo_id)
+            .filter_map(|edge| self.nodes.iter().find(|node| node.id == edge.blocker_id))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::DependencyCounts;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::ssr::SqlTodo;
+    use leptos::prelude::*;
+    use sqlx::{Sqlite, SqlitePool, Transaction};
+
+    #[derive(sqlx::FromRow)]
+    pub struct SqlDependencyNode {
+        #[sqlx(flatten)]
+        pub todo: SqlTodo,
+        pub level: i32,
+    }
+
+    pub async fn dependency_counts(todo_id: u32, pool: &SqlitePool) -> DependencyCounts {
+        sqlx::query_as::<_, (u32, u32)>(
+            "SELECT
+                (SELECT COUNT(*) FROM todo_dependencies JOIN todos ON todos.id = todo_dependencies.blocker_id
+                WHERE todo_dependencies.todo_id = ?1 AND NOT todos.completed AND todos.deleted_at IS NULL),
+                (SELECT COUNT(*) FROM todo_dependencies JOIN todos ON todos.id = todo_dependencies.todo_id
+                WHERE todo_dependencies.blocker_id = ?1 AND NOT todos.completed AND todos.deleted_at IS NULL)",
+        )
+        .bind(todo_id)
+        .fetch_one(pool)
+        .await
+        .map(|(blocked_by, blocking)| DependencyCounts { blocked_by, blocking })
+        .unwrap_or_default()
+    }
+
+    /// Titles of the open todos that `todo_id` is waiting for.
+    pub async fn open_blockers(todo_id: u32, pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar::<_, String>(
+            "SELECT todos.title FROM todo_dependencies JOIN todos ON todos.id = todo_dependencies.blocker_id
+            WHERE todo_dependencies.todo_id = ? AND NOT todos.completed AND todos.deleted_at IS NULL
+            ORDER BY todos.id",
+        )
+        .bind(todo_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Fails while `todo_id` is still waiting for open todos, naming them.
+    pub async fn ensure_unblocked(todo_id: u32, pool: &SqlitePool) -> Result<(), ServerFnError<TodoAppError>> {
+        let blockers = open_blockers(todo_id, pool)
+            .await
+            .map_err(TodoAppError::from)?;
+        if blockers.is_empty() {
+            return Ok(());
+        }
+        Err(ServerFnError::ServerError(format!(
+            "Blocked by {}. Complete those first.",
+            blockers
+                .iter()
+                .map(|title| format!("\"{title}\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )))
+    }
+
+    /// Makes `todo_id` wait for `blocker_id`, unless `blocker_id` already waits
+    /// for `todo_id`. Returns whether it did. `tx` should hold the write lock
+    /// from the start, or two requests adding opposite dependencies could both
+    /// find no cycle.
+    pub async fn insert_dependency(
+        todo_id: u32,
+        blocker_id: u32,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<bool, sqlx::Error> {
+        if waits_for(blocker_id, todo_id, &mut **tx).await? {
+            return Ok(false);
+        }
+        sqlx::query("INSERT OR IGNORE INTO todo_dependencies (todo_id, blocker_id) VALUES (?, ?)")
+            .bind(todo_id)
+            .bind(blocker_id)
+            .execute(&mut **tx)
+            .await?;
+        Ok(true)
+    }
+
+    /// Whether `todo_id` already waits for `blocker_id`, directly or through
+    /// other todos. Trashed todos count too, since they can be restored.
+    pub async fn waits_for<'c, E>(todo_id: u32, blocker_id: u32, executor: E) -> Result<bool, sqlx::Error>
+    where
+        E: sqlx::Executor<'c, Database = Sqlite>,
+    {
+        sqlx::query_scalar::<_, bool>(
+            "WITH RECURSIVE upstream (id) AS (
+                SELECT blocker_id FROM todo_dependencies WHERE todo_id = ?
+                UNION
+                SELECT todo_dependencies.blocker_id FROM todo_dependencies
+                JOIN upstream ON todo_dependencies.todo_id = upstream.id
+            )
+            SELECT EXISTS (SELECT 1 FROM upstream WHERE id = ?)",
+        )
+        .bind(todo_id)
+        .bind(blocker_id)
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Every todo within `depth` steps of `todo_id`, placed at the longest
+    /// distance it's reachable at so that edges always point the same way.
+    pub async fn dependency_nodes(
+        todo_id: u32,
+        depth: i32,
+        pool: &SqlitePool,
+    ) -> Result<Vec<SqlDependencyNode>, sqlx::Error> {
+        sqlx::query_as::<_, SqlDependencyNode>(
+            "WITH RECURSIVE upstream (id, depth) AS (
+                SELECT blocker_id, 1 FROM todo_dependencies WHERE todo_id = ?1
+                UNION
+                SELECT todo_dependencies.blocker_id, upstream.depth + 1 FROM todo_dependencies
+                JOIN upstream ON todo_dependencies.todo_id = upstream.id
+                WHERE upstream.depth < ?2
+            ), downstream (id, depth) AS (
+                SELECT todo_id, 1 FROM todo_dependencies WHERE blocker_id = ?1
+                UNION
+                SELECT todo_dependencies.todo_id, downstream.depth + 1 FROM todo_dependencies
+                JOIN downstream ON todo_dependencies.blocker_id = downstream.id
+                WHERE downstream.depth < ?2
+            ), graph (id, level) AS (
+                SELECT id, -MAX(depth) FROM upstream GROUP BY id
+                UNION ALL
+                SELECT id, MAX(depth) FROM downstream GROUP BY id
+                UNION ALL
+                SELECT ?1, 0
+            )
+            SELECT todos.*, graph.level FROM graph JOIN todos ON todos.id = graph.id
+            WHERE todos.deleted_at IS NULL
+            ORDER BY graph.level, todos.id",
+        )
+        .bind(todo_id)
+        .bind(depth)
+        .fetch_all(pool)
+        .await
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::todo_app::ssr::test_pool;
+
+        async fn add_todo(title: &str, pool: &SqlitePool) -> u32 {
+            sqlx::query_scalar("INSERT INTO todos (user_id, title, completed) VALUES (1, ?, false) RETURNING id")
+                .bind(title)
+                .fetch_one(pool)
+                .await
+                .unwrap()
+        }
+
+        async fn depend(todo_id: u32, blocker_id: u32, pool: &SqlitePool) -> bool {
+            let mut tx = pool.begin_with("BEGIN IMMEDIATE").await.unwrap();
+            let added = insert_dependency(todo_id, blocker_id, &mut tx).await.unwrap();
+            tx.commit().await.unwrap();
+            added
+        }
+
+        #[tokio::test]
+        async fn rejects_cycles() {
+            let pool = test_pool().await;
+            let [a, b, c] = [add_todo("a", &pool).await, add_todo("b", &pool).await, add_todo("c", &pool).await];
+
+            assert!(depend(a, b, &pool).await);
+            assert!(!depend(b, a, &pool).await);
+            assert!(depend(b, c, &pool).await);
+            assert!(!depend(c, a, &pool).await);
+            // Shortcuts along the way are no cycle.
+            assert!(depend(a, c, &pool).await);
+
+            let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM todo_dependencies")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+            assert_eq!(count, 3);
+            assert!(waits_for(a, c, &pool).await.unwrap());
+            assert!(!waits_for(c, a, &pool).await.unwrap());
+        }
+
+        #[tokio::test]
+        async fn opposite_dependencies_added_at_once_leave_no_cycle() {
+            let path = std::env::temp_dir().join(format!("todos-dependencies-{}.db", std::process::id()));
+            let options = sqlx::sqlite::SqliteConnectOptions::new()
+                .filename(&path)
+                .create_if_missing(true);
+            let pool = SqlitePool::connect_with(options).await.unwrap();
+            sqlx::migrate!().run(&pool).await.unwrap();
+            let [a, b] = [add_todo("a", &pool).await, add_todo("b", &pool).await];
+
+            let (first, second) = tokio::join!(depend(a, b, &pool), depend(b, a, &pool));
+            assert!(first != second);
+            assert!(!(waits_for(a, b, &pool).await.unwrap() && waits_for(b, a, &pool).await.unwrap()));
+
+            pool.close().await;
+            let _ = std::fs::remove_file(path);
+        }
+
+        #[tokio::test]
+        async fn blocks_completion_until_blockers_are_done() {
+            let pool = test_pool().await;
+            let [todo, blocker] = [add_todo("todo", &pool).await, add_todo("blocker", &pool).await];
+            assert!(depend(todo, blocker, &pool).await);
+
+            let Err(ServerFnError::ServerError(message)) = ensure_unblocked(todo, &pool).await else {
+                panic!("a blocked todo was let through");
+            };
+            assert_eq!(message, "Blocked by \"blocker\". Complete those first.");
+            assert!(ensure_unblocked(blocker, &pool).await.is_ok());
+            assert_eq!(
+                dependency_counts(todo, &pool).await,
+                DependencyCounts { blocked_by: 1, blocking: 0 }
+            );
+
+            sqlx::query("UPDATE todos SET completed = true WHERE id = ?")
+                .bind(blocker)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert!(ensure_unblocked(todo, &pool).await.is_ok());
+
+            // Trashed blockers don't hold anything up either.
+            sqlx::query("UPDATE todos SET completed = false, deleted_at = CURRENT_TIMESTAMP WHERE id = ?")
+                .bind(blocker)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert!(ensure_unblocked(todo, &pool).await.is_ok());
+        }
+    }
+}
+
+/// The dependency graph around the todo `id`, leaving out todos the current
+/// user can't see.
+#[server(GetDependencyGraph, "/api")]
+pub async fn get_dependency_graph(id: u32) -> Result<DependencyGraph, ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::ssr::*;
+    use sqlx::{QueryBuilder, Sqlite};
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(id, &user, ListRole::Viewer, &pool).await?;
+
+    let mut nodes = Vec::new();
+    for row in dependency_nodes(id, DEPENDENCY_GRAPH_DEPTH, &pool)
+        .await
+        .map_err(TodoAppError::from)?
+    {
+        if todo_role(&row.todo, &user, &pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .is_some()
+        {
+            nodes.push(DependencyNode {
+                id: row.todo.id,
+                title: row.todo.title,
+                completed: row.todo.completed,
+                level: row.level,
+            });
+        }
+    }
+    if nodes.is_empty() {
+        return Ok(DependencyGraph::default());
+    }
+
+    let mut sql = QueryBuilder::<Sqlite>::new(
+        "SELECT todo_id, blocker_id FROM todo_dependencies WHERE todo_id IN (",
+    );
+    let mut separated = sql.separated(", ");
+    for node in &nodes {
+        separated.push_bind(node.id);
+    }
+    sql.push(")");
+
+    let edges = sql
+        .build_query_as::<(u32, u32)>()
+        .fetch_all(&pool)
+        .await
+        .map_err(TodoAppError::from)?
+        .into_iter()
+        .filter(|(_, blocker_id)| nodes.iter().any(|node| node.id == *blocker_id))
+        .map(|(todo_id, blocker_id)| DependencyEdge { todo_id, blocker_id })
+        .collect();
+
+    Ok(DependencyGraph { nodes, edges })
+}
+
+/// Makes the todo `id` wait for `blocker_id`, unless that would close a cycle.
+#[server(AddDependency, "/api")]
+pub async fn add_dependency(id: u32, blocker_id: u32) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::insert_dependency;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if id == blocker_id {
+        return Err(ServerFnError::ServerError("A todo can't block itself.".to_string()));
+    }
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+    let blocker = accessible_todo(blocker_id, &user, ListRole::Viewer, &pool).await?;
+
+    if todo.workspace_id != blocker.workspace_id {
+        return Err(ServerFnError::ServerError(
+            "Todos can only depend on todos in the same workspace.".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await.map_err(TodoAppError::from)?;
+    if !insert_dependency(id, blocker_id, &mut tx).await.map_err(TodoAppError::from)? {
+        return Err(ServerFnError::ServerError(format!(
+            "\"{}\" already waits for \"{}\", so it can't block it.",
+            blocker.title, todo.title
+        )));
+    }
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::DependencyAdded,
+        AuditTarget::todo(id),
+        None,
+        Some(serde_json::json!({ "blocked_by": blocker.title })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(RemoveDependency, "/api")]
+pub async fn remove_dependency(id: u32, blocker_id: u32) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    // Someone who can't see the blocker, say after leaving its list, can still
+    // stop waiting for it, but the history only tells them its id.
+    let blocker = sqlx::query_as::<_, SqlTodo>("SELECT * FROM todos WHERE id = ?")
+        .bind(blocker_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(TodoAppError::from)?;
+    let blocker = match blocker {
+        Some(blocker) if todo_role(&blocker, &user, &pool).await.map_err(TodoAppError::from)?.is_some() => {
+            serde_json::json!(blocker.title)
+        }
+        _ => serde_json::json!(blocker_id),
+    };
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM todo_dependencies WHERE todo_id = ? AND blocker_id = ?")
+        .bind(id)
+        .bind(blocker_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::DependencyRemoved,
+        AuditTarget::todo(id),
+        Some(serde_json::json!({ "blocked_by": blocker })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Checks or unchecks a todo, explaining instead when it's still waiting for
+/// other todos. The server refuses those as well.
+pub fn set_completed(
+    id: u32,
+    completed: bool,
+    dependencies: DependencyCounts,
+    update_todo: ServerAction<UpdateTodo>,
+) {
+    if completed && dependencies.is_blocked() {
+        ToasterInjection::expect_context().dispatch_toast(
+            move || {
+                view! {
+                    <Toast>
+                        <ToastTitle>"Still blocked"</ToastTitle>
+                        <ToastBody>
+                            {format!(
+                                "Waiting for {} open {}. Complete those first.",
+                                dependencies.blocked_by,
+                                if dependencies.blocked_by == 1 { "todo" } else { "todos" },
+                            )}
+                        </ToastBody>
+                    </Toast>
+                }
+            },
+            ToastOptions::default().with_intent(ToastIntent::Warning),
+        );
+    } else {
+        update_todo.dispatch(UpdateTodo {
+            id,
+            title: None,
+            completed: Some(completed),
+        });
+    }
+}
+
+#[component]
+pub fn DependencyBadges(dependencies: DependencyCounts) -> impl IntoView {
+    view! {
+        {dependencies.is_blocked().then(|| view! {
+            <span title="Open todos this one is waiting for">
+                <Badge appearance=BadgeAppearance::Tint color=BadgeColor::Danger>
+                    {format!("Blocked by {}", dependencies.blocked_by)}
+                </Badge>
+            </span>
+        })}
+        {(dependencies.blocking > 0).then(|| view! {
+            <span title="Open todos waiting for this one">
+                <Badge appearance=BadgeAppearance::Tint color=BadgeColor::Warning>
+                    {format!("Blocks {}", dependencies.blocking)}
+                </Badge>
+            </span>
+        })}
+    }
+}
+
+/// Draws the graph left to right, each todo's blockers to the left of it.
+#[component]
+pub fn DependencyGraphView(graph: DependencyGraph, todo_id: u32) -> impl IntoView {
+    let min_level = graph.nodes.iter().map(|node| node.level).min().unwrap_or(0);
+    let max_level = graph.nodes.iter().map(|node| node.level).max().unwrap_or(0);
+
+    // Nodes come sorted by level, so each one goes below the last in its column.
+    let mut rows = HashMap::<i32, i32>::new();
+    let positions = graph
+        .nodes
+        .iter()
+        .map(|node| {
+            let row = rows.entry(node.level).or_default();
+            let position = (
+                (node.level - min_level) * (NODE_WIDTH + COLUMN_GAP),
+                *row * (NODE_HEIGHT + ROW_GAP),
+            );
+            *row += 1;
+            (node.id, position)
+        })
+        .collect::<HashMap<_, _>>();
+    let width = (max_level - min_level + 1) * (NODE_WIDTH + COLUMN_GAP) - COLUMN_GAP;
+    let height = rows.values().max().copied().unwrap_or(1) * (NODE_HEIGHT + ROW_GAP) - ROW_GAP;
+
+    let edges = graph
+        .edges
+        .iter()
+        .filter_map(|edge| Some((positions.get(&edge.blocker_id)?, positions.get(&edge.todo_id)?)))
+        .map(|(&(x1, y1), &(x2, y2))| view! {
+            <line
+                class="todo-dependency-edge"
+                x1=x1 + NODE_WIDTH
+                y1=y1 + NODE_HEIGHT / 2
+                x2=x2
+                y2=y2 + NODE_HEIGHT / 2
+                marker-end="url(#todo-dependency-arrow)"
+            />
+        })
+        .collect_view();
+
+    let nodes = graph
+        .nodes
+        .into_iter()
+        .map(|node| {
+            let (x, y) = positions[&node.id];
+            let class = if node.id == todo_id {
+                "todo-dependency-node todo-dependency-current"
+            } else if node.completed {
+                "todo-dependency-node todo-dependency-done"
+            } else {
+                "todo-dependency-node"
+            };
+            let label = if node.title.chars().count() > NODE_TITLE_LEN {
+                format!("{}…", node.title.chars().take(NODE_TITLE_LEN - 1).collect::<String>())
+            } else {
+                node.title.clone()
+            };
+            view! {
+                <g class=class>
+                    <title>{node.title}</title>
+                    <rect x=x y=y width=NODE_WIDTH height=NODE_HEIGHT rx=4 />
+                    <text x=x + 8 y=y + NODE_HEIGHT / 2 dominant-baseline="middle">{label}</text>
+                </g>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <div class="todo-dependency-graph">
+            <svg width=width height=height viewBox=format!("0 0 {width} {height}")>
+                <defs>
+                    <marker
+                        id="todo-dependency-arrow"
+                        viewBox="0 0 10 10"
+                        refX=10
+                        refY=5
+                        markerWidth=6
+                        markerHeight=6
+                        orient="auto"
+                    >
+                        <path d="M 0 0 L 10 5 L 0 10 z" />
+                    </marker>
+                </defs>
+                {edges}
+                {nodes}
+            </svg>
+        </div>
+    }
+}
+
+/// What the todo is waiting for, a picker to add more and the graph around it.
+#[component]
+pub fn TodoDependencies(todo_id: u32) -> impl IntoView {
+    let add_dependency = ServerAction::<AddDependency>::new();
+    let remove_dependency = ServerAction::<RemoveDependency>::new();
+
+    let changes = use_todo_changes();
+    let graph = Resource::new(
+        move || {
+            (
+                add_dependency.version().get(),
+                remove_dependency.version().get(),
+                changes.get(),
+            )
+        },
+        move |_| get_dependency_graph(todo_id),
+    );
+
+    let text = RwSignal::new(String::new());
+    // Suggestions are only needed once someone types, which happens in the browser.
+    let candidates = LocalResource::new(move || {
+        let text = text.get();
+        async move {
+            if text.trim().is_empty() {
+                Ok(Vec::new())
+            } else {
+                search_todos(text).await
+            }
+        }
+    });
+
+    let error = move || {
+        [
+            add_dependency.value().get().and_then(Result::err),
+            remove_dependency.value().get().and_then(Result::err),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    graph.get().map(|graph| match graph {
+                        Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                        Ok(graph) => {
+                            let blockers = graph
+                                .blockers(todo_id)
+                                .into_iter()
+                                .map(|blocker| {
+                                    let blocker_id = blocker.id;
+                                    view! {
+                                        <Flex align=FlexAlign::Center gap=FlexGap::Small>
+                                            <Icon icon=if blocker.completed { icondata::LuCheckCircle } else { icondata::LuCircle } />
+                                            <Text>{blocker.title}</Text>
+                                            <span title="No longer blocks this todo">
+                                                <Button
+                                                    appearance=ButtonAppearance::Subtle
+                                                    size=ButtonSize::Small
+                                                    icon=icondata::LuX
+                                                    on_click=move |_| {
+                                                        remove_dependency.dispatch(RemoveDependency { id: todo_id, blocker_id });
+                                                    }
+                                                />
+                                            </span>
+                                        </Flex>
+                                    }
+                                })
+                                .collect::<Vec<_>>();
+                            view! {
+                                {if blockers.is_empty() {
+                                    view! { <Text>"Not waiting for anything."</Text> }.into_any()
+                                } else {
+                                    blockers.collect_view().into_any()
+                                }}
+                                {(graph.nodes.len() > 1).then(|| view! { <DependencyGraphView graph todo_id /> })}
+                            }
+                                .into_any()
+                        }
+                    })
+                }}
+            </Transition>
+            <AutoComplete
+                value=text
+                placeholder="Blocked by..."
+                clear_after_select=true
+                blur_after_select=true
+                on_select=move |value: String| {
+                    if let Ok(blocker_id) = value.parse() {
+                        add_dependency.dispatch(AddDependency { id: todo_id, blocker_id });
+                    }
+                }
+            >
+                {move || {
+                    candidates
+                        .get()
+                        .and_then(|candidates| candidates.take().ok())
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|result| result.todo.id != todo_id)
+                        .map(|result| view! {
+                            <AutoCompleteOption value=result.todo.id.to_string()>{result.todo.title}</AutoCompleteOption>
+                        })
+                        .collect_view()
+                }}
+            </AutoComplete>
+            {error}
+        </Flex>
+    }
+}
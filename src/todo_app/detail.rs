@@ -0,0 +1,66 @@
+use leptos::prelude::*;
+use thaw::*;
+
+use super::attachments::TodoAttachments;
+use super::audit::TodoHistory;
+use super::comments::TodoComments;
+use super::dependencies::TodoDependencies;
+use super::notes::{TodoNotes, UpdateNotes};
+use super::Todo;
+
+/// Side drawer with everything about a single todo. Opened from a row of the
+/// `Todos` table by setting `todo` and `open`.
+#[component]
+pub fn TodoDetail(
+    todo: RwSignal<Option<Todo>>,
+    open: RwSignal<bool>,
+    update_notes: ServerAction<UpdateNotes>,
+) -> impl IntoView {
+    let tab = RwSignal::new("details".to_string());
+
+    view! {
+        <OverlayDrawer open position=DrawerPosition::Right size=DrawerSize::Medium>
+            <DrawerHeader>
+                <DrawerHeaderTitle>
+                    <DrawerHeaderTitleAction slot>
+                        <Button
+                            appearance=ButtonAppearance::Subtle
+                            icon=icondata::LuX
+                            on_click=move |_| open.set(false)
+                        />
+                    </DrawerHeaderTitleAction>
+                    {move || todo.with(|todo| todo.as_ref().map(|todo| todo.title.clone()))}
+                </DrawerHeaderTitle>
+            </DrawerHeader>
+            <DrawerBody>
+                <TabList selected_value=tab>
+                    <Tab value="details">"Details"</Tab>
+                    <Tab value="history">"History"</Tab>
+                </TabList>
+                {move || {
+                    todo.get().map(|todo| {
+                        if tab.get() == "history" {
+                            view! { <TodoHistory todo_id=todo.id /> }.into_any()
+                        } else {
+                            view! {
+                                <Flex vertical=true gap=FlexGap::Large>
+                                    <TodoNotes todo_id=todo.id notes=todo.notes update_notes />
+                                    <Divider />
+                                    <Caption1Strong>"Blocked by"</Caption1Strong>
+                                    <TodoDependencies todo_id=todo.id />
+                                    <Divider />
+                                    <Caption1Strong>"Attachments"</Caption1Strong>
+                                    <TodoAttachments todo_id=todo.id />
+                                    <Divider />
+                                    <Caption1Strong>"Comments"</Caption1Strong>
+                                    <TodoComments todo_id=todo.id />
+                                </Flex>
+                            }
+                                .into_any()
+                        }
+                    })
+                }}
+            </DrawerBody>
+        </OverlayDrawer>
+    }
+}
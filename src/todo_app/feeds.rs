@@ -0,0 +1,426 @@
+use leptos::prelude::*;
+use thaw::*;
+
+use crate::errors::TodoAppError;
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use crate::todo_app::priority::Priority;
+    use crate::todo_app::ssr::SqlTodo;
+    use axum::{
+        extract::{Path, State},
+        http::{header, StatusCode},
+        response::{IntoResponse, Response},
+    };
+    use chrono::{Days, NaiveDateTime, NaiveTime, Utc};
+    use rand::RngCore;
+    use sqlx::SqlitePool;
+
+    pub const PRODID: &str = "-//Todos//Todo feed//EN";
+
+    /// Content lines are folded after this many octets, RFC 5545 section 3.1.
+    const LINE_LIMIT: usize = 75;
+
+    /// A fresh feed token, 32 random bytes as hex.
+    pub fn generate_token() -> String {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// Escapes a TEXT value, RFC 5545 section 3.3.11.
+    pub fn escape(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace(';', "\\;")
+            .replace(',', "\\,")
+            .replace("\r\n", "\\n")
+            .replace(['\r', '\n'], "\\n")
+    }
+
+    /// Appends a content line, folded so no line is longer than [`LINE_LIMIT`]
+    /// octets without splitting a character.
+    pub fn push_line(ics: &mut String, line: &str) {
+        let mut len = 0;
+        for c in line.chars() {
+            if len + c.len_utf8() > LINE_LIMIT {
+                ics.push_str("\r\n ");
+                len = 1;
+            }
+            ics.push(c);
+            len += c.len_utf8();
+        }
+        ics.push_str("\r\n");
+    }
+
+    /// Todos due at midnight only have a due date, so they become all-day
+    /// entries. Everything else is in floating local time, like in the app.
+    fn date_property(name: &str, at: NaiveDateTime) -> String {
+        if at.time() == NaiveTime::MIN {
+            format!("{name};VALUE=DATE:{}", at.format("%Y%m%d"))
+        } else {
+            format!("{name}:{}", at.format("%Y%m%dT%H%M%S"))
+        }
+    }
+
+    /// RFC 5545 priorities run from 1 (highest) to 9, 0 being undefined.
+    fn priority(priority: Priority) -> u8 {
+        match priority {
+            Priority::None => 0,
+            Priority::Low => 7,
+            Priority::Medium => 5,
+            Priority::High => 3,
+            Priority::Urgent => 1,
+        }
+    }
+
+    /// The current time as a `DTSTAMP` value.
+    pub fn dtstamp() -> String {
+        Utc::now().format("%Y%m%dT%H%M%SZ").to_string()
+    }
+
+    /// The UID of a todo that didn't get one from a CalDAV client.
+    pub fn default_uid(id: u32) -> String {
+        format!("todo-{id}@todos")
+    }
+
+    /// Appends `todo` as a VTODO, the way task apps see it.
+    pub fn push_vtodo(ics: &mut String, todo: &SqlTodo, uid: &str, stamp: &str) {
+        push_line(ics, "BEGIN:VTODO");
+        push_line(ics, &format!("UID:{uid}"));
+        push_line(ics, &format!("DTSTAMP:{stamp}"));
+        push_line(ics, &format!("SUMMARY:{}", escape(&todo.title)));
+        if !todo.notes.trim().is_empty() {
+            push_line(ics, &format!("DESCRIPTION:{}", escape(&todo.notes)));
+        }
+        if let Some(due) = todo.due_at {
+            push_line(ics, &date_property("DUE", due));
+        }
+        if let Some(recurrence) = &todo.recurrence {
+            push_line(ics, &format!("RRULE:{recurrence}"));
+        }
+        push_line(
+            ics,
+            if todo.completed { "STATUS:COMPLETED" } else { "STATUS:NEEDS-ACTION" },
+        );
+        push_line(ics, &format!("PRIORITY:{}", priority(todo.priority)));
+        push_line(ics, "END:VTODO");
+    }
+
+    /// The dated todos as a calendar. Each becomes a VTODO for task apps and a
+    /// VEVENT on its due date for calendar apps, which mostly ignore VTODOs.
+    pub fn calendar(todos: &[SqlTodo]) -> String {
+        let stamp = dtstamp();
+        let mut ics = String::new();
+        for line in [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            &format!("PRODID:{PRODID}"),
+            "CALSCALE:GREGORIAN",
+            "X-WR-CALNAME:Todos",
+        ] {
+            push_line(&mut ics, line);
+        }
+
+        for todo in todos {
+            let Some(due) = todo.due_at else {
+                continue;
+            };
+            push_vtodo(&mut ics, todo, &default_uid(todo.id), &stamp);
+
+            let summary = format!("SUMMARY:{}", escape(&todo.title));
+            let description = (!todo.notes.trim().is_empty())
+                .then(|| format!("DESCRIPTION:{}", escape(&todo.notes)));
+
+            push_line(&mut ics, "BEGIN:VEVENT");
+            push_line(&mut ics, &format!("UID:todo-{}-due@todos", todo.id));
+            push_line(&mut ics, &format!("DTSTAMP:{stamp}"));
+            push_line(&mut ics, &summary);
+            if let Some(description) = &description {
+                push_line(&mut ics, description);
+            }
+            push_line(&mut ics, &date_property("DTSTART", due));
+            if due.time() == NaiveTime::MIN {
+                push_line(&mut ics, &date_property("DTEND", due + Days::new(1)));
+            }
+            push_line(&mut ics, "TRANSP:TRANSPARENT");
+            if let Some(remind) = todo.remind_at.filter(|_| !todo.completed) {
+                // Triggers are relative to the start, which is the due date.
+                let minutes = (remind - due).num_minutes();
+                push_line(&mut ics, "BEGIN:VALARM");
+                push_line(&mut ics, "ACTION:DISPLAY");
+                push_line(&mut ics, &summary.replacen("SUMMARY", "DESCRIPTION", 1));
+                push_line(
+                    &mut ics,
+                    &if minutes < 0 {
+                        format!("TRIGGER:-PT{}M", -minutes)
+                    } else {
+                        format!("TRIGGER:PT{minutes}M")
+                    },
+                );
+                push_line(&mut ics, "END:VALARM");
+            }
+            push_line(&mut ics, "END:VEVENT");
+        }
+
+        push_line(&mut ics, "END:VCALENDAR");
+        ics
+    }
+
+    /// Dated todos of `user_id` across all their workspaces: their own and
+    /// those in lists shared with them, leaving out the trash.
+    pub async fn feed_todos(user_id: i64, pool: &SqlitePool) -> Result<Vec<SqlTodo>, sqlx::Error> {
+        sqlx::query_as::<_, SqlTodo>(
+            "SELECT * FROM todos
+            WHERE ((list_id IS NULL AND user_id = ?1)
+                OR list_id IN (SELECT list_id FROM list_members WHERE user_id = ?1))
+            AND workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = ?1)
+            AND due_at IS NOT NULL AND deleted_at IS NULL
+            ORDER BY due_at, id",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// `GET /feeds/:token.ics`. Calendar apps subscribing to the feed can't
+    /// log in, so the secret token in the path is what authenticates them.
+    pub async fn todo_feed(State(pool): State<SqlitePool>, Path(file): Path<String>) -> Response {
+        let Some(token) = file.strip_suffix(".ics") else {
+            return (StatusCode::NOT_FOUND, "Unknown feed.").into_response();
+        };
+
+        let todos = match sqlx::query_scalar::<_, i64>("SELECT user_id FROM feed_tokens WHERE token = ?")
+            .bind(token)
+            .fetch_optional(&pool)
+            .await
+        {
+            Ok(Some(user_id)) => feed_todos(user_id, &pool).await,
+            Ok(None) => return (StatusCode::NOT_FOUND, "Unknown feed.").into_response(),
+            Err(error) => Err(error),
+        };
+
+        match todos {
+            Ok(todos) => (
+                [
+                    (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+                    (header::CACHE_CONTROL, "no-store"),
+                ],
+                calendar(&todos),
+            )
+                .into_response(),
+            Err(error) => {
+                log::error!("Building the todo feed failed: {error:?}");
+                (StatusCode::INTERNAL_SERVER_ERROR, "The feed could not be built.").into_response()
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use chrono::NaiveDate;
+
+        fn folded(line: &str) -> String {
+            let mut ics = String::new();
+            push_line(&mut ics, line);
+            ics
+        }
+
+        #[test]
+        fn escapes_text() {
+            assert_eq!(escape("a\\b;c,d"), "a\\\\b\\;c\\,d");
+            assert_eq!(escape("one\r\ntwo\nthree\rfour"), "one\\ntwo\\nthree\\nfour");
+            assert_eq!(escape("Plain: text"), "Plain: text");
+        }
+
+        #[test]
+        fn leaves_short_lines_alone() {
+            let line = "x".repeat(LINE_LIMIT);
+            assert_eq!(folded(&line), format!("{line}\r\n"));
+        }
+
+        #[test]
+        fn folds_long_lines() {
+            let line = format!("SUMMARY:{}", "x".repeat(200));
+            let ics = folded(&line);
+            assert!(ics.ends_with("\r\n"));
+            let lines = ics.trim_end_matches("\r\n").split("\r\n").collect::<Vec<_>>();
+            assert_eq!(lines.len(), 3);
+            assert!(lines.iter().all(|line| line.len() <= LINE_LIMIT));
+            assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+            assert_eq!(ics.replace("\r\n ", "").trim_end(), line);
+        }
+
+        #[test]
+        fn folds_between_characters() {
+            // The two octet "é" would straddle the limit, so it moves to the next line whole.
+            let line = format!("{}é{}", "x".repeat(LINE_LIMIT - 1), "ü".repeat(100));
+            let ics = folded(&line);
+            let lines = ics.trim_end_matches("\r\n").split("\r\n").collect::<Vec<_>>();
+            assert_eq!(lines[0], "x".repeat(LINE_LIMIT - 1));
+            assert!(lines[1].starts_with(" é"));
+            assert!(lines.iter().all(|line| line.len() <= LINE_LIMIT));
+            assert_eq!(ics.replace("\r\n ", "").trim_end(), line);
+
+            // Four octet characters too, whatever they are lined up against.
+            for offset in 0..4 {
+                let line = format!("{}{}", "x".repeat(offset), "🦀".repeat(40));
+                let ics = folded(&line);
+                assert!(ics.split("\r\n").all(|line| line.len() <= LINE_LIMIT));
+                assert_eq!(ics.replace("\r\n ", "").trim_end(), line);
+            }
+        }
+
+        #[test]
+        fn writes_dates_and_priorities() {
+            let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+            assert_eq!(date_property("DUE", date.and_time(NaiveTime::MIN)), "DUE;VALUE=DATE:20240131");
+            assert_eq!(date_property("DUE", date.and_hms_opt(9, 30, 0).unwrap()), "DUE:20240131T093000");
+            assert_eq!(priority(Priority::Urgent), 1);
+            assert_eq!(priority(Priority::None), 0);
+        }
+    }
+}
+
+/// The current user's feed token, if they have one.
+#[server(GetFeedToken, "/api")]
+pub async fn get_feed_token() -> Result<Option<String>, ServerFnError<TodoAppError>> {
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    Ok(sqlx::query_scalar::<_, String>("SELECT token FROM feed_tokens WHERE user_id = ?")
+        .bind(user.id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(TodoAppError::from)?)
+}
+
+/// Gives the current user a new feed token, which stops the old one working.
+#[server(CreateFeedToken, "/api")]
+pub async fn create_feed_token() -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::generate_token;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query(
+        "INSERT INTO feed_tokens (user_id, token) VALUES (?, ?)
+        ON CONFLICT (user_id) DO UPDATE SET token = excluded.token, created_at = CURRENT_TIMESTAMP",
+    )
+    .bind(user.id)
+    .bind(generate_token())
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    // The token itself is a secret, so it stays out of the log.
+    record(&mut *tx, Some(user.id), AuditAction::FeedTokenCreated, AuditTarget::user(user.id), None, None)
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(RevokeFeedToken, "/api")]
+pub async fn revoke_feed_token() -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM feed_tokens WHERE user_id = ?")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(&mut *tx, Some(user.id), AuditAction::FeedTokenRevoked, AuditTarget::user(user.id), None, None)
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// The address calendar apps subscribe to, with buttons to replace or revoke it.
+#[component]
+pub fn CalendarFeed() -> impl IntoView {
+    let create_feed_token = ServerAction::<CreateFeedToken>::new();
+    let revoke_feed_token = ServerAction::<RevokeFeedToken>::new();
+    let token = Resource::new(
+        move || (create_feed_token.version().get(), revoke_feed_token.version().get()),
+        move |_| get_feed_token(),
+    );
+    // Only the browser knows which address the app is reached at.
+    let origin = RwSignal::new(String::new());
+    Effect::new(move |_| origin.set(window().location().origin().unwrap_or_default()));
+
+    view! {
+        <Flex vertical=true>
+            <Caption1Strong>"Calendar feed"</Caption1Strong>
+            <Text>"Subscribe to this address in a calendar app to see your todos by due date."</Text>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                {move || {
+                    token.get().map(|token| match token {
+                        Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                        Ok(None) => view! {
+                            <Flex>
+                                <Button
+                                    icon=icondata::LuCalendarPlus
+                                    on_click=move |_| {
+                                        create_feed_token.dispatch(CreateFeedToken {});
+                                    }
+                                >
+                                    "Create feed address"
+                                </Button>
+                            </Flex>
+                        }
+                            .into_any(),
+                        Ok(Some(token)) => {
+                            let path = format!("/feeds/{token}.ics");
+                            let address = RwSignal::new(String::new());
+                            {
+                                let path = path.clone();
+                                Effect::new(move |_| address.set(format!("{}{path}", origin.get())));
+                            }
+                            let webcal = move || {
+                                let origin = origin.get();
+                                let host = origin.split_once("://").map_or(origin.as_str(), |(_, host)| host);
+                                format!("webcal://{host}{path}")
+                            };
+                            view! {
+                                <Input value=address readonly=true />
+                                <Flex>
+                                    <Link href=Signal::derive(webcal)>"Open in calendar app"</Link>
+                                </Flex>
+                                <Flex>
+                                    <Button
+                                        icon=icondata::LuRefreshCw
+                                        on_click=move |_| {
+                                            create_feed_token.dispatch(CreateFeedToken {});
+                                        }
+                                    >
+                                        "New address"
+                                    </Button>
+                                    <Button
+                                        icon=icondata::LuX
+                                        on_click=move |_| {
+                                            revoke_feed_token.dispatch(RevokeFeedToken {});
+                                        }
+                                    >
+                                        "Revoke"
+                                    </Button>
+                                </Flex>
+                            }
+                                .into_any()
+                        }
+                    })
+                }}
+            </Transition>
+        </Flex>
+    }
+}
@@ -0,0 +1,1177 @@
+use chrono::NaiveDateTime;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+use thaw_utils::mount_style;
+
+use super::lists::get_lists;
+use super::priority::Priority;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportFormat {
+    #[default]
+    Csv,
+    Json,
+    TodoTxt,
+}
+
+impl ImportFormat {
+    pub const ALL: [ImportFormat; 3] = [ImportFormat::Csv, ImportFormat::Json, ImportFormat::TodoTxt];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "csv",
+            ImportFormat::Json => "json",
+            ImportFormat::TodoTxt => "todotxt",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.as_str() == value)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportFormat::Csv => "CSV",
+            ImportFormat::Json => "JSON",
+            ImportFormat::TodoTxt => "todo.txt",
+        }
+    }
+
+    /// Goes by the file's extension, CSV unless it says otherwise.
+    pub fn guess(file_name: &str) -> Self {
+        let file_name = file_name.to_lowercase();
+        if file_name.ends_with(".json") {
+            ImportFormat::Json
+        } else if file_name.ends_with(".txt") {
+            ImportFormat::TodoTxt
+        } else {
+            ImportFormat::Csv
+        }
+    }
+}
+
+/// Which column of a CSV file holds which field, by its header. Fields
+/// without a column are left empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvColumns {
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    pub due_at: Option<String>,
+    pub priority: Option<String>,
+    pub completed: Option<String>,
+    pub tags: Option<String>,
+}
+
+impl CsvColumns {
+    /// The fields that can be mapped, with their labels.
+    pub const FIELDS: [(&'static str, &'static str); 6] = [
+        ("title", "Title"),
+        ("notes", "Notes"),
+        ("due_at", "Due date"),
+        ("priority", "Priority"),
+        ("completed", "Completed"),
+        ("tags", "Tags"),
+    ];
+
+    fn field_mut(&mut self, field: &str) -> Option<&mut Option<String>> {
+        match field {
+            "title" => Some(&mut self.title),
+            "notes" => Some(&mut self.notes),
+            "due_at" => Some(&mut self.due_at),
+            "priority" => Some(&mut self.priority),
+            "completed" => Some(&mut self.completed),
+            "tags" => Some(&mut self.tags),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        match field {
+            "title" => self.title.as_deref(),
+            "notes" => self.notes.as_deref(),
+            "due_at" => self.due_at.as_deref(),
+            "priority" => self.priority.as_deref(),
+            "completed" => self.completed.as_deref(),
+            "tags" => self.tags.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, field: &str, header: Option<String>) {
+        if let Some(column) = self.field_mut(field) {
+            *column = header.filter(|header| !header.is_empty());
+        }
+    }
+}
+
+/// A todo read from an import file, not saved yet.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedTodo {
+    pub title: String,
+    pub notes: String,
+    pub completed: bool,
+    pub due_at: Option<NaiveDateTime>,
+    pub priority: Priority,
+    /// Tag names, created for the importing user where they don't exist yet.
+    pub tags: Vec<String>,
+}
+
+/// A todo of the file, or why it couldn't be read. `row` is the line for CSV
+/// and todo.txt, and the position in the array for JSON, counting from 1.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportRow {
+    pub row: usize,
+    pub todo: Result<ImportedTodo, String>,
+}
+
+/// What an import file holds, as shown before importing it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub format: ImportFormat,
+    /// The header of a CSV file, to map the columns from.
+    pub headers: Vec<String>,
+    pub columns: CsvColumns,
+    pub rows: Vec<ImportRow>,
+    /// How many todos were saved, once the import went through.
+    pub imported: Option<usize>,
+}
+
+impl ImportPreview {
+    pub fn errors(&self) -> usize {
+        self.rows.iter().filter(|row| row.todo.is_err()).count()
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{CsvColumns, ImportFormat, ImportPreview, ImportRow, ImportedTodo};
+    use crate::auth::{ssr::AuthSession, User};
+    use crate::todo_app::attachments::ssr::{error_response, server_fn_error_response};
+    use crate::todo_app::audit::{ssr::*, AuditAction};
+    use crate::todo_app::lists::ssr::accessible_list;
+    use crate::todo_app::live::{ssr::ChangeHub, TodoChange};
+    use crate::todo_app::priority::Priority;
+    use crate::todo_app::ssr::ListRole;
+    use crate::todo_app::workspaces::ssr::session_workspace;
+    use crate::errors::TodoAppError;
+    use axum::{
+        extract::{Multipart, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+    };
+    use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+    use serde_json::Value;
+    use sqlx::{Sqlite, SqlitePool, Transaction};
+    use std::collections::HashMap;
+
+    /// Largest file [`import_todos`] accepts, enough for years of todos.
+    pub const IMPORT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+    /// Headers that are taken to mean a field, compared case-insensitively.
+    const COLUMN_NAMES: [(&str, &[&str]); 6] = [
+        ("title", &["title", "name", "task", "summary", "subject", "content"]),
+        ("notes", &["notes", "note", "description", "body", "details"]),
+        ("due_at", &["due", "due_at", "due date", "due_date", "deadline"]),
+        ("priority", &["priority"]),
+        ("completed", &["completed", "done", "complete", "status"]),
+        ("tags", &["tags", "tag", "labels", "label"]),
+    ];
+
+    /// Reads `content` as `format`. CSV columns are guessed from the header
+    /// unless `columns` maps any of them.
+    pub fn parse(format: ImportFormat, content: &str, columns: CsvColumns) -> ImportPreview {
+        let content = content.trim_start_matches('\u{feff}');
+        let mut preview = ImportPreview {
+            format,
+            ..Default::default()
+        };
+        match format {
+            ImportFormat::Csv => {
+                let mut records = csv_records(content).into_iter();
+                preview.headers = records
+                    .next()
+                    .map(|(_, headers)| headers.into_iter().map(|header| header.trim().to_string()).collect())
+                    .unwrap_or_default();
+                preview.columns = if columns == CsvColumns::default() {
+                    guess_columns(&preview.headers)
+                } else {
+                    columns
+                };
+                preview.rows = records
+                    .map(|(row, record)| ImportRow {
+                        row,
+                        todo: csv_todo(&preview.headers, &preview.columns, &record),
+                    })
+                    .collect();
+            }
+            ImportFormat::Json => preview.rows = json_rows(content),
+            ImportFormat::TodoTxt => {
+                preview.rows = content
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(index, line)| ImportRow {
+                        row: index + 1,
+                        todo: todo_txt(line),
+                    })
+                    .collect();
+            }
+        }
+        preview
+    }
+
+    /// Splits CSV into records, RFC 4180: fields may be quoted, with `""` for a
+    /// quote and line breaks inside. Each record comes with the line it starts
+    /// on. Files from spreadsheets set to use `;` work as well.
+    fn csv_records(content: &str) -> Vec<(usize, Vec<String>)> {
+        let header = content.lines().next().unwrap_or_default();
+        let delimiter = if header.matches(';').count() > header.matches(',').count() { ';' } else { ',' };
+
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut line = 1;
+        let mut start = 1;
+        let mut chars = content.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted => {
+                    if chars.next_if_eq(&'"').is_some() {
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                '"' if field.is_empty() => quoted = true,
+                '\n' if !quoted => {
+                    record.push(std::mem::take(&mut field));
+                    if record.iter().any(|field| !field.trim().is_empty()) {
+                        records.push((start, std::mem::take(&mut record)));
+                    }
+                    record.clear();
+                    line += 1;
+                    start = line;
+                }
+                '\r' if !quoted => {}
+                c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+                c => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+        }
+        record.push(field);
+        if record.iter().any(|field| !field.trim().is_empty()) {
+            records.push((start, record));
+        }
+        records
+    }
+
+    fn guess_columns(headers: &[String]) -> CsvColumns {
+        let mut columns = CsvColumns::default();
+        for (field, names) in COLUMN_NAMES {
+            let header = headers
+                .iter()
+                .find(|header| names.contains(&header.to_lowercase().as_str()));
+            columns.set(field, header.cloned());
+        }
+        columns
+    }
+
+    fn csv_todo(headers: &[String], columns: &CsvColumns, record: &[String]) -> Result<ImportedTodo, String> {
+        let value = |field: &str| {
+            columns
+                .get(field)
+                .and_then(|column| headers.iter().position(|header| header == column))
+                .and_then(|index| record.get(index))
+                .map_or("", |value| value.trim())
+        };
+        if columns.title.is_none() {
+            return Err("No column is mapped to the title.".to_string());
+        }
+
+        Ok(ImportedTodo {
+            title: title(value("title"))?,
+            notes: value("notes").to_string(),
+            completed: parse_completed(value("completed"))?,
+            due_at: parse_date(value("due_at"))?,
+            priority: parse_priority(value("priority"))?,
+            tags: value("tags")
+                .split([',', ';'])
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// An array of objects shaped like [`Todo`](crate::todo_app::Todo), as the
+    /// app sends them. Only the fields an import can use are read.
+    fn json_rows(content: &str) -> Vec<ImportRow> {
+        match serde_json::from_str::<Value>(content) {
+            Ok(Value::Array(items)) => items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| ImportRow {
+                    row: index + 1,
+                    todo: json_todo(item),
+                })
+                .collect(),
+            Ok(_) => vec![ImportRow {
+                row: 1,
+                todo: Err("Expected an array of todos.".to_string()),
+            }],
+            Err(error) => vec![ImportRow {
+                row: error.line(),
+                todo: Err(format!("This is not valid JSON: {error}")),
+            }],
+        }
+    }
+
+    fn json_todo(item: &Value) -> Result<ImportedTodo, String> {
+        let object = item.as_object().ok_or("Expected an object.")?;
+        let text = |key: &str| match object.get(key) {
+            None | Some(Value::Null) => Ok(""),
+            Some(Value::String(text)) => Ok(text.as_str()),
+            Some(_) => Err(format!("\"{key}\" must be text.")),
+        };
+
+        Ok(ImportedTodo {
+            title: title(text("title")?)?,
+            notes: text("notes")?.to_string(),
+            completed: match object.get("completed") {
+                None | Some(Value::Null) => false,
+                Some(Value::Bool(completed)) => *completed,
+                Some(_) => return Err("\"completed\" must be true or false.".to_string()),
+            },
+            due_at: parse_date(text("due_at")?)?,
+            priority: parse_priority(text("priority")?)?,
+            tags: match object.get("tags") {
+                None | Some(Value::Null) => Vec::new(),
+                Some(Value::Array(tags)) => tags
+                    .iter()
+                    .map(|tag| match tag {
+                        Value::String(name) => Some(name.clone()),
+                        Value::Object(tag) => tag.get("name").and_then(Value::as_str).map(str::to_string),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or("\"tags\" must be names or objects with a name.")?,
+                Some(_) => return Err("\"tags\" must be a list.".to_string()),
+            },
+        })
+    }
+
+    /// A line of todo.txt: `x` when done, `(A)` for the priority, the dates it
+    /// was done and created, then the text with `+project` and `@context` tags
+    /// and `key:value` pairs, of which `due` and `pri` are used.
+    fn todo_txt(line: &str) -> Result<ImportedTodo, String> {
+        let mut words = line.split_whitespace().peekable();
+        let completed = words.next_if_eq(&"x").is_some();
+        let mut priority = words
+            .next_if(|word| word.len() == 3 && word.starts_with('(') && word.ends_with(')'))
+            .map(|word| letter_priority(&word[1..2]))
+            .transpose()?
+            .unwrap_or_default();
+        // The app keeps its own dates of creation.
+        while words
+            .next_if(|word| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok())
+            .is_some()
+        {}
+
+        let mut text = Vec::new();
+        let mut tags = Vec::new();
+        let mut due_at = None;
+        for word in words {
+            if let Some(tag) = word.strip_prefix(['+', '@']).filter(|tag| !tag.is_empty()) {
+                tags.push(tag.to_string());
+            } else if let Some(due) = word.strip_prefix("due:") {
+                due_at = parse_date(due)?;
+            } else if let Some(letter) = word.strip_prefix("pri:") {
+                priority = letter_priority(letter)?;
+            } else {
+                text.push(word);
+            }
+        }
+
+        Ok(ImportedTodo {
+            title: title(&text.join(" "))?,
+            notes: String::new(),
+            completed,
+            due_at,
+            priority,
+            tags,
+        })
+    }
+
+    fn title(value: &str) -> Result<String, String> {
+        match value.trim() {
+            "" => Err("The title is empty.".to_string()),
+            title => Ok(title.to_string()),
+        }
+    }
+
+    /// Dates as other tools write them. Without a time, they are due that day.
+    fn parse_date(value: &str) -> Result<Option<NaiveDateTime>, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(None);
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+            if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+                return Ok(Some(date_time));
+            }
+        }
+        if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+            return Ok(Some(date_time.with_timezone(&Local).naive_local()));
+        }
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| Some(date.and_time(NaiveTime::MIN)))
+            .map_err(|_| format!("\"{value}\" is not a date like 2024-01-31."))
+    }
+
+    fn parse_priority(value: &str) -> Result<Priority, String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(Priority::None);
+        }
+        Priority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("\"{value}\" is not a priority, use none, low, medium, high or urgent."))
+    }
+
+    /// todo.txt priorities go from A down to Z.
+    fn letter_priority(letter: &str) -> Result<Priority, String> {
+        match letter {
+            "A" => Ok(Priority::Urgent),
+            "B" => Ok(Priority::High),
+            "C" => Ok(Priority::Medium),
+            letter if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Ok(Priority::Low),
+            letter => Err(format!("\"{letter}\" is not a priority, use a letter from A to Z.")),
+        }
+    }
+
+    fn parse_completed(value: &str) -> Result<bool, String> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "0" | "false" | "no" | "open" | "todo" | "needs-action" | "in progress" => Ok(false),
+            "1" | "true" | "yes" | "x" | "done" | "completed" | "complete" => Ok(true),
+            _ => Err(format!("\"{}\" is neither done nor open.", value.trim())),
+        }
+    }
+
+    /// Saves `todos` for `user`, creating the tags they don't have yet.
+    /// Returns the last todo saved.
+    pub async fn save_todos(
+        todos: &[ImportedTodo],
+        user: &User,
+        workspace_id: i64,
+        list_id: Option<i64>,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<Option<u32>, sqlx::Error> {
+        let mut tag_ids = HashMap::<String, i64>::new();
+        let mut last = None;
+        for todo in todos {
+            // Done todos go into the list's first done status, the rest into its first open one.
+            let id = sqlx::query_scalar::<_, u32>(
+                "INSERT INTO todos (title, notes, user_id, completed, due_at, priority, list_id, workspace_id, status_id)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8,
+                    (SELECT id FROM list_statuses WHERE list_id = ?7 AND is_done = ?4 ORDER BY position LIMIT 1))
+                RETURNING id",
+            )
+            .bind(&todo.title)
+            .bind(&todo.notes)
+            .bind(user.id)
+            .bind(todo.completed)
+            .bind(todo.due_at)
+            .bind(todo.priority)
+            .bind(list_id)
+            .bind(workspace_id)
+            .fetch_one(&mut **tx)
+            .await?;
+            let created = current_todo_snapshot(id, &mut **tx).await?;
+            record(&mut **tx, Some(user.id), AuditAction::TodoCreated, AuditTarget::todo(id), None, created).await?;
+
+            for name in &todo.tags {
+                let tag_id = match tag_ids.get(name) {
+                    Some(tag_id) => *tag_id,
+                    None => {
+                        let tag_id = tag_id(name, user, tx).await?;
+                        tag_ids.insert(name.clone(), tag_id);
+                        tag_id
+                    }
+                };
+                sqlx::query("INSERT OR IGNORE INTO todo_tags (todo_id, tag_id) VALUES (?, ?)")
+                    .bind(id)
+                    .bind(tag_id)
+                    .execute(&mut **tx)
+                    .await?;
+                record(
+                    &mut **tx,
+                    Some(user.id),
+                    AuditAction::TagAttached,
+                    AuditTarget::todo(id),
+                    None,
+                    Some(serde_json::json!({ "tag": name })),
+                )
+                .await?;
+            }
+            last = Some(id);
+        }
+        Ok(last)
+    }
+
+    /// The user's tag called `name`, which is created if they don't have one.
+    async fn tag_id(name: &str, user: &User, tx: &mut Transaction<'_, Sqlite>) -> Result<i64, sqlx::Error> {
+        if let Some(id) = sqlx::query_scalar::<_, i64>("SELECT id FROM tags WHERE user_id = ? AND name = ?")
+            .bind(user.id)
+            .bind(name)
+            .fetch_optional(&mut **tx)
+            .await?
+        {
+            return Ok(id);
+        }
+
+        let (id, color) = sqlx::query_as::<_, (i64, String)>(
+            "INSERT INTO tags (user_id, name) VALUES (?, ?) RETURNING id, color",
+        )
+            .bind(user.id)
+            .bind(name)
+            .fetch_one(&mut **tx)
+            .await?;
+        record(
+            &mut **tx,
+            Some(user.id),
+            AuditAction::TagCreated,
+            AuditTarget::tag(id),
+            None,
+            Some(serde_json::json!({ "name": name, "color": color })),
+        )
+        .await?;
+        Ok(id)
+    }
+
+    /// `POST /todos/import` with the file in a `file` field. The `format`, the
+    /// CSV columns as `column.<field>` and a `list_id` to import into are
+    /// optional. Answers with the [`ImportPreview`]; with `commit` set and no
+    /// errors in it, the todos are saved as well, all or none of them.
+    pub async fn import_todos(
+        State(pool): State<SqlitePool>,
+        State(changes): State<ChangeHub>,
+        auth_session: AuthSession,
+        mut multipart: Multipart,
+    ) -> Response {
+        let Some(user) = auth_session.current_user.clone() else {
+            return server_fn_error_response(TodoAppError::Forbidden.into());
+        };
+
+        let mut file = None;
+        let mut format = None;
+        let mut columns = CsvColumns::default();
+        let mut list_id = None;
+        let mut commit = false;
+        loop {
+            let field = match multipart.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                Err(error) => return error_response(error.status(), error.body_text()),
+            };
+            let name = field.name().unwrap_or_default().to_string();
+            let file_name = field.file_name().unwrap_or_default().to_string();
+            let value = match field.text().await {
+                Ok(value) => value,
+                Err(error) => return error_response(error.status(), error.body_text()),
+            };
+            match name.as_str() {
+                "file" => file = Some((file_name, value)),
+                "format" => format = ImportFormat::parse(&value),
+                // Left empty to import into the workspace without a list.
+                "list_id" if value.is_empty() => {}
+                "list_id" => match value.parse::<i64>() {
+                    Ok(id) => list_id = Some(id),
+                    Err(_) => return error_response(StatusCode::BAD_REQUEST, format!("\"{value}\" is not a list.")),
+                },
+                "commit" => commit = value == "true",
+                name => {
+                    if let Some(field) = name.strip_prefix("column.") {
+                        columns.set(field, Some(value));
+                    }
+                }
+            }
+        }
+
+        let Some((file_name, content)) = file else {
+            return error_response(StatusCode::BAD_REQUEST, "No file was uploaded.");
+        };
+        let format = format.unwrap_or_else(|| ImportFormat::guess(&file_name));
+        let mut preview = parse(format, &content, columns);
+        if !commit || preview.rows.is_empty() || preview.errors() > 0 {
+            return axum::Json(preview).into_response();
+        }
+
+        let workspace_id = match list_id {
+            Some(list_id) => match accessible_list(list_id, &user, ListRole::Editor, &pool).await {
+                Ok(list) => list.workspace_id,
+                Err(error) => return server_fn_error_response(error),
+            },
+            None => match session_workspace(&auth_session, &user, &pool).await {
+                Ok(workspace_id) => Some(workspace_id),
+                Err(error) => return server_fn_error_response(error),
+            },
+        };
+        let Some(workspace_id) = workspace_id else {
+            return server_fn_error_response(TodoAppError::Forbidden.into());
+        };
+
+        let todos = preview
+            .rows
+            .iter()
+            .filter_map(|row| row.todo.clone().ok())
+            .collect::<Vec<_>>();
+        let saved = async {
+            let mut tx = pool.begin().await?;
+            let last = save_todos(&todos, &user, workspace_id, list_id, &mut tx).await?;
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(last)
+        };
+        match saved.await {
+            Ok(last) => {
+                // This isn't a server function, so nothing publishes the change for
+                // us. Clients refetch everything on any change, so one will do.
+                if let Some(id) = last {
+                    changes.publish(TodoChange {
+                        action: AuditAction::TodoCreated,
+                        target_type: "todo".to_string(),
+                        target_id: id.into(),
+                        todo_id: Some(id),
+                        owner_id: Some(user.id),
+                        list_id,
+                        workspace_id: Some(workspace_id),
+                    });
+                }
+                preview.imported = Some(todos.len());
+                axum::Json(preview).into_response()
+            }
+            Err(error) => {
+                log::error!("Importing todos failed: {error:?}");
+                error_response(StatusCode::INTERNAL_SERVER_ERROR, "The todos could not be imported.")
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::todo_app::ssr::test_pool;
+
+        fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(year, month, day).unwrap()
+        }
+
+        fn todo(title: &str) -> ImportedTodo {
+            ImportedTodo {
+                title: title.to_string(),
+                notes: String::new(),
+                completed: false,
+                due_at: None,
+                priority: Priority::None,
+                tags: Vec::new(),
+            }
+        }
+
+        fn record(fields: &[&str]) -> Vec<String> {
+            fields.iter().map(|field| field.to_string()).collect()
+        }
+
+        #[test]
+        fn splits_csv_records() {
+            let csv = "title,notes\r\n\"Quoted, with comma\",\"He said \"\"hi\"\"\"\r\n\r\n\
+                \"Two\nlines\",after\nlast,\n";
+            assert_eq!(
+                csv_records(csv),
+                [
+                    (1, record(&["title", "notes"])),
+                    (2, record(&["Quoted, with comma", "He said \"hi\""])),
+                    (4, record(&["Two\nlines", "after"])),
+                    // Records after a line break inside a field keep their own line.
+                    (6, record(&["last", ""])),
+                ]
+            );
+        }
+
+        #[test]
+        fn detects_semicolons() {
+            assert_eq!(
+                csv_records("title;due\n\"a;b\";1,5\nc;"),
+                [(1, record(&["title", "due"])), (2, record(&["a;b", "1,5"])), (3, record(&["c", ""]))]
+            );
+            // A header with more commas than semicolons stays comma separated.
+            assert_eq!(csv_records("a,b;c\n1,2;3"), [(1, record(&["a", "b;c"])), (2, record(&["1", "2;3"]))]);
+            // The last line needs no line break.
+            assert_eq!(csv_records("a"), [(1, record(&["a"]))]);
+            assert!(csv_records("").is_empty());
+        }
+
+        #[test]
+        fn maps_csv_columns() {
+            let csv = "\u{feff}Task,Deadline,Priority,Done,Labels,Extra\n\
+                Buy milk,2024-01-31,High,x,\"home, shop\",ignored\n\
+                ,2024-01-31,,,,\n\
+                Pay rent,soon,,,,\n\
+                Call,,,maybe,,\n\
+                Plan,,whenever,,,\n";
+            let preview = parse(ImportFormat::Csv, csv, CsvColumns::default());
+            assert_eq!(preview.headers[0], "Task");
+            assert_eq!(preview.columns.title.as_deref(), Some("Task"));
+            assert_eq!(preview.columns.due_at.as_deref(), Some("Deadline"));
+            assert_eq!(preview.columns.notes, None);
+            assert_eq!(preview.errors(), 4);
+            assert_eq!(
+                preview.rows[0],
+                ImportRow {
+                    row: 2,
+                    todo: Ok(ImportedTodo {
+                        completed: true,
+                        due_at: Some(date(2024, 1, 31).and_time(NaiveTime::MIN)),
+                        priority: Priority::High,
+                        tags: vec!["home".to_string(), "shop".to_string()],
+                        ..todo("Buy milk")
+                    }),
+                }
+            );
+            assert_eq!(preview.rows[1].todo, Err("The title is empty.".to_string()));
+            assert_eq!(preview.rows[2].todo, Err("\"soon\" is not a date like 2024-01-31.".to_string()));
+            assert_eq!(preview.rows[3].todo, Err("\"maybe\" is neither done nor open.".to_string()));
+            assert_eq!(preview.rows[4].row, 6);
+            assert!(preview.rows[4].todo.is_err());
+
+            // Columns picked by hand replace the guesses entirely.
+            let mut columns = CsvColumns::default();
+            columns.set("title", Some("Extra".to_string()));
+            columns.set("notes", Some("Task".to_string()));
+            let preview = parse(ImportFormat::Csv, csv, columns);
+            assert_eq!(preview.rows[0].todo, Ok(ImportedTodo { notes: "Buy milk".to_string(), ..todo("ignored") }));
+            assert_eq!(preview.errors(), 4);
+
+            let preview = parse(ImportFormat::Csv, "Notes\nsomething\n", CsvColumns::default());
+            assert_eq!(preview.rows[0].todo, Err("No column is mapped to the title.".to_string()));
+        }
+
+        #[test]
+        fn reads_todo_txt() {
+            assert_eq!(
+                todo_txt("(A) 2024-01-02 Call mom +family @phone due:2024-06-01 url:https://example.com"),
+                Ok(ImportedTodo {
+                    due_at: Some(date(2024, 6, 1).and_time(NaiveTime::MIN)),
+                    priority: Priority::Urgent,
+                    tags: vec!["family".to_string(), "phone".to_string()],
+                    ..todo("Call mom url:https://example.com")
+                })
+            );
+            // Done, with the dates of completion and creation.
+            assert_eq!(
+                todo_txt("x 2024-01-03 2024-01-01 Done thing pri:C"),
+                Ok(ImportedTodo { completed: true, priority: Priority::Medium, ..todo("Done thing") })
+            );
+            assert_eq!(todo_txt("(B) Review").map(|todo| todo.priority), Ok(Priority::High));
+            assert_eq!(todo_txt("(Z) Someday").map(|todo| todo.priority), Ok(Priority::Low));
+            // Only a lone lowercase x at the start marks a todo done.
+            assert_eq!(todo_txt("xylophone lessons").map(|todo| todo.completed), Ok(false));
+            assert_eq!(todo_txt("X-ray").map(|todo| todo.title), Ok("X-ray".to_string()));
+            // Priorities and dates count only at the start, a lone + or @ isn't a tag.
+            assert_eq!(
+                todo_txt("Meet at 2024-01-01 (A) + @").map(|todo| (todo.title, todo.tags)),
+                Ok(("Meet at 2024-01-01 (A) + @".to_string(), vec![]))
+            );
+        }
+
+        #[test]
+        fn reports_todo_txt_problems() {
+            assert_eq!(todo_txt("(1) oops"), Err("\"1\" is not a priority, use a letter from A to Z.".to_string()));
+            assert_eq!(todo_txt("Pay due:tomorrow"), Err("\"tomorrow\" is not a date like 2024-01-31.".to_string()));
+            assert_eq!(todo_txt("x +just-a-tag"), Err("The title is empty.".to_string()));
+
+            let preview = parse(ImportFormat::TodoTxt, "First\n\n  \n(1) oops\n", CsvColumns::default());
+            assert_eq!(preview.rows.len(), 2);
+            assert_eq!(preview.rows[1].row, 4);
+        }
+
+        #[test]
+        fn reads_json_todos() {
+            let json = r##"[
+                {"id": 3, "title": "From the app", "notes": "Some *notes*", "completed": true,
+                 "due_at": "2024-02-03T10:00:00", "priority": "Urgent",
+                 "tags": [{"id": 1, "name": "work", "color": "#fff"}, "home"], "assignee": null},
+                {"title": "Bare"},
+                {"title": 5},
+                {"title": "Bad tags", "tags": "work"},
+                {"title": "Bad date", "due_at": "03/02/2024"},
+                "not an object"
+            ]"##;
+            let rows = json_rows(json);
+            assert_eq!(
+                rows[0].todo,
+                Ok(ImportedTodo {
+                    notes: "Some *notes*".to_string(),
+                    completed: true,
+                    due_at: date(2024, 2, 3).and_hms_opt(10, 0, 0),
+                    priority: Priority::Urgent,
+                    tags: vec!["work".to_string(), "home".to_string()],
+                    ..todo("From the app")
+                })
+            );
+            assert_eq!(rows[1].todo, Ok(todo("Bare")));
+            assert_eq!(rows[2].todo, Err("\"title\" must be text.".to_string()));
+            assert_eq!(rows[3].todo, Err("\"tags\" must be a list.".to_string()));
+            assert!(rows[4].todo.is_err());
+            assert_eq!(rows[5], ImportRow { row: 6, todo: Err("Expected an object.".to_string()) });
+        }
+
+        #[test]
+        fn reports_json_that_isnt_a_list_of_todos() {
+            assert_eq!(json_rows("{}")[0].todo, Err("Expected an array of todos.".to_string()));
+            let rows = json_rows("[\n{\"title\": \"a\"},\n{");
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].row, 3);
+            assert!(rows[0].todo.as_ref().is_err_and(|error| error.starts_with("This is not valid JSON")));
+            assert!(json_rows("[]").is_empty());
+        }
+
+        #[test]
+        fn parses_dates() {
+            let day = date(2024, 1, 31);
+            assert_eq!(parse_date(""), Ok(None));
+            assert_eq!(parse_date(" 2024-01-31 "), Ok(Some(day.and_time(NaiveTime::MIN))));
+            assert_eq!(parse_date("2024-01-31 09:30"), Ok(day.and_hms_opt(9, 30, 0)));
+            assert_eq!(parse_date("2024-01-31T09:30"), Ok(day.and_hms_opt(9, 30, 0)));
+            assert_eq!(parse_date("2024-01-31 09:30:15"), Ok(day.and_hms_opt(9, 30, 15)));
+            assert_eq!(parse_date("2024-01-31T09:30:15.250"), Ok(day.and_hms_milli_opt(9, 30, 15, 250)));
+            let utc = DateTime::parse_from_rfc3339("2024-01-31T09:30:00Z").unwrap();
+            assert_eq!(parse_date("2024-01-31T09:30:00Z"), Ok(Some(utc.with_timezone(&Local).naive_local())));
+            assert!(parse_date("2024-02-30").is_err());
+            assert!(parse_date("31.01.2024").is_err());
+        }
+
+        #[test]
+        fn reads_priorities_and_completion() {
+            assert_eq!(parse_priority("URGENT"), Ok(Priority::Urgent));
+            assert_eq!(parse_priority(" "), Ok(Priority::None));
+            assert!(parse_priority("3").is_err());
+            assert_eq!(parse_completed("Yes"), Ok(true));
+            assert_eq!(parse_completed("needs-action"), Ok(false));
+        }
+
+        #[tokio::test]
+        async fn saves_todos_with_their_tags() {
+            let pool = test_pool().await;
+            let user_id = sqlx::query_scalar::<_, i64>(
+                "INSERT INTO users (username, password) VALUES ('alice', '') RETURNING id",
+            )
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            let user = User::get(user_id, &pool).await.unwrap();
+            let workspace_id = sqlx::query_scalar::<_, i64>(
+                "INSERT INTO workspaces (name, created_by) VALUES ('Home', ?) RETURNING id",
+            )
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            let todos = [
+                ImportedTodo { tags: vec!["home".to_string(), "shop".to_string()], ..todo("a") },
+                ImportedTodo { completed: true, tags: vec!["home".to_string()], ..todo("b") },
+            ];
+
+            let mut tx = pool.begin().await.unwrap();
+            let last = save_todos(&todos, &user, workspace_id, None, &mut tx).await.unwrap();
+            tx.commit().await.unwrap();
+
+            let saved = sqlx::query_as::<_, (u32, String, bool)>(
+                "SELECT id, title, completed FROM todos WHERE user_id = ? ORDER BY id",
+            )
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            let titles = saved.iter().map(|(_, title, completed)| (title.as_str(), *completed)).collect::<Vec<_>>();
+            assert_eq!(titles, [("a", false), ("b", true)]);
+            assert_eq!(last, saved.last().map(|(id, ..)| *id));
+
+            let tags = sqlx::query_as::<_, (String, i64)>(
+                "SELECT tags.name, COUNT(*) FROM tags JOIN todo_tags ON todo_tags.tag_id = tags.id
+                WHERE tags.user_id = ? GROUP BY tags.name ORDER BY tags.name",
+            )
+            .bind(user_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+            assert_eq!(tags, [("home".to_string(), 2), ("shop".to_string(), 1)]);
+        }
+    }
+}
+
+/// Posts `file` to the import endpoint, returning the error message on failure.
+#[cfg(feature = "hydrate")]
+async fn upload(
+    file: web_sys::File,
+    format: String,
+    columns: CsvColumns,
+    list_id: String,
+    commit: bool,
+) -> Result<ImportPreview, String> {
+    let form = web_sys::FormData::new().map_err(|_| "Could not read the file.".to_string())?;
+    form.append_with_blob_and_filename("file", &file, &file.name())
+        .map_err(|_| "Could not read the file.".to_string())?;
+    let mut fields = vec![
+        ("format".to_string(), format),
+        ("list_id".to_string(), list_id),
+        ("commit".to_string(), commit.to_string()),
+    ];
+    for (field, _) in CsvColumns::FIELDS {
+        if let Some(header) = columns.get(field) {
+            fields.push((format!("column.{field}"), header.to_string()));
+        }
+    }
+    for (name, value) in fields {
+        form.append_with_str(&name, &value)
+            .map_err(|_| "Could not read the file.".to_string())?;
+    }
+
+    let response = gloo_net::http::Request::post("/todos/import")
+        .body(form)
+        .map_err(|error| error.to_string())?
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+
+    if response.ok() {
+        response.json::<ImportPreview>().await.map_err(|error| error.to_string())
+    } else {
+        Err(response.text().await.unwrap_or_else(|_| response.status_text()))
+    }
+}
+
+/// Uploads a file, previews the todos in it with the problems of each row,
+/// and imports them once there are none.
+#[component]
+pub fn ImportTodos() -> impl IntoView {
+    mount_style("todos", include_str!("./todos.css"));
+    let lists = Resource::new(|| (), |_| get_lists());
+    let file = RwSignal::new_local(None::<web_sys::File>);
+    let format = RwSignal::new(String::new());
+    let columns = CsvColumns::FIELDS.map(|_| RwSignal::new(String::new()));
+    let list_id = RwSignal::new(String::new());
+    let preview = RwSignal::new(None::<ImportPreview>);
+    let error = RwSignal::new(None::<String>);
+    let busy = RwSignal::new(false);
+
+    let current_columns = move || {
+        let mut current = CsvColumns::default();
+        for ((field, _), column) in CsvColumns::FIELDS.into_iter().zip(columns) {
+            current.set(field, Some(column.get()));
+        }
+        current
+    };
+
+    let send = move |commit: bool| {
+        let Some(selected) = file.get_untracked() else {
+            return;
+        };
+        error.set(None);
+        busy.set(true);
+        #[cfg(feature = "hydrate")]
+        leptos::task::spawn_local(async move {
+            let columns_sent = current_columns();
+            let result = upload(selected, format.get_untracked(), columns_sent, list_id.get_untracked(), commit).await;
+            busy.set(false);
+            match result {
+                Ok(result) => {
+                    // Only what the server guessed changes, so this doesn't upload again for nothing.
+                    if format.get_untracked() != result.format.as_str() {
+                        format.set(result.format.as_str().to_string());
+                    }
+                    for ((field, _), column) in CsvColumns::FIELDS.into_iter().zip(columns) {
+                        let header = result.columns.get(field).unwrap_or_default().to_string();
+                        if column.get_untracked() != header {
+                            column.set(header);
+                        }
+                    }
+                    if result.imported.is_some() {
+                        file.set(None);
+                    }
+                    preview.set(Some(result));
+                }
+                Err(message) => error.set(Some(message)),
+            }
+        });
+        #[cfg(not(feature = "hydrate"))]
+        let _ = (selected, commit, current_columns);
+    };
+
+    // Every change to the file or how to read it previews it again.
+    Effect::watch(
+        move || (file.with(|file| file.as_ref().map(|file| file.name())), format.get(), current_columns()),
+        move |_, _, _| send(false),
+        false,
+    );
+
+    let custom_request = move |files: web_sys::FileList| {
+        if let Some(selected) = files.item(0) {
+            format.set(ImportFormat::guess(&selected.name()).as_str().to_string());
+            for column in columns {
+                column.set(String::new());
+            }
+            preview.set(None);
+            file.set(Some(selected));
+        }
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Text>
+                "Bring in todos from other tools: CSV with a header row, a JSON array of todos, or todo.txt."
+            </Text>
+            <Flex align=FlexAlign::Center>
+                <Upload custom_request>
+                    <Button icon=icondata::LuUpload>"Choose file"</Button>
+                </Upload>
+                <Caption1>{move || file.with(|file| file.as_ref().map(|file| file.name()))}</Caption1>
+            </Flex>
+            <Flex align=FlexAlign::Center>
+                <Text>"Format"</Text>
+                <Select value=format>
+                    {ImportFormat::ALL
+                        .into_iter()
+                        .map(|format| view! { <option value=format.as_str()>{format.label()}</option> })
+                        .collect_view()}
+                </Select>
+                <Text>"Into"</Text>
+                <Select value=list_id>
+                    <option value="">"No list"</option>
+                    <Transition>
+                        {move || {
+                            lists
+                                .get()
+                                .and_then(Result::ok)
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter(|list| list.role >= super::members::ListRole::Editor)
+                                .map(|list| view! { <option value=list.id.to_string()>{list.name}</option> })
+                                .collect_view()
+                        }}
+                    </Transition>
+                </Select>
+            </Flex>
+            <Show when=move || {
+                format.get() == ImportFormat::Csv.as_str()
+                    && preview.with(|preview| preview.as_ref().is_some_and(|preview| !preview.headers.is_empty()))
+            }>
+                <Flex align=FlexAlign::Center class="todo-import-columns">
+                    {CsvColumns::FIELDS
+                        .into_iter()
+                        .zip(columns)
+                        .map(|((_, label), column)| view! {
+                            <Flex vertical=true gap=FlexGap::Small>
+                                <Caption1>{label}</Caption1>
+                                <Select value=column>
+                                    <option value="">"Not imported"</option>
+                                    {move || {
+                                        preview.with(|preview| {
+                                            preview
+                                                .iter()
+                                                .flat_map(|preview| preview.headers.clone())
+                                                .map(|header| view! { <option value=header.clone()>{header.clone()}</option> })
+                                                .collect_view()
+                                        })
+                                    }}
+                                </Select>
+                            </Flex>
+                        })
+                        .collect_view()}
+                </Flex>
+            </Show>
+            {move || error.get().map(|error| view! { <Text class="error">{error}</Text> })}
+            {move || {
+                preview.get().map(|preview| {
+                    let errors = preview.errors();
+                    let count = preview.rows.len();
+                    let summary = match preview.imported {
+                        Some(imported) => format!("Imported {imported} todos."),
+                        None if errors > 0 => format!("{errors} of {count} rows have problems. Fix them and choose the file again."),
+                        None => format!("{count} todos are ready to import."),
+                    };
+                    let can_import = preview.imported.is_none() && count > 0 && errors == 0;
+                    view! {
+                        <Flex align=FlexAlign::Center justify=FlexJustify::SpaceBetween>
+                            <Text>{summary}</Text>
+                            <Button
+                                appearance=ButtonAppearance::Primary
+                                icon=icondata::LuCheck
+                                disabled=Signal::derive(move || !can_import || busy.get())
+                                on_click=move |_| send(true)
+                            >
+                                {format!("Import {count} todos")}
+                            </Button>
+                        </Flex>
+                        {(count != 0).then(|| view! {
+                            <Card>
+                                <Table>
+                                    <TableHeader>
+                                        <TableRow>
+                                            <TableHeaderCell max_width=60.0>"Row"</TableHeaderCell>
+                                            <TableHeaderCell resizable=true min_width=200.0>"Title"</TableHeaderCell>
+                                            <TableHeaderCell max_width=120.0>"Due"</TableHeaderCell>
+                                            <TableHeaderCell max_width=100.0>"Priority"</TableHeaderCell>
+                                            <TableHeaderCell max_width=60.0>"Done"</TableHeaderCell>
+                                            <TableHeaderCell max_width=160.0>"Tags"</TableHeaderCell>
+                                        </TableRow>
+                                    </TableHeader>
+                                    <TableBody>
+                                        {preview
+                                            .rows
+                                            .clone()
+                                            .into_iter()
+                                            .map(|ImportRow { row, todo }| match todo {
+                                                Ok(todo) => view! {
+                                                    <TableRow>
+                                                        <TableCell>{row}</TableCell>
+                                                        <TableCell>
+                                                            <TableCellLayout truncate=true>{todo.title}</TableCellLayout>
+                                                        </TableCell>
+                                                        <TableCell>
+                                                            {todo.due_at.map(|due| due.format("%Y-%m-%d").to_string())}
+                                                        </TableCell>
+                                                        <TableCell>{todo.priority.label()}</TableCell>
+                                                        <TableCell>{todo.completed.then_some("Yes")}</TableCell>
+                                                        <TableCell>
+                                                            <TableCellLayout truncate=true>{todo.tags.join(", ")}</TableCellLayout>
+                                                        </TableCell>
+                                                    </TableRow>
+                                                }
+                                                    .into_any(),
+                                                Err(message) => view! {
+                                                    <TableRow class="todo-import-error">
+                                                        <TableCell>{row}</TableCell>
+                                                        <TableCell>
+                                                            <Text class="error">{message}</Text>
+                                                        </TableCell>
+                                                    </TableRow>
+                                                }
+                                                    .into_any(),
+                                            })
+                                            .collect_view()}
+                                    </TableBody>
+                                </Table>
+                            </Card>
+                        })}
+                    }
+                })
+            }}
+        </Flex>
+    }
+}
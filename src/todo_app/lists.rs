@@ -0,0 +1,412 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use super::members::{ListMembers, ListRole};
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoList {
+    pub id: i64,
+    pub name: String,
+    pub created_at: String,
+    /// What the current user can do with the list.
+    pub role: ListRole,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::TodoList;
+    use crate::auth::User;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::members::{ssr::require_list_role, ListRole};
+    use leptos::prelude::*;
+    use sqlx::SqlitePool;
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlTodoList {
+        pub id: i64,
+        pub user_id: i64,
+        pub name: String,
+        pub created_at: String,
+        pub workspace_id: Option<i64>,
+    }
+
+    impl SqlTodoList {
+        pub fn into_list(self, role: ListRole) -> TodoList {
+            TodoList {
+                id: self.id,
+                name: self.name,
+                created_at: self.created_at,
+                role,
+            }
+        }
+    }
+
+    /// A list with the role the current user has in it.
+    #[derive(sqlx::FromRow)]
+    pub struct SqlListRow {
+        #[sqlx(flatten)]
+        pub list: SqlTodoList,
+        pub role: ListRole,
+    }
+
+    /// Loads a list, failing with `Forbidden` unless `user` has at least `role` in it.
+    pub async fn accessible_list(
+        id: i64,
+        user: &User,
+        role: ListRole,
+        pool: &SqlitePool,
+    ) -> Result<SqlTodoList, ServerFnError<TodoAppError>> {
+        let list = sqlx::query_as::<_, SqlTodoList>("SELECT * FROM lists WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .ok_or(TodoAppError::NotFound)?;
+
+        require_list_role(id, user, role, pool).await?;
+        Ok(list)
+    }
+}
+
+/// The lists in the current workspace the current user is a member of, their
+/// own and those shared with them.
+#[server(GetLists, "/api")]
+pub async fn get_lists() -> Result<Vec<TodoList>, ServerFnError<TodoAppError>> {
+    use self::ssr::SqlListRow;
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(Vec::new());
+    };
+    let workspace_id = current_workspace(&user, &pool).await?;
+
+    Ok(sqlx::query_as::<_, SqlListRow>(
+        "SELECT lists.*, list_members.role FROM lists
+        JOIN list_members ON list_members.list_id = lists.id
+        WHERE list_members.user_id = ? AND lists.workspace_id = ? ORDER BY lists.name",
+    )
+    .bind(user.id)
+    .bind(workspace_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .into_iter()
+    .map(|row| row.list.into_list(row.role))
+    .collect())
+}
+
+#[server(GetList, "/api")]
+pub async fn get_list(id: i64) -> Result<TodoList, ServerFnError<TodoAppError>> {
+    use self::ssr::accessible_list;
+    use crate::todo_app::members::ssr::require_list_role;
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let list = accessible_list(id, &user, ListRole::Viewer, &pool).await?;
+    let role = require_list_role(id, &user, ListRole::Viewer, &pool).await?;
+    Ok(list.into_list(role))
+}
+
+#[server(AddList, "/api")]
+pub async fn add_list(name: String) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::statuses::ssr::create_default_statuses;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "List name cannot be empty.".to_string(),
+        ));
+    }
+
+    let workspace_id = current_workspace(&user, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let id = sqlx::query("INSERT INTO lists (user_id, name, workspace_id) VALUES (?, ?, ?)")
+        .bind(user.id)
+        .bind(name.trim())
+        .bind(workspace_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?
+        .last_insert_rowid();
+
+    sqlx::query("INSERT INTO list_members (list_id, user_id, role) VALUES (?, ?, ?)")
+        .bind(id)
+        .bind(user.id)
+        .bind(ListRole::Owner)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    create_default_statuses(id, &mut tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::ListCreated,
+        AuditTarget::list(id),
+        None,
+        Some(serde_json::json!({ "name": name.trim() })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(RenameList, "/api")]
+pub async fn rename_list(
+    id: i64,
+    name: String,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::accessible_list;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "List name cannot be empty.".to_string(),
+        ));
+    }
+
+    let list = accessible_list(id, &user, ListRole::Owner, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE lists SET name = ? WHERE id = ?")
+        .bind(name.trim())
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::ListRenamed,
+        AuditTarget::list(id),
+        Some(serde_json::json!({ "name": list.name })),
+        Some(serde_json::json!({ "name": name.trim() })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Only empty lists can be deleted. The `ON DELETE CASCADE` on `todos.list_id`
+/// would take any todos left in it along, even those still in the trash.
+#[server(DeleteList, "/api")]
+pub async fn delete_list(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::accessible_list;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let list = accessible_list(id, &user, ListRole::Owner, &pool).await?;
+
+    // Take the write lock up front, so no todo is added between the check and the delete.
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await.map_err(TodoAppError::from)?;
+
+    let (has_todos,) = sqlx::query_as::<_, (bool,)>("SELECT EXISTS (SELECT 1 FROM todos WHERE list_id = ?)")
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    if has_todos {
+        return Err(ServerFnError::ServerError(format!(
+            "\"{}\" still has todos, counting those in the trash. Delete them first.",
+            list.name
+        )));
+    }
+
+    sqlx::query("DELETE FROM lists WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::ListDeleted,
+        AuditTarget::list(id),
+        Some(serde_json::json!({ "name": list.name })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[component]
+pub fn Lists() -> impl IntoView {
+    let add_list = ServerAction::<AddList>::new();
+    let rename_list = ServerAction::<RenameList>::new();
+    let delete_list = ServerAction::<DeleteList>::new();
+
+    let lists = Resource::new(
+        move || {
+            (
+                add_list.version().get(),
+                rename_list.version().get(),
+                delete_list.version().get(),
+            )
+        },
+        move |_| get_lists(),
+    );
+
+    let error = move || {
+        [
+            add_list.value().get().and_then(Result::err),
+            rename_list.value().get().and_then(Result::err),
+            delete_list.value().get().and_then(Result::err),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    let name = RwSignal::new(String::from(""));
+    let sharing = RwSignal::new(None::<TodoList>);
+    let sharing_open = RwSignal::new(false);
+
+    view! {
+        <Flex vertical=true>
+            {error}
+            <Flex justify=FlexJustify::Center gap=FlexGap::Small>
+                <Text>"New List"</Text>
+                <Input value=name />
+                <Button appearance=ButtonAppearance::Primary on_click=move |_| {
+                    add_list.dispatch(AddList { name: name.get() });
+                    name.set(String::from(""));
+                }>"Add"</Button>
+            </Flex>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                <ErrorBoundary fallback=|errors| {
+                    view! { <ErrorTemplate errors=errors/> }
+                }>
+                    <Card>
+                        <Table>
+                            <TableHeader>
+                                <TableRow>
+                                    <TableHeaderCell resizable=true min_width=100.0>"Name"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Role"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Members"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Open"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Delete"</TableHeaderCell>
+                                </TableRow>
+                            </TableHeader>
+                            <TableBody>
+                                {move || {
+                                    lists.get().map(move |lists| match lists {
+                                        Err(e) => {
+                                            view! {
+                                                <pre class="error">"Server Error: " {e.to_string()}</pre>
+                                            }
+                                                .into_any()
+                                        },
+                                        Ok(lists) => {
+                                            if lists.is_empty() {
+                                                view! { <p>"No lists were found."</p> }.into_any()
+                                            } else {
+                                                lists
+                                                    .into_iter()
+                                                    .map(move |list| {
+                                                        let id = list.id;
+                                                        let is_owner = list.role == ListRole::Owner;
+                                                        let name = RwSignal::new(list.name.clone());
+                                                        let saved_name = list.name.clone();
+                                                        view! {
+                                                            <TableRow>
+                                                                <TableCell>
+                                                                    <TableCellLayout truncate=true>
+                                                                        <Input
+                                                                            value=name
+                                                                            disabled=!is_owner
+                                                                            on_blur=move |_| {
+                                                                                let new_name = name.get_untracked();
+                                                                                if new_name != saved_name {
+                                                                                    rename_list.dispatch(RenameList { id, name: new_name });
+                                                                                }
+                                                                            }
+                                                                        />
+                                                                    </TableCellLayout>
+                                                                </TableCell>
+                                                                <TableCell>{list.role.label()}</TableCell>
+                                                                <TableCell>
+                                                                    <Button
+                                                                        appearance=ButtonAppearance::Subtle
+                                                                        icon=icondata::LuUsers
+                                                                        on_click=move |_| {
+                                                                            sharing.set(Some(list.clone()));
+                                                                            sharing_open.set(true);
+                                                                        }
+                                                                    />
+                                                                </TableCell>
+                                                                <TableCell>
+                                                                    <Link href=format!("/lists/{id}")>"Open"</Link>
+                                                                </TableCell>
+                                                                <TableCell>
+                                                                    <Button
+                                                                        disabled=!is_owner
+                                                                        on_click=move |_| {
+                                                                            delete_list.dispatch(DeleteList { id });
+                                                                        }
+                                                                        icon=icondata::AiCloseCircleOutlined
+                                                                    />
+                                                                </TableCell>
+                                                            </TableRow>
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                                    .into_any()
+                                            }
+                                        }
+                                    })
+                                }}
+                            </TableBody>
+                        </Table>
+                    </Card>
+                </ErrorBoundary>
+            </Transition>
+            <OverlayDrawer open=sharing_open position=DrawerPosition::Right size=DrawerSize::Medium>
+                <DrawerHeader>
+                    <DrawerHeaderTitle>
+                        <DrawerHeaderTitleAction slot>
+                            <Button
+                                appearance=ButtonAppearance::Subtle
+                                icon=icondata::LuX
+                                on_click=move |_| sharing_open.set(false)
+                            />
+                        </DrawerHeaderTitleAction>
+                        {move || sharing.with(|list| list.as_ref().map(|list| format!("Members of {}", list.name)))}
+                    </DrawerHeaderTitle>
+                </DrawerHeader>
+                <DrawerBody>
+                    {move || {
+                        sharing.get().map(|list| view! { <ListMembers list_id=list.id role=list.role /> })
+                    }}
+                </DrawerBody>
+            </OverlayDrawer>
+        </Flex>
+    }
+}
@@ -0,0 +1,244 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::audit::AuditAction;
+
+/// Sent to connected clients whenever something they can see changed. It
+/// mirrors the audit event of the change, without the data itself.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoChange {
+    pub action: AuditAction,
+    pub target_type: String,
+    pub target_id: i64,
+    pub todo_id: Option<u32>,
+    /// Whose todos, list or tag changed. Only they, admins and the members
+    /// of `list_id` are told, and only within `workspace_id`.
+    pub owner_id: Option<i64>,
+    pub list_id: Option<i64>,
+    pub workspace_id: Option<i64>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::TodoChange;
+    use crate::auth::{ssr::AuthSession, User};
+    use crate::todo_app::audit::AuditAction;
+    use crate::todo_app::members::ssr::list_role;
+    use crate::todo_app::workspaces::ssr::workspace_role;
+    use crate::todo_app::ssr::is_todos_admin;
+    use axum::{
+        extract::State,
+        http::StatusCode,
+        response::{
+            sse::{Event, KeepAlive, Sse},
+            IntoResponse, Response,
+        },
+    };
+    use leptos::prelude::*;
+    use sqlx::SqlitePool;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::broadcast::{self, error::RecvError};
+
+    /// How many changes a slow client can fall behind before it has to refetch everything.
+    const CHANGE_BUFFER: usize = 256;
+
+    /// Fans todo changes out to everyone connected to [`todo_events`].
+    #[derive(Clone, Debug)]
+    pub struct ChangeHub {
+        sender: broadcast::Sender<TodoChange>,
+    }
+
+    impl Default for ChangeHub {
+        fn default() -> Self {
+            Self {
+                sender: broadcast::channel(CHANGE_BUFFER).0,
+            }
+        }
+    }
+
+    impl ChangeHub {
+        pub fn publish(&self, change: TodoChange) {
+            // Nobody listening is fine, there is just no one to tell.
+            let _ = self.sender.send(change);
+        }
+
+        /// Publishes what a server function queued up, once it has finished
+        /// and its transaction is committed. Only call this when it succeeded,
+        /// what failed was rolled back.
+        pub fn publish_pending(&self, pending: &PendingChanges) {
+            for change in pending.0.lock().unwrap().drain(..) {
+                self.publish(change);
+            }
+        }
+    }
+
+    /// Changes made while handling a single request.
+    #[derive(Clone, Debug, Default)]
+    pub struct PendingChanges(Arc<Mutex<Vec<TodoChange>>>);
+
+    /// Queues `change` for the [`ChangeHub`] when the current request is done.
+    /// Outside of a server function there is nothing to queue it on.
+    pub fn queue_change(change: TodoChange) {
+        if let Some(pending) = use_context::<PendingChanges>() {
+            pending.0.lock().unwrap().push(change);
+        }
+    }
+
+    /// What one connected user gets to hear about. The answers are kept, so a
+    /// change doesn't cost every connection its own queries, and forgotten
+    /// whenever someone joins or leaves a list or workspace.
+    struct Visibility {
+        user: User,
+        is_admin: bool,
+        workspaces: HashMap<i64, bool>,
+        lists: HashMap<i64, bool>,
+    }
+
+    impl Visibility {
+        async fn sees(&mut self, change: &TodoChange, pool: &SqlitePool) -> bool {
+            if matches!(
+                change.action,
+                AuditAction::ListMemberAdded
+                    | AuditAction::ListMemberUpdated
+                    | AuditAction::ListMemberRemoved
+                    | AuditAction::ListDeleted
+                    | AuditAction::WorkspaceMemberAdded
+                    | AuditAction::WorkspaceMemberRemoved
+            ) {
+                self.workspaces.clear();
+                self.lists.clear();
+            }
+
+            let is_owner = change.owner_id == Some(self.user.id);
+            let Some(workspace_id) = change.workspace_id else {
+                return self.is_admin || is_owner;
+            };
+            // Nothing crosses from one tenant to another, not even for admins.
+            if !self.in_workspace(workspace_id, pool).await {
+                return false;
+            }
+            match change.list_id {
+                _ if self.is_admin || is_owner => true,
+                Some(list_id) => self.in_list(list_id, pool).await,
+                None => false,
+            }
+        }
+
+        async fn in_workspace(&mut self, workspace_id: i64, pool: &SqlitePool) -> bool {
+            if let Some(member) = self.workspaces.get(&workspace_id) {
+                return *member;
+            }
+            match workspace_role(workspace_id, &self.user, pool).await {
+                Ok(role) => *self.workspaces.entry(workspace_id).or_insert(role.is_some()),
+                Err(_) => false,
+            }
+        }
+
+        async fn in_list(&mut self, list_id: i64, pool: &SqlitePool) -> bool {
+            if let Some(member) = self.lists.get(&list_id) {
+                return *member;
+            }
+            match list_role(list_id, &self.user, pool).await {
+                Ok(role) => *self.lists.entry(list_id).or_insert(role.is_some()),
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// `GET /todos/events`, a server-sent event stream of [`TodoChange`]s as JSON.
+    pub async fn todo_events(
+        State(hub): State<ChangeHub>,
+        State(pool): State<SqlitePool>,
+        auth_session: AuthSession,
+    ) -> Response {
+        // No Content tells the browser to stop reconnecting.
+        let Some(user) = auth_session.current_user else {
+            return StatusCode::NO_CONTENT.into_response();
+        };
+        let visibility = Visibility {
+            is_admin: is_todos_admin(&user, &pool).await,
+            user,
+            workspaces: HashMap::new(),
+            lists: HashMap::new(),
+        };
+
+        let state = (hub.sender.subscribe(), pool, visibility);
+        let events = futures::stream::unfold(state, move |(mut receiver, pool, mut visibility)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(change) => {
+                        if visibility.sees(&change, &pool).await {
+                            let event = Event::default().json_data(&change).unwrap_or_default();
+                            return Some((Ok::<_, Infallible>(event), (receiver, pool, visibility)));
+                        }
+                    }
+                    // Changes were dropped, so the client can't know what's current anymore.
+                    Err(RecvError::Lagged(_)) => {
+                        let event = Event::default().event("lagged").data("");
+                        return Some((Ok(event), (receiver, pool, visibility)));
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+    }
+}
+
+/// Counts the changes pushed by the server, see [`provide_todo_changes`].
+#[derive(Clone, Copy, Debug)]
+pub struct TodoChanges(ReadSignal<u64>);
+
+/// Subscribes to the server's change stream for as long as the calling
+/// component lives. Resources that read [`use_todo_changes`] below it refetch
+/// whenever a todo changes, wherever that happened.
+pub fn provide_todo_changes() {
+    let (changes, set_changes) = signal(0u64);
+    provide_context(TodoChanges(changes));
+
+    #[cfg(feature = "hydrate")]
+    {
+        use futures::{
+            future::{AbortHandle, Abortable},
+            StreamExt,
+        };
+        use gloo_net::eventsource::futures::EventSource;
+
+        let (abort, registration) = AbortHandle::new_pair();
+        leptos::task::spawn_local(async move {
+            let _ = Abortable::new(
+                async move {
+                    let Ok(mut source) = EventSource::new("/todos/events") else {
+                        return;
+                    };
+                    let (Ok(messages), Ok(lagged)) = (source.subscribe("message"), source.subscribe("lagged")) else {
+                        return;
+                    };
+                    let mut events = futures::stream::select(messages, lagged);
+                    while let Some(event) = events.next().await {
+                        if event.is_ok() {
+                            set_changes.update(|changes| *changes += 1);
+                        }
+                    }
+                },
+                registration,
+            )
+            .await;
+        });
+        on_cleanup(move || abort.abort());
+    }
+    #[cfg(not(feature = "hydrate"))]
+    let _ = set_changes;
+}
+
+/// How many changes the server reported so far, or always 0 without
+/// [`provide_todo_changes`] further up.
+pub fn use_todo_changes() -> Signal<u64> {
+    match use_context::<TodoChanges>() {
+        Some(TodoChanges(changes)) => changes.into(),
+        None => Signal::stored(0),
+    }
+}
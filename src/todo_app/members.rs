@@ -0,0 +1,480 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use super::live::use_todo_changes;
+use crate::errors::TodoAppError;
+
+/// What a member can do with a shared list and its todos. Every role can do
+/// everything the roles before it can.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "lowercase"))]
+pub enum ListRole {
+    /// Sees the list and its todos.
+    Viewer,
+    /// Adds, edits, completes and comments on todos.
+    Editor,
+    /// Deletes todos, renames or deletes the list and manages its members.
+    Owner,
+}
+
+impl ListRole {
+    pub const ALL: [ListRole; 3] = [ListRole::Viewer, ListRole::Editor, ListRole::Owner];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListRole::Viewer => "viewer",
+            ListRole::Editor => "editor",
+            ListRole::Owner => "owner",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        ListRole::ALL.into_iter().find(|role| role.as_str() == value)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ListRole::Viewer => "Viewer",
+            ListRole::Editor => "Editor",
+            ListRole::Owner => "Owner",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListMember {
+    pub user_id: i64,
+    pub username: String,
+    pub role: ListRole,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{ListMember, ListRole};
+    use crate::auth::User;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::ssr::is_todos_admin;
+    use leptos::prelude::*;
+    use sqlx::{Sqlite, SqlitePool, Transaction};
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlListMember {
+        pub user_id: i64,
+        pub username: String,
+        pub role: ListRole,
+    }
+
+    impl SqlListMember {
+        pub fn into_member(self) -> ListMember {
+            ListMember {
+                user_id: self.user_id,
+                username: self.username,
+                role: self.role,
+            }
+        }
+    }
+
+    /// The role `user` has in the list `list_id`, `None` if they aren't a
+    /// member. Admins can do anything with every list in their workspaces.
+    pub async fn list_role(
+        list_id: i64,
+        user: &User,
+        pool: &SqlitePool,
+    ) -> Result<Option<ListRole>, sqlx::Error> {
+        let row = sqlx::query_as::<_, (bool, Option<ListRole>)>(
+            "SELECT workspace_members.user_id IS NOT NULL, list_members.role FROM lists
+            LEFT JOIN workspace_members
+                ON workspace_members.workspace_id = lists.workspace_id AND workspace_members.user_id = ?1
+            LEFT JOIN list_members ON list_members.list_id = lists.id AND list_members.user_id = ?1
+            WHERE lists.id = ?2",
+        )
+        .bind(user.id)
+        .bind(list_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(match row {
+            Some((true, _)) if is_todos_admin(user, pool).await => Some(ListRole::Owner),
+            Some((true, role)) => role,
+            _ => None,
+        })
+    }
+
+    /// Fails with `Forbidden` unless `user` has at least `role` in the list.
+    pub async fn require_list_role(
+        list_id: i64,
+        user: &User,
+        role: ListRole,
+        pool: &SqlitePool,
+    ) -> Result<ListRole, ServerFnError<TodoAppError>> {
+        match list_role(list_id, user, pool).await.map_err(TodoAppError::from)? {
+            Some(actual) if actual >= role => Ok(actual),
+            _ => Err(TodoAppError::Forbidden.into()),
+        }
+    }
+
+    pub async fn list_member(
+        list_id: i64,
+        user_id: i64,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<SqlListMember, ServerFnError<TodoAppError>> {
+        Ok(sqlx::query_as::<_, SqlListMember>(
+            "SELECT list_members.user_id, users.username, list_members.role
+            FROM list_members JOIN users ON users.id = list_members.user_id
+            WHERE list_members.list_id = ? AND list_members.user_id = ?",
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(TodoAppError::from)?
+        .ok_or(TodoAppError::NotFound)?)
+    }
+
+    /// A list can't be left without an owner, someone has to be able to manage it.
+    /// Owners demoting each other at once would both see the other one, so
+    /// `tx` has to hold the write lock, see `BEGIN IMMEDIATE`.
+    pub async fn ensure_other_owner(
+        list_id: i64,
+        user_id: i64,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), ServerFnError<TodoAppError>> {
+        let other_owners = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM list_members WHERE list_id = ? AND user_id != ? AND role = 'owner'",
+        )
+        .bind(list_id)
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+        if other_owners == 0 {
+            Err(ServerFnError::ServerError(
+                "A list needs at least one owner.".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// How membership changes show up in the audit log, keyed by username
+    /// so the diff says whose role changed.
+    pub fn member_snapshot(username: &str, role: ListRole) -> Option<serde_json::Value> {
+        Some(serde_json::json!({ username: role.as_str() }))
+    }
+}
+
+/// Everyone in a list, owners first.
+#[server(GetListMembers, "/api")]
+pub async fn get_list_members(list_id: i64) -> Result<Vec<ListMember>, ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    require_list_role(list_id, &user, ListRole::Viewer, &pool).await?;
+
+    Ok(sqlx::query_as::<_, SqlListMember>(
+        "SELECT list_members.user_id, users.username, list_members.role
+        FROM list_members JOIN users ON users.id = list_members.user_id
+        WHERE list_members.list_id = ?
+        ORDER BY CASE list_members.role WHEN 'owner' THEN 0 WHEN 'editor' THEN 1 ELSE 2 END, users.username",
+    )
+    .bind(list_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .into_iter()
+    .map(SqlListMember::into_member)
+    .collect())
+}
+
+/// Shares a list with another member of its workspace.
+#[server(AddListMember, "/api")]
+pub async fn add_list_member(
+    list_id: i64,
+    username: String,
+    role: ListRole,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::auth::User;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    require_list_role(list_id, &user, ListRole::Owner, &pool).await?;
+
+    let username = username.trim().trim_start_matches('@');
+    let Some(member) = User::get_from_username(username.to_string(), &pool).await else {
+        return Err(ServerFnError::ServerError(format!(
+            "There is no user named {username}."
+        )));
+    };
+    let in_workspace = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM lists JOIN workspace_members
+            ON workspace_members.workspace_id = lists.workspace_id
+            WHERE lists.id = ? AND workspace_members.user_id = ?)",
+    )
+    .bind(list_id)
+    .bind(member.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+    if !in_workspace {
+        return Err(ServerFnError::ServerError(format!(
+            "{username} is not a member of this workspace."
+        )));
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let added = sqlx::query(
+        "INSERT OR IGNORE INTO list_members (list_id, user_id, role) VALUES (?, ?, ?)",
+    )
+    .bind(list_id)
+    .bind(member.id)
+    .bind(role)
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?
+    .rows_affected();
+    if added == 0 {
+        return Err(ServerFnError::ServerError(format!(
+            "{username} is already a member of this list."
+        )));
+    }
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::ListMemberAdded,
+        AuditTarget::list(list_id),
+        None,
+        member_snapshot(&member.username, role),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(SetListMemberRole, "/api")]
+pub async fn set_list_member_role(
+    list_id: i64,
+    user_id: i64,
+    role: ListRole,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    require_list_role(list_id, &user, ListRole::Owner, &pool).await?;
+
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await.map_err(TodoAppError::from)?;
+    let member = list_member(list_id, user_id, &mut tx).await?;
+    if member.role == ListRole::Owner && role != ListRole::Owner {
+        ensure_other_owner(list_id, user_id, &mut tx).await?;
+    }
+    sqlx::query("UPDATE list_members SET role = ? WHERE list_id = ? AND user_id = ?")
+        .bind(role)
+        .bind(list_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::ListMemberUpdated,
+        AuditTarget::list(list_id),
+        member_snapshot(&member.username, member.role),
+        member_snapshot(&member.username, role),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Owners can remove anyone, and every member can leave a list on their own.
+#[server(RemoveListMember, "/api")]
+pub async fn remove_list_member(
+    list_id: i64,
+    user_id: i64,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if user_id != user.id {
+        require_list_role(list_id, &user, ListRole::Owner, &pool).await?;
+    }
+
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await.map_err(TodoAppError::from)?;
+    let member = list_member(list_id, user_id, &mut tx).await?;
+    if member.role == ListRole::Owner {
+        ensure_other_owner(list_id, user_id, &mut tx).await?;
+    }
+    sqlx::query("DELETE FROM list_members WHERE list_id = ? AND user_id = ?")
+        .bind(list_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::ListMemberRemoved,
+        AuditTarget::list(list_id),
+        member_snapshot(&member.username, member.role),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// The members of a list. Owners can invite people by username, change their
+/// roles and remove them, everyone else only sees who is in it.
+#[component]
+pub fn ListMembers(list_id: i64, role: ListRole) -> impl IntoView {
+    let add_member = ServerAction::<AddListMember>::new();
+    let set_role = ServerAction::<SetListMemberRole>::new();
+    let remove_member = ServerAction::<RemoveListMember>::new();
+
+    let changes = use_todo_changes();
+    let members = Resource::new(
+        move || {
+            (
+                add_member.version().get(),
+                set_role.version().get(),
+                remove_member.version().get(),
+                changes.get(),
+            )
+        },
+        move |_| get_list_members(list_id),
+    );
+
+    let is_owner = role == ListRole::Owner;
+    let username = RwSignal::new(String::new());
+    let new_role = RwSignal::new(ListRole::Editor.as_str().to_string());
+
+    let error = move || {
+        [
+            add_member.value().get().and_then(Result::err),
+            set_role.value().get().and_then(Result::err),
+            remove_member.value().get().and_then(Result::err),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                <Table>
+                    <TableHeader>
+                        <TableRow>
+                            <TableHeaderCell resizable=true min_width=100.0>"User"</TableHeaderCell>
+                            <TableHeaderCell max_width=140.0>"Role"</TableHeaderCell>
+                            <TableHeaderCell max_width=80.0>"Remove"</TableHeaderCell>
+                        </TableRow>
+                    </TableHeader>
+                    <TableBody>
+                        {move || {
+                            members.get().map(|members| match members {
+                                Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                                Ok(members) => {
+                                    members
+                                        .into_iter()
+                                        .map(|member| {
+                                            let user_id = member.user_id;
+                                            let label = member.role.label();
+                                            let member_role = RwSignal::new(member.role.as_str().to_string());
+                                            view! {
+                                                <TableRow>
+                                                    <TableCell>
+                                                        <TableCellLayout truncate=true>{member.username}</TableCellLayout>
+                                                    </TableCell>
+                                                    <TableCell>
+                                                        <Show
+                                                            when=move || is_owner
+                                                            fallback=move || label
+                                                        >
+                                                            <Select
+                                                                value=member_role
+                                                                on:change=move |_| {
+                                                                    if let Some(role) = ListRole::parse(&member_role.get_untracked()) {
+                                                                        set_role.dispatch(SetListMemberRole { list_id, user_id, role });
+                                                                    }
+                                                                }
+                                                            >
+                                                                {ListRole::ALL
+                                                                    .into_iter()
+                                                                    .map(|role| view! { <option value=role.as_str()>{role.label()}</option> })
+                                                                    .collect_view()}
+                                                            </Select>
+                                                        </Show>
+                                                    </TableCell>
+                                                    <TableCell>
+                                                        <Show when=move || is_owner>
+                                                            <Button
+                                                                appearance=ButtonAppearance::Subtle
+                                                                icon=icondata::AiCloseCircleOutlined
+                                                                on_click=move |_| {
+                                                                    remove_member.dispatch(RemoveListMember { list_id, user_id });
+                                                                }
+                                                            />
+                                                        </Show>
+                                                    </TableCell>
+                                                </TableRow>
+                                            }
+                                        })
+                                        .collect_view()
+                                        .into_any()
+                                }
+                            })
+                        }}
+                    </TableBody>
+                </Table>
+            </Transition>
+            {error}
+            <Show when=move || is_owner>
+                <Flex align=FlexAlign::Center gap=FlexGap::Small>
+                    <Input value=username placeholder="Username" />
+                    <Select value=new_role>
+                        {ListRole::ALL
+                            .into_iter()
+                            .map(|role| view! { <option value=role.as_str()>{role.label()}</option> })
+                            .collect_view()}
+                    </Select>
+                    <Button
+                        appearance=ButtonAppearance::Primary
+                        icon=icondata::LuUserPlus
+                        on_click=move |_| {
+                            let role = ListRole::parse(&new_role.get_untracked()).unwrap_or(ListRole::Editor);
+                            if !username.get_untracked().trim().is_empty() {
+                                add_member.dispatch(AddListMember { list_id, username: username.get_untracked(), role });
+                                username.set(String::new());
+                            }
+                        }
+                    >
+                        "Invite"
+                    </Button>
+                </Flex>
+            </Show>
+        </Flex>
+    }
+}
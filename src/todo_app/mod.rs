@@ -1,44 +1,207 @@
 
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use leptos::ev::DragEvent;
 use leptos::prelude::*;
+use leptos::reactive::wrappers::write::SignalSetter;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use thaw::*;
+use thaw_utils::mount_style;
 
-use crate::{auth::*, error_template::ErrorTemplate, apps::layout::AppLayout};
+use crate::{auth::*, error_template::ErrorTemplate, errors::TodoAppError, apps::layout::AppLayout};
+use self::assignees::*;
+use self::dependencies::*;
+use self::detail::*;
+use self::live::*;
+use self::notes::*;
+use self::ordering::*;
+use self::priority::*;
+use self::query::*;
+use self::recurrence::*;
+use self::subtasks::*;
+use self::tags::*;
+use self::trash::*;
+
+pub mod assignees;
+pub mod attachments;
+pub mod audit;
+pub mod board;
+pub mod calendar;
+pub mod caldav;
+pub mod comments;
+pub mod dependencies;
+pub mod detail;
+pub mod feeds;
+pub mod imports;
+pub mod lists;
+pub mod live;
+pub mod members;
+pub mod notes;
+pub mod ordering;
+pub mod priority;
+pub mod query;
+pub mod recurrence;
+pub mod search;
+pub mod statuses;
+pub mod subtasks;
+pub mod tags;
+pub mod trash;
+pub mod workspaces;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Todo {
     id: u32,
     user: Option<User>,
+    /// Who is doing it, which needn't be who created it.
+    assignee: Option<User>,
     title: String,
+    /// Markdown, see [`notes::render_markdown`].
+    notes: String,
     created_at: String,
     completed: bool,
+    due_at: Option<NaiveDateTime>,
+    remind_at: Option<NaiveDateTime>,
+    list_id: Option<i64>,
+    /// Column of the list's board, see [`statuses`]. `completed` follows it.
+    status_id: Option<i64>,
+    parent_id: Option<u32>,
+    recurrence: Option<Recurrence>,
+    priority: Priority,
+    /// Set while the todo is in the trash.
+    deleted_at: Option<NaiveDateTime>,
+    /// Completion of all subtasks below this todo, `None` if it has none.
+    progress: Option<SubtaskProgress>,
+    dependencies: DependencyCounts,
+    tags: Vec<TodoTag>,
+}
+
+impl Todo {
+    /// Open todos whose due date has already passed.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.completed && self.due_at.is_some_and(|due| due.date() < today)
+    }
+
+    pub fn is_due_today(&self, today: NaiveDate) -> bool {
+        !self.completed && self.due_at.is_some_and(|due| due.date() == today)
+    }
 }
 
 #[cfg(feature = "ssr")]
 pub mod ssr {
     use super::Todo;
+    use super::dependencies::ssr::dependency_counts;
+    use super::members::ssr::list_role;
+    use super::workspaces::ssr::workspace_role;
+    pub use super::members::ListRole;
+    use super::priority::Priority;
+    use super::subtasks::ssr::subtask_progress;
+    use super::tags::ssr::tags_for_todo;
     use crate::auth::{ssr::AuthSession, User};
+    use crate::errors::TodoAppError;
+    use axum_session_auth::HasPermission;
+    use chrono::NaiveDateTime;
     use leptos::prelude::*;
     use sqlx::SqlitePool;
 
-    pub fn pool() -> Result<SqlitePool, ServerFnError> {
+    /// Permission token granting read and write access to every user's todos.
+    pub const TODOS_ADMIN: &str = "todos.admin";
+
+    pub fn pool() -> Result<SqlitePool, ServerFnError<TodoAppError>> {
         use_context::<SqlitePool>()
             .ok_or_else(|| ServerFnError::ServerError("Pool missing.".into()))
     }
 
-    pub fn auth() -> Result<AuthSession, ServerFnError> {
+    pub fn auth() -> Result<AuthSession, ServerFnError<TodoAppError>> {
         use_context::<AuthSession>().ok_or_else(|| {
             ServerFnError::ServerError("Auth session missing.".into())
         })
     }
 
-    #[derive(sqlx::FromRow, Clone)]
-    pub struct SqlTodo {
+    /// The logged in user. Guests are not allowed to change todos.
+    pub fn current_user() -> Result<User, ServerFnError<TodoAppError>> {
+        auth()?
+            .current_user
+            .ok_or(ServerFnError::WrappedServerError(TodoAppError::Forbidden))
+    }
+
+    /// A fresh in-memory database with every migration applied. A single
+    /// connection, as each would otherwise get a database of its own.
+    #[cfg(test)]
+    pub async fn test_pool() -> SqlitePool {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool
+    }
+
+    pub async fn is_todos_admin(user: &User, pool: &SqlitePool) -> bool {
+        user.has(TODOS_ADMIN, &Some(pool)).await
+    }
+
+    /// The role `user` has for a todo. Todos in a list are shared with the
+    /// list's members, everything else belongs to whoever created it. Either
+    /// way, only members of the todo's workspace get to see it.
+    pub async fn todo_role(
+        todo: &SqlTodo,
+        user: &User,
+        pool: &SqlitePool,
+    ) -> Result<Option<ListRole>, sqlx::Error> {
+        let Some(workspace_id) = todo.workspace_id else {
+            return Ok(None);
+        };
+        if workspace_role(workspace_id, user, pool).await?.is_none() {
+            return Ok(None);
+        }
+        match todo.list_id {
+            Some(list_id) => list_role(list_id, user, pool).await,
+            None if todo.user_id == user.id || is_todos_admin(user, pool).await => Ok(Some(ListRole::Owner)),
+            None => Ok(None),
+        }
+    }
+
+    /// Loads a todo, failing with `Forbidden` unless `user` has at least `role` for it.
+    pub async fn accessible_todo(
         id: u32,
-        user_id: i64,
-        title: String,
-        created_at: String,
-        completed: bool,
+        user: &User,
+        role: ListRole,
+        pool: &SqlitePool,
+    ) -> Result<SqlTodo, ServerFnError<TodoAppError>> {
+        let todo = sqlx::query_as::<_, SqlTodo>("SELECT * FROM todos WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .ok_or(TodoAppError::NotFound)?;
+
+        match todo_role(&todo, user, pool).await.map_err(TodoAppError::from)? {
+            Some(actual) if actual >= role => Ok(todo),
+            _ => Err(TodoAppError::Forbidden.into()),
+        }
+    }
+
+    /// Serialized as the before and after snapshots of the audit log.
+    #[derive(sqlx::FromRow, Clone, serde::Serialize)]
+    pub struct SqlTodo {
+        pub id: u32,
+        pub user_id: i64,
+        pub title: String,
+        pub notes: String,
+        pub created_at: String,
+        pub completed: bool,
+        pub due_at: Option<NaiveDateTime>,
+        pub remind_at: Option<NaiveDateTime>,
+        pub list_id: Option<i64>,
+        pub parent_id: Option<u32>,
+        pub recurrence: Option<String>,
+        pub deleted_at: Option<NaiveDateTime>,
+        pub priority: Priority,
+        pub position: Option<f64>,
+        pub workspace_id: Option<i64>,
+        pub assignee_id: Option<i64>,
+        pub status_id: Option<i64>,
     }
 
     impl SqlTodo {
@@ -46,97 +209,366 @@ pub mod ssr {
             Todo {
                 id: self.id,
                 user: User::get(self.user_id, pool).await,
+                assignee: match self.assignee_id {
+                    Some(id) => User::get(id, pool).await,
+                    None => None,
+                },
                 title: self.title,
+                notes: self.notes,
                 created_at: self.created_at,
                 completed: self.completed,
+                due_at: self.due_at,
+                remind_at: self.remind_at,
+                list_id: self.list_id,
+                status_id: self.status_id,
+                parent_id: self.parent_id,
+                recurrence: self.recurrence.and_then(|rule| rule.parse().ok()),
+                deleted_at: self.deleted_at,
+                priority: self.priority,
+                progress: subtask_progress(self.id, pool).await,
+                dependencies: dependency_counts(self.id, pool).await,
+                tags: tags_for_todo(self.id, pool).await,
             }
         }
     }
 }
 
+/// Reminders set without a time of day go off at the start of the working day.
+const REMINDER_DEFAULT_TIME: NaiveTime = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+
+/// One page of the todos in the current workspace that the current user can
+/// see matching `query`, their own and those in lists shared with them.
 #[server(GetTodos, "/api")]
-pub async fn get_todos() -> Result<Vec<Todo>, ServerFnError> {
-    use self::ssr::{pool, SqlTodo};
+pub async fn get_todos(
+    query: TodoQuery,
+) -> Result<TodoPage, ServerFnError<TodoAppError>> {
+    use self::query::ssr::{encode_cursor, SqlTodoRow};
+    use self::ssr::*;
+    use self::workspaces::ssr::current_workspace;
     use futures::future::join_all;
+    use sqlx::{QueryBuilder, Sqlite};
 
     let pool = pool()?;
 
-    Ok(join_all(
-        sqlx::query_as::<_, SqlTodo>("SELECT * FROM todos")
-            .fetch_all(&pool)
-            .await?
-            .iter()
-            .map(|todo: &SqlTodo| todo.clone().into_todo(&pool)),
-    )
-    .await)
+    // Guests don't own anything, so there is nothing for them to see.
+    let Some(user) = auth()?.current_user else {
+        return Ok(TodoPage::default());
+    };
+    let workspace_id = current_workspace(&user, &pool).await?;
+
+    let mut sql = QueryBuilder::<Sqlite>::new(format!(
+        "SELECT *, {} AS sort_value FROM todos WHERE (",
+        query.sort_sql()
+    ));
+    sql.push_bind(is_todos_admin(&user, &pool).await)
+        .push(" OR (list_id IS NULL AND user_id = ")
+        .push_bind(user.id)
+        .push(") OR list_id IN (SELECT list_id FROM list_members WHERE user_id = ")
+        .push_bind(user.id)
+        // Subtasks are listed under their parents.
+        .push(")) AND workspace_id = ")
+        .push_bind(workspace_id)
+        .push(" AND parent_id IS NULL AND deleted_at IS NULL");
+    query.push_filters(&mut sql);
+    query.push_page(&mut sql);
+
+    let mut rows = sql
+        .build_query_as::<SqlTodoRow>()
+        .fetch_all(&pool)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    let next_cursor = if rows.len() > query.page_size() as usize {
+        rows.truncate(query.page_size() as usize);
+        rows.last()
+            .map(|row| encode_cursor(row.todo.id, &row.cursor_value(query.sort)))
+    } else {
+        None
+    };
+
+    Ok(TodoPage {
+        todos: join_all(rows.into_iter().map(|row| row.todo.into_todo(&pool)))
+            .await,
+        next_cursor,
+    })
 }
 
 #[server(AddTodo, "/api")]
-pub async fn add_todo(title: String) -> Result<(), ServerFnError> {
+pub async fn add_todo(
+    title: String,
+    due_at: Option<NaiveDateTime>,
+    remind_at: Option<NaiveDateTime>,
+    list_id: Option<i64>,
+    #[server(default)] parent_id: Option<u32>,
+    #[server(default)] recurrence: Option<String>,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::audit::{ssr::record_todo, AuditAction};
+    use self::lists::ssr::accessible_list;
     use self::ssr::*;
+    use self::statuses::ssr::OPEN_STATUS;
+    use self::workspaces::ssr::current_workspace;
 
-    let user = get_user().await?;
+    let user = current_user()?;
     let pool = pool()?;
 
-    let id = match user {
-        Some(user) => user.id,
-        None => -1,
+    let recurrence = match recurrence.filter(|rule| !rule.trim().is_empty()) {
+        Some(rule) => Some(
+            rule.parse::<Recurrence>()
+                .map_err(|error| ServerFnError::ServerError(format!("Invalid recurrence: {error}")))?
+                .to_string(),
+        ),
+        None => None,
     };
 
-    // fake API delay
-    std::thread::sleep(std::time::Duration::from_millis(1250));
+    // Subtasks always live in their parent's list, and todos in their list's workspace.
+    let (list_id, workspace_id) = match parent_id {
+        Some(parent_id) => {
+            let parent = accessible_todo(parent_id, &user, ListRole::Editor, &pool).await?;
+            (parent.list_id, parent.workspace_id)
+        }
+        None => match list_id {
+            Some(list_id) => {
+                let list = accessible_list(list_id, &user, ListRole::Editor, &pool).await?;
+                (Some(list_id), list.workspace_id)
+            }
+            None => (None, Some(current_workspace(&user, &pool).await?)),
+        },
+    };
 
-    Ok(sqlx::query(
-        "INSERT INTO todos (title, user_id, completed) VALUES (?, ?, false)",
-    )
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let id = sqlx::query(&format!(
+        "INSERT INTO todos (title, user_id, completed, due_at, remind_at, list_id, parent_id, recurrence, workspace_id, status_id)
+        VALUES (?, ?, false, ?, ?, ?, ?, ?, ?, {OPEN_STATUS})"
+    ))
     .bind(title)
+    .bind(user.id)
+    .bind(due_at)
+    .bind(remind_at)
+    .bind(list_id)
+    .bind(parent_id)
+    .bind(recurrence)
+    .bind(workspace_id)
+    .bind(list_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?
+    .last_insert_rowid();
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoCreated, id as u32, None)
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(UpdateTodo, "/api")]
+pub async fn update_todo(
+    id: u32,
+    title: Option<String>,
+    completed: Option<bool>,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::audit::{ssr::*, AuditAction};
+    use self::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if title.as_ref().is_some_and(|title| title.trim().is_empty()) {
+        return Err(ServerFnError::ServerError(
+            "Title cannot be empty.".to_string(),
+        ));
+    }
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    if completed == Some(true) && !todo.completed {
+        self::dependencies::ssr::ensure_unblocked(id, &pool).await?;
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+
+    // Checking a todo off moves it to the first done status of its list, and
+    // unchecking it back to the first open one.
+    sqlx::query(
+        "UPDATE todos SET title = COALESCE(?1, title), completed = COALESCE(?2, completed),
+        status_id = CASE WHEN ?2 IS NULL OR ?2 = completed THEN status_id ELSE COALESCE(
+            (SELECT id FROM list_statuses WHERE list_id = todos.list_id AND is_done = ?2 ORDER BY position LIMIT 1),
+            status_id
+        ) END
+        WHERE id = ?3",
+    )
+    .bind(title.map(|title| title.trim().to_string()))
+    .bind(completed)
     .bind(id)
-    .execute(&pool)
+    .execute(&mut *tx)
     .await
-    .map(|_| ())?)
+    .map_err(TodoAppError::from)?;
+
+    if completed == Some(true) && !todo.completed {
+        self::recurrence::ssr::repeat_completed(&todo, user.id, &mut tx)
+            .await
+            .map_err(TodoAppError::from)?;
+    }
+
+    let updated = current_todo_snapshot(id, &mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    record(&mut *tx, Some(user.id), AuditAction::TodoUpdated, AuditTarget::todo(id), todo_snapshot(&todo), updated)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
 }
 
+/// Moves a todo and its subtasks to the trash, see [`trash`].
 // The struct name and path prefix arguments are optional.
 #[server]
-pub async fn delete_todo(id: u16) -> Result<(), ServerFnError> {
+pub async fn delete_todo(id: u32) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::audit::{ssr::record_todo, AuditAction};
     use self::ssr::*;
+    use self::trash::ssr::TODO_SUBTREE;
 
+    let user = current_user()?;
     let pool = pool()?;
 
-    Ok(sqlx::query("DELETE FROM todos WHERE id = $1")
-        .bind(id)
-        .execute(&pool)
+    let todo = accessible_todo(id, &user, ListRole::Owner, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query(&format!(
+        "{TODO_SUBTREE} UPDATE todos SET deleted_at = CURRENT_TIMESTAMP
+        WHERE id IN (SELECT id FROM tree) AND deleted_at IS NULL"
+    ))
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoDeleted, id, Some(&todo))
         .await
-        .map(|_| ())?)
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
 }
 
 #[component]
-pub fn Todos(is_guest: bool) -> impl IntoView {
+pub fn Todos(
+    is_guest: bool,
+    #[prop(optional)] list_id: Option<i64>,
+    #[prop(optional, into)] title: Option<String>,
+) -> impl IntoView {
+    mount_style("todos", include_str!("./todos.css"));
+    provide_todo_changes();
+    let changes = use_todo_changes();
     let add_todo = ServerMultiAction::<AddTodo>::new();
+    let update_todo = ServerAction::<UpdateTodo>::new();
     let delete_todo = ServerAction::<DeleteTodo>::new();
+    let restore_todo = ServerAction::<RestoreTodo>::new();
+    let set_priority = ServerAction::<SetPriority>::new();
+    let reorder_todo = ServerAction::<ReorderTodo>::new();
+    let update_notes = ServerAction::<UpdateNotes>::new();
+    let attach_tag = ServerAction::<AttachTag>::new();
+    let detach_tag = ServerAction::<DetachTag>::new();
+    let add_subtask = ServerAction::<AddTodo>::new();
+    let assign_todo = ServerAction::<AssignTodo>::new();
     let submissions = add_todo.submissions();
+    let expanded = RwSignal::new(HashSet::<u32>::new());
+    let dragging = RwSignal::new(None::<u32>);
+    let detail_todo = RwSignal::new(None::<Todo>);
+    let detail_open = RwSignal::new(false);
+    let filters = TodoFilters::new();
+    // Cursors of the pages before the current one, so "Previous" can go back.
+    let cursors = RwSignal::new(Vec::<String>::new());
+    let cursor = RwSignal::new(None::<String>);
+
+    // Any change to the filters starts over at the first page.
+    Effect::watch(
+        move || filters.query(list_id),
+        move |_, _, _| {
+            cursors.set(Vec::new());
+            cursor.set(None);
+        },
+        false,
+    );
+
+    let all_tags = Resource::new(|| (), |_| get_tags());
 
     // list of todos is loaded from the server in reaction to changes
     let todos = Resource::new(
-        move || (add_todo.version().get(), delete_todo.version().get()),
-        move |_| get_todos(),
+        move || {
+            (
+                (
+                    add_todo.version().get(),
+                    update_todo.version().get(),
+                    delete_todo.version().get(),
+                    restore_todo.version().get(),
+                    set_priority.version().get(),
+                    reorder_todo.version().get(),
+                    update_notes.version().get(),
+                    attach_tag.version().get(),
+                    detach_tag.version().get(),
+                    add_subtask.version().get(),
+                    assign_todo.version().get(),
+                ),
+                // Changes made anywhere else, pushed by the server.
+                changes.get(),
+                filters.query(list_id),
+                cursor.get(),
+            )
+        },
+        move |(.., query, cursor)| get_todos(TodoQuery { cursor, ..query }),
     );
 
+    let layout_title = title.unwrap_or_else(|| "Todos".to_owned());
     let title = RwSignal::new(String::from(""));
+    let due_date = RwSignal::new(None::<NaiveDate>);
+    let remind_date = RwSignal::new(None::<NaiveDate>);
+    let remind_time = RwSignal::new(None::<NaiveTime>);
+    let recurrence = RwSignal::new(String::new());
 
     view! {
-        <AppLayout is_guest=is_guest title="Todos".to_owned()>
+        <AppLayout is_guest=is_guest title=layout_title>
             <Flex vertical=true>
+                {list_id.map(|list_id| view! {
+                    <Flex justify=FlexJustify::End>
+                        <Link href=format!("/lists/{list_id}/board")>"Board view"</Link>
+                    </Flex>
+                })}
                 <Transition fallback=move || view! { <p>"Loading..."</p> }>
                     <Flex justify=FlexJustify::Center gap=FlexGap::Small>
                         <Text>"Add a Todo"</Text>
                         <Input value=title />
+                        <DatePicker value=due_date />
+                        <Text>"Remind me"</Text>
+                        <DatePicker value=remind_date />
+                        <TimePicker value=remind_time />
+                        <RecurrenceSelect value=recurrence />
                         <Button appearance=ButtonAppearance::Primary on_click=move |_| {
-                            add_todo.dispatch(AddTodo { title: title.get() });
+                            add_todo.dispatch(AddTodo {
+                                title: title.get(),
+                                due_at: due_date.get().map(|date| date.and_time(NaiveTime::MIN)),
+                                remind_at: remind_date.get().map(|date| {
+                                    date.and_time(remind_time.get().unwrap_or(REMINDER_DEFAULT_TIME))
+                                }),
+                                list_id,
+                                parent_id: None,
+                                recurrence: Some(recurrence.get()).filter(|rule| !rule.is_empty()),
+                            });
                             title.set(String::from(""));
+                            due_date.set(None);
+                            remind_date.set(None);
+                            remind_time.set(None);
+                            recurrence.set(String::new());
                         }>"Add"</Button>
                     </Flex>
                 </Transition>
+                <TodoFilterBar filters />
+                <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                    {move || {
+                        all_tags
+                            .get()
+                            .and_then(Result::ok)
+                            .filter(|tags| !tags.is_empty())
+                            .map(|tags| view! { <TagFilterBar tags selected=filters.tags /> })
+                    }}
+                </Transition>
                 <Transition fallback=move || view! { <p>"Loading..."</p> }>
                     <ErrorBoundary fallback=|errors| {
                         view! { <ErrorTemplate errors=errors/> }
@@ -145,8 +577,29 @@ pub fn Todos(is_guest: bool) -> impl IntoView {
                             <Table>
                                 <TableHeader>
                                     <TableRow>
+                                        <TableHeaderCell max_width=60.0>"Done"</TableHeaderCell>
                                         <TableHeaderCell resizable=true min_width=100.0>"Title"</TableHeaderCell>
+                                        <TableHeaderCell max_width=140.0>
+                                            <Button
+                                                appearance=ButtonAppearance::Subtle
+                                                icon=icondata::LuArrowUpDown
+                                                on_click=move |_| filters.sort_by(TodoSortKey::DueAt)
+                                            >
+                                                "Due"
+                                            </Button>
+                                        </TableHeaderCell>
+                                        <TableHeaderCell max_width=90.0>
+                                            <Button
+                                                appearance=ButtonAppearance::Subtle
+                                                icon=icondata::LuArrowUpDown
+                                                on_click=move |_| filters.sort_by(TodoSortKey::Priority)
+                                            >
+                                                "Priority"
+                                            </Button>
+                                        </TableHeaderCell>
+                                        <TableHeaderCell min_width=100.0>"Tags"</TableHeaderCell>
                                         <TableHeaderCell resizable=true max_width=100.0>"Creator"</TableHeaderCell>
+                                        <TableHeaderCell resizable=true max_width=160.0>"Assignee"</TableHeaderCell>
                                         <TableHeaderCell max_width=100.0>"Close"</TableHeaderCell>
                                     </TableRow>
                                 </TableHeader>
@@ -159,34 +612,164 @@ pub fn Todos(is_guest: bool) -> impl IntoView {
                                                 }
                                                     .into_any()
                                             },
-                                            Ok(todos) => {
+                                            Ok(TodoPage { todos, .. }) => {
+                                                let today = Local::now().date_naive();
+                                                let all_tags = all_tags.get().and_then(Result::ok).unwrap_or_default();
+                                                // Dragging only makes sense while the table shows the manual order.
+                                                let can_reorder = filters.sort.get() == TodoSortKey::Position.as_str()
+                                                    && filters.direction.get() == SortDirection::Asc;
+                                                let order = todos.iter().map(|todo| todo.id).collect::<Vec<_>>();
                                                 if todos.is_empty() {
                                                     view! { <p>"No tasks were found."</p> }.into_any()
                                                 } else {
                                                     todos
                                                         .into_iter()
                                                         .map(move |todo| {
+                                                            let id = todo.id;
+                                                            let completed = todo.completed;
+                                                            let dependencies = todo.dependencies;
+                                                            let title = RwSignal::new(todo.title.clone());
+                                                            let saved_title = todo.title.clone();
+                                                            let all_tags = all_tags.clone();
+                                                            let order = order.clone();
+                                                            let detail = todo.clone();
+                                                            let is_expanded = move || expanded.with(|expanded| expanded.contains(&id));
+                                                            let row_class = if todo.is_overdue(today) {
+                                                                "todo-overdue"
+                                                            } else if todo.is_due_today(today) {
+                                                                "todo-due-today"
+                                                            } else {
+                                                                ""
+                                                            };
                                                             view! {
-                                                                <TableRow>
+                                                                <TableRow
+                                                                    class=row_class
+                                                                    on:dragover=move |ev: DragEvent| {
+                                                                        if dragging.get_untracked().is_some() {
+                                                                            ev.prevent_default();
+                                                                        }
+                                                                    }
+                                                                    on:drop=move |ev: DragEvent| {
+                                                                        ev.prevent_default();
+                                                                        let neighbors = dragging
+                                                                            .get_untracked()
+                                                                            .and_then(|dragged| Some((dragged, drop_neighbors(&order, dragged, id)?)));
+                                                                        if let Some((dragged, (after, before))) = neighbors {
+                                                                            reorder_todo.dispatch(ReorderTodo { id: dragged, after, before });
+                                                                        }
+                                                                        dragging.set(None);
+                                                                    }
+                                                                >
+                                                                    <TableCell>
+                                                                        {can_reorder.then(|| view! {
+                                                                            <span
+                                                                                class="todo-drag-handle"
+                                                                                title="Drag to reorder"
+                                                                                draggable="true"
+                                                                                on:dragstart=move |_| dragging.set(Some(id))
+                                                                                on:dragend=move |_| dragging.set(None)
+                                                                            >
+                                                                                <Icon icon=icondata::LuGripVertical />
+                                                                            </span>
+                                                                        })}
+                                                                        <Checkbox checked=(
+                                                                            Signal::derive(move || completed),
+                                                                            SignalSetter::map(move |completed| {
+                                                                                set_completed(id, completed, dependencies, update_todo);
+                                                                            }),
+                                                                        ) />
+                                                                    </TableCell>
                                                                     <TableCell>
                                                                         <TableCellLayout truncate=true>
-                                                                            {todo.title}
+                                                                            <Button
+                                                                                appearance=ButtonAppearance::Subtle
+                                                                                icon=Signal::derive(move || {
+                                                                                    if is_expanded() {
+                                                                                        icondata::LuChevronDown
+                                                                                    } else {
+                                                                                        icondata::LuChevronRight
+                                                                                    }
+                                                                                })
+                                                                                on_click=move |_| expanded.update(|expanded| {
+                                                                                    if !expanded.remove(&id) {
+                                                                                        expanded.insert(id);
+                                                                                    }
+                                                                                })
+                                                                            />
+                                                                            <Input
+                                                                                value=title
+                                                                                on_blur=move |_| {
+                                                                                    let new_title = title.get_untracked();
+                                                                                    if new_title != saved_title {
+                                                                                        update_todo.dispatch(UpdateTodo {
+                                                                                            id,
+                                                                                            title: Some(new_title),
+                                                                                            completed: None,
+                                                                                        });
+                                                                                    }
+                                                                                }
+                                                                            />
+                                                                            {todo.progress.map(|progress| view! { <SubtaskBadge progress /> })}
+                                                                            <DependencyBadges dependencies=todo.dependencies />
+                                                                            <span title="Details">
+                                                                                <Button
+                                                                                    appearance=ButtonAppearance::Subtle
+                                                                                    icon=icondata::LuFileText
+                                                                                    on_click=move |_| {
+                                                                                        detail_todo.set(Some(detail.clone()));
+                                                                                        detail_open.set(true);
+                                                                                    }
+                                                                                />
+                                                                            </span>
                                                                         </TableCellLayout>
                                                                     </TableCell>
+                                                                    <TableCell>
+                                                                        <TableCellLayout truncate=true>
+                                                                            {todo.due_at.map(|due| due.format("%Y-%m-%d").to_string())}
+                                                                            {todo.remind_at.map(|remind| view! {
+                                                                                <span title=format!("Reminder at {}", remind.format("%Y-%m-%d %H:%M"))>
+                                                                                    <Icon icon=icondata::LuBell />
+                                                                                </span>
+                                                                            })}
+                                                                            {todo.recurrence.map(|recurrence| view! {
+                                                                                <span title=recurrence.describe()>
+                                                                                    <Icon icon=icondata::LuRepeat />
+                                                                                </span>
+                                                                            })}
+                                                                        </TableCellLayout>
+                                                                    </TableCell>
+                                                                    <TableCell>
+                                                                        <PriorityBadge todo_id=id priority=todo.priority set_priority />
+                                                                    </TableCell>
+                                                                    <TableCell>
+                                                                        <TagChips
+                                                                            todo_id=id
+                                                                            tags=todo.tags
+                                                                            all_tags
+                                                                            attach_tag
+                                                                            detach_tag
+                                                                        />
+                                                                    </TableCell>
                                                                     <TableCell>
                                                                         <TableCellLayout truncate=true>
                                                                             {todo.user.unwrap_or_default().username}
                                                                         </TableCellLayout>
                                                                     </TableCell>
+                                                                    <TableCell>
+                                                                        <AssigneePicker todo_id=id assignee=todo.assignee assign_todo />
+                                                                    </TableCell>
                                                                     <TableCell>
                                                                         <Button 
                                                                             on_click=move |_| {
-                                                                                delete_todo.dispatch(DeleteTodo { id: todo.id as u16 });
+                                                                                trash_with_undo(id, delete_todo, restore_todo);
                                                                             } 
                                                                             icon=icondata::AiCloseCircleOutlined
                                                                         />
                                                                     </TableCell>
                                                                 </TableRow>
+                                                                {move || is_expanded().then(|| view! {
+                                                                    <SubtaskRows parent_id=id add_subtask update_todo delete_todo restore_todo />
+                                                                })}
                                                             }
                                                         })
                                                         .collect_view()
@@ -201,18 +784,30 @@ pub fn Todos(is_guest: bool) -> impl IntoView {
                                             .into_iter()
                                             .filter(|submission| submission.pending().get())
                                             .map(|submission| {
+                                                let input = submission.input();
                                                 view! {
                                                     <TableRow>
+                                                        <TableCell>
+                                                            <Checkbox />
+                                                        </TableCell>
                                                         <TableCell>
                                                             <TableCellLayout truncate=true>
                                                                 {submission.input().get().map(|data| data.title)}
                                                             </TableCellLayout>
                                                         </TableCell>
+                                                        <TableCell>
+                                                            <TableCellLayout truncate=true>
+                                                                {input.get().and_then(|data| data.due_at).map(|due| due.format("%Y-%m-%d").to_string())}
+                                                            </TableCellLayout>
+                                                        </TableCell>
+                                                        <TableCell />
+                                                        <TableCell />
                                                         <TableCell>
                                                             <TableCellLayout truncate=true>
                                                                 <Spinner size=SpinnerSize::Small label="Pending..." />
                                                             </TableCellLayout>
                                                         </TableCell>
+                                                        <TableCell />
                                                         <TableCell>
                                                             <Button disabled=true icon=icondata::AiCloseCircleOutlined />
                                                         </TableCell>
@@ -225,9 +820,44 @@ pub fn Todos(is_guest: bool) -> impl IntoView {
                                 </TableBody>
                             </Table>
                     </Card>
+                    <Flex justify=FlexJustify::Center gap=FlexGap::Small>
+                        <Button
+                            disabled=Signal::derive(move || cursor.with(Option::is_none))
+                            on_click=move |_| {
+                                let previous = cursors.try_update(Vec::pop).flatten();
+                                cursor.set(previous);
+                            }
+                        >
+                            "Previous"
+                        </Button>
+                        <Button
+                            disabled=Signal::derive(move || {
+                                todos
+                                    .get()
+                                    .and_then(Result::ok)
+                                    .and_then(|page| page.next_cursor)
+                                    .is_none()
+                            })
+                            on_click=move |_| {
+                                let next = todos
+                                    .get_untracked()
+                                    .and_then(Result::ok)
+                                    .and_then(|page| page.next_cursor);
+                                if let Some(next) = next {
+                                    if let Some(current) = cursor.get_untracked() {
+                                        cursors.update(|cursors| cursors.push(current));
+                                    }
+                                    cursor.set(Some(next));
+                                }
+                            }
+                        >
+                            "Next"
+                        </Button>
+                    </Flex>
                 </ErrorBoundary>
             </Transition>
             </Flex>
+            <TodoDetail todo=detail_todo open=detail_open update_notes />
         </AppLayout>
     }
 }
@@ -0,0 +1,113 @@
+use leptos::prelude::*;
+use pulldown_cmark::{html::push_html, Options, Parser};
+use thaw::*;
+
+use crate::errors::TodoAppError;
+
+/// Longest notes a todo can carry, in characters.
+pub const MAX_NOTES_LEN: usize = 20_000;
+
+/// Renders Markdown notes to HTML that is safe to put in the page.
+///
+/// This runs on the server and in the browser alike, so both have to produce
+/// exactly the same output for hydration to line up.
+pub fn render_markdown(markdown: &str) -> String {
+    let mut html = String::new();
+    push_html(
+        &mut html,
+        Parser::new_ext(
+            markdown,
+            Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+        ),
+    );
+
+    ammonia::Builder::default()
+        // Task list items are the only inputs notes may contain.
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["checked"])
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "")
+        .clean(&html)
+        .to_string()
+}
+
+#[server(UpdateNotes, "/api")]
+pub async fn update_notes(id: u32, notes: String) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if notes.chars().count() > MAX_NOTES_LEN {
+        return Err(ServerFnError::ServerError(format!(
+            "Notes can be at most {MAX_NOTES_LEN} characters long."
+        )));
+    }
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todos SET notes = ? WHERE id = ?")
+        .bind(notes)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoUpdated, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Rendered notes with an editor behind the "Edit" button.
+#[component]
+pub fn TodoNotes(
+    todo_id: u32,
+    notes: String,
+    update_notes: ServerAction<UpdateNotes>,
+) -> impl IntoView {
+    let saved = RwSignal::new(notes.clone());
+    let text = RwSignal::new(notes);
+    let editing = RwSignal::new(false);
+
+    view! {
+        <Flex vertical=true>
+            <Show
+                when=move || editing.get()
+                fallback=move || view! {
+                    <Show
+                        when=move || !saved.with(String::is_empty)
+                        fallback=|| view! { <Text>"No notes yet."</Text> }
+                    >
+                        <div class="todo-notes" inner_html=move || render_markdown(&saved.get()) />
+                    </Show>
+                    <Flex>
+                        <Button icon=icondata::LuPencil on_click=move |_| editing.set(true)>"Edit"</Button>
+                    </Flex>
+                }
+            >
+                <Textarea value=text placeholder="Notes, in Markdown" resize=TextareaResize::Vertical />
+                <Flex>
+                    <Button
+                        appearance=ButtonAppearance::Primary
+                        on_click=move |_| {
+                            update_notes.dispatch(UpdateNotes { id: todo_id, notes: text.get_untracked() });
+                            saved.set(text.get_untracked());
+                            editing.set(false);
+                        }
+                    >
+                        "Save"
+                    </Button>
+                    <Button on_click=move |_| {
+                        text.set(saved.get_untracked());
+                        editing.set(false);
+                    }>
+                        "Cancel"
+                    </Button>
+                </Flex>
+            </Show>
+        </Flex>
+    }
+}
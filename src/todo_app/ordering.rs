@@ -0,0 +1,173 @@
+use leptos::prelude::*;
+
+use crate::errors::TodoAppError;
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use sqlx::{Sqlite, Transaction};
+
+    /// How far apart neighboring positions have to stay. Closer than this,
+    /// halving the gap again would soon run out of floating point precision.
+    pub const MIN_GAP: f64 = 1e-9;
+
+    /// Spreads the positions of the todos in `workspace_id` out to whole numbers
+    /// again, keeping their order. Only needed once repeated halving has used
+    /// up the gap between two of them.
+    pub async fn renumber_positions(
+        workspace_id: Option<i64>,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE todos SET position = ranked.rank
+            FROM (
+                SELECT id, ROW_NUMBER() OVER (ORDER BY COALESCE(position, id), id) AS rank
+                FROM todos WHERE workspace_id IS ?1
+            ) AS ranked
+            WHERE todos.id = ranked.id AND todos.workspace_id IS ?1",
+        )
+        .bind(workspace_id)
+        .execute(&mut **tx)
+        .await
+        .map(|_| ())
+    }
+
+    /// A position between the two neighbors, if there is still room for one
+    /// at least [`MIN_GAP`] away from both.
+    pub fn position_between(after: Option<f64>, before: Option<f64>) -> Option<f64> {
+        let position = match (after, before) {
+            (Some(after), Some(before)) => after + (before - after) / 2.0,
+            (Some(after), None) => after + 1.0,
+            (None, Some(before)) => before / 2.0,
+            (None, None) => return None,
+        };
+        let fits = after.is_none_or(|after| position - after >= MIN_GAP)
+            && before.is_none_or(|before| before - position >= MIN_GAP);
+        fits.then_some(position)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn places_between_neighbors() {
+            assert_eq!(position_between(Some(1.0), Some(2.0)), Some(1.5));
+            assert_eq!(position_between(Some(3.0), None), Some(4.0));
+            assert_eq!(position_between(None, Some(1.0)), Some(0.5));
+            assert_eq!(position_between(None, None), None);
+            assert_eq!(position_between(Some(2.0), Some(1.0)), None);
+        }
+
+        #[test]
+        fn runs_out_of_room_before_positions_collapse() {
+            let after = 1.0;
+            let mut before = 2.0;
+            let mut placed = 0;
+            while let Some(position) = position_between(Some(after), Some(before)) {
+                assert!(after < position && position < before);
+                // Printed the way the cursor text used to be, neighbors must still differ.
+                assert_ne!(format!("{position:.12}"), format!("{before:.12}"));
+                before = position;
+                placed += 1;
+            }
+            assert!(placed > 20, "only {placed} moves fit into the gap");
+            assert!(before - after >= MIN_GAP);
+        }
+
+        #[test]
+        fn runs_out_of_room_at_the_top() {
+            let mut before = 1.0;
+            while let Some(position) = position_between(None, Some(before)) {
+                assert!(position < before);
+                before = position;
+            }
+            assert!(before >= MIN_GAP);
+        }
+    }
+}
+
+/// Moves the todo `id` between `after` (the todo that should end up directly
+/// above it) and `before` (directly below). Either may be missing at the ends
+/// of the list, but not both.
+#[server(ReorderTodo, "/api")]
+pub async fn reorder_todo(
+    id: u32,
+    after: Option<u32>,
+    before: Option<u32>,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if after.is_none() && before.is_none() {
+        return Err(ServerFnError::ServerError(
+            "A todo has to be moved next to another one.".to_string(),
+        ));
+    }
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+    for neighbor in [after, before].into_iter().flatten() {
+        accessible_todo(neighbor, &user, ListRole::Editor, &pool).await?;
+    }
+
+    // Reorders in the same workspace would otherwise read positions the other
+    // is about to renumber, so the write lock is taken up front.
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await.map_err(TodoAppError::from)?;
+    let mut position = None;
+    for attempt in 0..2 {
+        if attempt > 0 {
+            renumber_positions(todo.workspace_id, &mut tx).await.map_err(TodoAppError::from)?;
+        }
+        let mut neighbors = [None, None];
+        for (neighbor, slot) in [after, before].into_iter().zip(&mut neighbors) {
+            if let Some(neighbor) = neighbor {
+                let position = sqlx::query_scalar::<_, f64>(
+                    "SELECT CAST(COALESCE(position, id) AS REAL) FROM todos WHERE id = ?",
+                )
+                .bind(neighbor)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(TodoAppError::from)?;
+                *slot = Some(position);
+            }
+        }
+        position = position_between(neighbors[0], neighbors[1]);
+        if position.is_some() {
+            break;
+        }
+    }
+
+    let position = position.ok_or_else(|| {
+        ServerFnError::ServerError("Those todos are not next to each other.".to_string())
+    })?;
+
+    sqlx::query("UPDATE todos SET position = ? WHERE id = ?")
+        .bind(position)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoUpdated, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Where `dragged` lands when dropped on `target` in the list `order`, as the
+/// `after` and `before` neighbors for [`reorder_todo`]. Dragging down puts it
+/// below the target, dragging up above it.
+pub fn drop_neighbors(order: &[u32], dragged: u32, target: u32) -> Option<(Option<u32>, Option<u32>)> {
+    let from = order.iter().position(|id| *id == dragged)?;
+    let to = order.iter().position(|id| *id == target)?;
+    if from == to {
+        None
+    } else if from < to {
+        Some((Some(target), order.get(to + 1).copied()))
+    } else {
+        Some((to.checked_sub(1).map(|index| order[index]), Some(target)))
+    }
+}
@@ -0,0 +1,124 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use crate::errors::TodoAppError;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[repr(i64)]
+pub enum Priority {
+    #[default]
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+    Urgent = 4,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] = [
+        Priority::None,
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Urgent,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        Priority::ALL
+            .into_iter()
+            .find(|priority| priority.as_str() == value)
+            .unwrap_or_default()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::None => "None",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+
+    pub fn badge_color(&self) -> BadgeColor {
+        match self {
+            Priority::None => BadgeColor::Subtle,
+            Priority::Low => BadgeColor::Informative,
+            Priority::Medium => BadgeColor::Warning,
+            Priority::High => BadgeColor::Severe,
+            Priority::Urgent => BadgeColor::Danger,
+        }
+    }
+}
+
+#[server(SetPriority, "/api")]
+pub async fn set_priority(
+    id: u32,
+    priority: Priority,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todos SET priority = ? WHERE id = ?")
+        .bind(priority)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoUpdated, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// The priority of a todo as a badge that opens a menu to change it.
+#[component]
+pub fn PriorityBadge(
+    todo_id: u32,
+    priority: Priority,
+    set_priority: ServerAction<SetPriority>,
+) -> impl IntoView {
+    view! {
+        <Menu on_select=move |value: String| {
+            set_priority.dispatch(SetPriority {
+                id: todo_id,
+                priority: Priority::parse(&value),
+            });
+        }>
+            <MenuTrigger slot>
+                <Badge
+                    appearance=if priority == Priority::None { BadgeAppearance::Outline } else { BadgeAppearance::Filled }
+                    color=priority.badge_color()
+                    attr:style="cursor: pointer"
+                >
+                    {priority.label()}
+                </Badge>
+            </MenuTrigger>
+            {Priority::ALL
+                .into_iter()
+                .map(|priority| view! {
+                    <MenuItem value=priority.as_str()>{priority.label()}</MenuItem>
+                })
+                .collect_view()}
+        </Menu>
+    }
+}
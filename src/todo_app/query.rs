@@ -0,0 +1,426 @@
+use chrono::NaiveDate;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thaw::*;
+
+use super::Todo;
+
+/// Todos shown per page unless the query asks for something else.
+pub const DEFAULT_PAGE_SIZE: u32 = 25;
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TodoSortKey {
+    /// The manual order set by dragging todos around.
+    #[default]
+    Position,
+    CreatedAt,
+    Title,
+    DueAt,
+    Priority,
+}
+
+impl TodoSortKey {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoSortKey::Position => "position",
+            TodoSortKey::CreatedAt => "created_at",
+            TodoSortKey::Title => "title",
+            TodoSortKey::DueAt => "due_at",
+            TodoSortKey::Priority => "priority",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "created_at" => TodoSortKey::CreatedAt,
+            "title" => TodoSortKey::Title,
+            "due_at" => TodoSortKey::DueAt,
+            "priority" => TodoSortKey::Priority,
+            _ => TodoSortKey::Position,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn toggled(self) -> Self {
+        match self {
+            SortDirection::Asc => SortDirection::Desc,
+            SortDirection::Desc => SortDirection::Asc,
+        }
+    }
+}
+
+/// Everything `get_todos` can filter, sort and page by. Unset fields don't filter.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TodoQuery {
+    /// Case-insensitive substring of the title.
+    pub text: Option<String>,
+    pub completed: Option<bool>,
+    /// Username of the creator.
+    pub creator: Option<String>,
+    pub list_id: Option<i64>,
+    /// Only todos carrying every one of these tags.
+    pub tag_ids: Vec<i64>,
+    pub created_after: Option<NaiveDate>,
+    pub created_before: Option<NaiveDate>,
+    pub due_after: Option<NaiveDate>,
+    pub due_before: Option<NaiveDate>,
+    pub sort: TodoSortKey,
+    pub direction: SortDirection,
+    /// The `next_cursor` of the previous page, `None` for the first page.
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoPage {
+    pub todos: Vec<Todo>,
+    /// Set when there are more todos after this page.
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{SortDirection, TodoQuery, TodoSortKey, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+    use crate::todo_app::ssr::SqlTodo;
+    use sqlx::{QueryBuilder, Sqlite};
+
+    /// A todo row together with the value it was sorted by, so the last row of a
+    /// page can be turned into the cursor for the next one.
+    #[derive(sqlx::FromRow)]
+    pub struct SqlTodoRow {
+        #[sqlx(flatten)]
+        pub todo: SqlTodo,
+        /// `NULL` when sorting by position, which is taken from `todo` instead.
+        pub sort_value: Option<String>,
+    }
+
+    impl SqlTodoRow {
+        pub fn cursor_value(&self, sort: TodoSortKey) -> CursorValue {
+            match sort {
+                TodoSortKey::Position => {
+                    CursorValue::Position(self.todo.position.unwrap_or(f64::from(self.todo.id)))
+                }
+                _ => CursorValue::Text(self.sort_value.clone().unwrap_or_default()),
+            }
+        }
+    }
+
+    /// The value a page ended on. Positions are compared as the REAL they are:
+    /// printed to any fixed precision, neighbors that repeated reordering moved
+    /// close together would come out the same.
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum CursorValue {
+        Position(f64),
+        Text(String),
+    }
+
+    impl CursorValue {
+        fn push_bind(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+            match self {
+                CursorValue::Position(position) => query.push_bind(*position),
+                CursorValue::Text(text) => query.push_bind(text.clone()),
+            };
+        }
+    }
+
+    impl TodoSortKey {
+        /// Never NULL, so keyset comparisons behave. Undated todos sort last.
+        fn sql(&self) -> &'static str {
+            match self {
+                TodoSortKey::Position => "COALESCE(position, id)",
+                TodoSortKey::CreatedAt => "COALESCE(created_at, '')",
+                TodoSortKey::Title => "lower(title)",
+                TodoSortKey::DueAt => "COALESCE(due_at, '9999-12-31 23:59:59')",
+                TodoSortKey::Priority => "CAST(priority AS TEXT)",
+            }
+        }
+    }
+
+    impl TodoQuery {
+        pub fn page_size(&self) -> u32 {
+            self.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+        }
+
+        /// Appends the `AND ...` filter clauses to a query that already has a `WHERE`.
+        pub fn push_filters(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+            if let Some(text) = self.text.as_ref().filter(|text| !text.trim().is_empty()) {
+                query
+                    .push(" AND title LIKE '%' || ")
+                    .push_bind(escape_like(text.trim()))
+                    .push(" || '%' ESCAPE '\\'");
+            }
+            if let Some(completed) = self.completed {
+                query.push(" AND COALESCE(completed, false) = ").push_bind(completed);
+            }
+            if let Some(creator) = self.creator.as_ref().filter(|creator| !creator.trim().is_empty()) {
+                query
+                    .push(" AND user_id IN (SELECT id FROM users WHERE username = ")
+                    .push_bind(creator.trim().to_string())
+                    .push(")");
+            }
+            if let Some(list_id) = self.list_id {
+                query.push(" AND list_id = ").push_bind(list_id);
+            }
+            if !self.tag_ids.is_empty() {
+                query.push(" AND id IN (SELECT todo_id FROM todo_tags WHERE tag_id IN (");
+                let mut separated = query.separated(", ");
+                for tag_id in &self.tag_ids {
+                    separated.push_bind(*tag_id);
+                }
+                query
+                    .push(") GROUP BY todo_id HAVING COUNT(DISTINCT tag_id) = ")
+                    .push_bind(self.tag_ids.len() as i64)
+                    .push(")");
+            }
+            if let Some(date) = self.created_after {
+                query.push(" AND created_at >= ").push_bind(date);
+            }
+            if let Some(date) = self.created_before {
+                query.push(" AND created_at < date(").push_bind(date).push(", '+1 day')");
+            }
+            if let Some(date) = self.due_after {
+                query.push(" AND due_at >= ").push_bind(date);
+            }
+            if let Some(date) = self.due_before {
+                query.push(" AND due_at < date(").push_bind(date).push(", '+1 day')");
+            }
+        }
+
+        /// Appends the keyset condition for `cursor`, then `ORDER BY` and `LIMIT`.
+        /// One extra row is fetched to find out whether another page follows.
+        pub fn push_page(&self, query: &mut QueryBuilder<'_, Sqlite>) {
+            let sort = self.sort.sql();
+            let (compare, order) = match self.direction {
+                SortDirection::Asc => (">", "ASC"),
+                SortDirection::Desc => ("<", "DESC"),
+            };
+
+            let cursor = self.cursor.as_deref().and_then(|cursor| decode_cursor(cursor, self.sort));
+            if let Some((id, value)) = cursor {
+                query.push(format!(" AND ({sort} {compare} "));
+                value.push_bind(query);
+                query.push(format!(" OR ({sort} = "));
+                value.push_bind(query);
+                query.push(format!(" AND id {compare} ")).push_bind(id).push("))");
+            }
+
+            query
+                .push(format!(" ORDER BY {sort} {order}, id {order} LIMIT "))
+                .push_bind(i64::from(self.page_size()) + 1);
+        }
+
+        /// Selected as `sort_value` into [`SqlTodoRow`].
+        pub fn sort_sql(&self) -> &'static str {
+            match self.sort {
+                TodoSortKey::Position => "NULL",
+                sort => sort.sql(),
+            }
+        }
+    }
+
+    /// Positions go into the cursor as their bits, so they come back exactly.
+    pub fn encode_cursor(id: u32, value: &CursorValue) -> String {
+        match value {
+            CursorValue::Position(position) => format!("{id}:{:016x}", position.to_bits()),
+            CursorValue::Text(text) => format!("{id}:{text}"),
+        }
+    }
+
+    fn decode_cursor(cursor: &str, sort: TodoSortKey) -> Option<(i64, CursorValue)> {
+        let (id, value) = cursor.split_once(':')?;
+        let value = match sort {
+            TodoSortKey::Position => {
+                CursorValue::Position(f64::from_bits(u64::from_str_radix(value, 16).ok()?))
+            }
+            _ => CursorValue::Text(value.to_string()),
+        };
+        Some((id.parse().ok()?, value))
+    }
+
+    fn escape_like(text: &str) -> String {
+        text.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::todo_app::ordering::ssr::position_between;
+
+        #[test]
+        fn cursors_round_trip() {
+            let value = CursorValue::Text("2024-01-31 10:00:00".to_string());
+            let cursor = encode_cursor(7, &value);
+            assert_eq!(cursor, "7:2024-01-31 10:00:00");
+            assert_eq!(decode_cursor(&cursor, TodoSortKey::DueAt), Some((7, value)));
+
+            let value = CursorValue::Position(1.0 / 3.0);
+            assert_eq!(decode_cursor(&encode_cursor(7, &value), TodoSortKey::Position), Some((7, value)));
+        }
+
+        #[test]
+        fn cursors_keep_close_positions_apart() {
+            let mut before = 2.0;
+            while let Some(position) = position_between(Some(1.0), Some(before)) {
+                let cursor = encode_cursor(1, &CursorValue::Position(position));
+                assert_ne!(cursor, encode_cursor(1, &CursorValue::Position(before)));
+                assert_eq!(
+                    decode_cursor(&cursor, TodoSortKey::Position),
+                    Some((1, CursorValue::Position(position)))
+                );
+                before = position;
+            }
+        }
+
+        #[test]
+        fn rejects_malformed_cursors() {
+            assert_eq!(decode_cursor("7", TodoSortKey::Title), None);
+            assert_eq!(decode_cursor("x:abc", TodoSortKey::Title), None);
+            assert_eq!(decode_cursor("7:1.5", TodoSortKey::Position), None);
+        }
+    }
+}
+
+/// The filter inputs above the todos table. Cursors are kept separately by the table.
+#[derive(Clone, Copy)]
+pub struct TodoFilters {
+    pub text: RwSignal<String>,
+    pub completed: RwSignal<String>,
+    pub creator: RwSignal<String>,
+    pub tags: RwSignal<HashSet<String>>,
+    pub created_after: RwSignal<Option<NaiveDate>>,
+    pub created_before: RwSignal<Option<NaiveDate>>,
+    pub due_after: RwSignal<Option<NaiveDate>>,
+    pub due_before: RwSignal<Option<NaiveDate>>,
+    pub sort: RwSignal<String>,
+    pub direction: RwSignal<SortDirection>,
+}
+
+impl TodoFilters {
+    pub fn new() -> Self {
+        Self {
+            text: RwSignal::new(String::new()),
+            completed: RwSignal::new(String::from("all")),
+            creator: RwSignal::new(String::new()),
+            tags: RwSignal::new(HashSet::new()),
+            created_after: RwSignal::new(None),
+            created_before: RwSignal::new(None),
+            due_after: RwSignal::new(None),
+            due_before: RwSignal::new(None),
+            sort: RwSignal::new(TodoSortKey::default().as_str().to_string()),
+            direction: RwSignal::new(SortDirection::default()),
+        }
+    }
+
+    /// Sorts by `key`, flipping the direction if the todos are already sorted by it.
+    pub fn sort_by(&self, key: TodoSortKey) {
+        if self.sort.get_untracked() == key.as_str() {
+            self.direction.update(|direction| *direction = direction.toggled());
+        } else {
+            self.sort.set(key.as_str().to_string());
+            self.direction.set(SortDirection::Asc);
+        }
+    }
+
+    pub fn query(&self, list_id: Option<i64>) -> TodoQuery {
+        let mut tag_ids = self
+            .tags
+            .with(|tags| tags.iter().filter_map(|tag| tag.parse().ok()).collect::<Vec<i64>>());
+        tag_ids.sort_unstable();
+
+        let text = self.text.get();
+        let creator = self.creator.get();
+
+        TodoQuery {
+            text: (!text.trim().is_empty()).then_some(text),
+            completed: match self.completed.get().as_str() {
+                "open" => Some(false),
+                "done" => Some(true),
+                _ => None,
+            },
+            creator: (!creator.trim().is_empty()).then_some(creator),
+            list_id,
+            tag_ids,
+            created_after: self.created_after.get(),
+            created_before: self.created_before.get(),
+            due_after: self.due_after.get(),
+            due_before: self.due_before.get(),
+            sort: TodoSortKey::parse(&self.sort.get()),
+            direction: self.direction.get(),
+            cursor: None,
+            limit: None,
+        }
+    }
+}
+
+impl Default for TodoFilters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[component]
+pub fn TodoFilterBar(filters: TodoFilters) -> impl IntoView {
+    let show_dates = RwSignal::new(false);
+
+    view! {
+        <Flex vertical=true>
+            <Flex justify=FlexJustify::Center align=FlexAlign::Center gap=FlexGap::Small>
+                <Input value=filters.text placeholder="Search titles" />
+                <Select value=filters.completed>
+                    <option value="all">"All"</option>
+                    <option value="open">"Open"</option>
+                    <option value="done">"Done"</option>
+                </Select>
+                <Input value=filters.creator placeholder="Creator" />
+                <Text>"Sort by"</Text>
+                <Select value=filters.sort>
+                    <option value="position">"Manual"</option>
+                    <option value="created_at">"Created"</option>
+                    <option value="title">"Title"</option>
+                    <option value="due_at">"Due date"</option>
+                    <option value="priority">"Priority"</option>
+                </Select>
+                <Button
+                    appearance=ButtonAppearance::Subtle
+                    icon=Signal::derive(move || match filters.direction.get() {
+                        SortDirection::Asc => icondata::LuArrowUp,
+                        SortDirection::Desc => icondata::LuArrowDown,
+                    })
+                    on_click=move |_| filters.direction.update(|direction| *direction = direction.toggled())
+                />
+                <Button
+                    appearance=ButtonAppearance::Subtle
+                    icon=icondata::LuCalendarRange
+                    on_click=move |_| show_dates.update(|show| *show = !*show)
+                >
+                    "Dates"
+                </Button>
+            </Flex>
+            <Show when=move || show_dates.get()>
+                <Flex justify=FlexJustify::Center align=FlexAlign::Center gap=FlexGap::Small>
+                    <Text>"Created"</Text>
+                    <DatePicker value=filters.created_after />
+                    <Text>"to"</Text>
+                    <DatePicker value=filters.created_before />
+                    <Text>"Due"</Text>
+                    <DatePicker value=filters.due_after />
+                    <Text>"to"</Text>
+                    <DatePicker value=filters.due_before />
+                </Flex>
+            </Show>
+        </Flex>
+    }
+}
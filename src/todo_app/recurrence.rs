@@ -0,0 +1,562 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::errors::TodoAppError;
+
+/// Gives up looking for a next occurrence after this many candidates, so rules
+/// like "every February 30th" end instead of spinning forever.
+const MAX_CANDIDATES: usize = 1000;
+
+/// Largest `INTERVAL` accepted, far beyond any sensible rule but small enough
+/// that stepping through [`MAX_CANDIDATES`] of them can't overflow.
+const MAX_INTERVAL: u32 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The subset of RFC 5545 `RRULE`s we understand: `FREQ`, `INTERVAL`, `BYDAY`
+/// (plain weekdays, daily and weekly only), `BYMONTHDAY` (monthly only) and `UNTIL`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub weekdays: Vec<Weekday>,
+    /// Negative days count from the end of the month, `-1` being the last day.
+    pub month_days: Vec<i32>,
+    pub until: Option<NaiveDate>,
+}
+
+impl Recurrence {
+    /// The first occurrence strictly after `date`, or `None` once the rule has ended.
+    pub fn next_after(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let next = match self.frequency {
+            Frequency::Daily => self.next_daily(date),
+            Frequency::Weekly => self.next_weekly(date),
+            Frequency::Monthly => self.next_monthly(date),
+            Frequency::Yearly => self.next_yearly(date),
+        }?;
+        match self.until {
+            Some(until) if next > until => None,
+            _ => Some(next),
+        }
+    }
+
+    fn next_daily(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let mut next = date;
+        for _ in 0..MAX_CANDIDATES {
+            next = next.checked_add_days(Days::new(self.interval.into()))?;
+            if self.weekdays.is_empty() || self.weekdays.contains(&next.weekday()) {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    fn next_weekly(&self, date: NaiveDate) -> Option<NaiveDate> {
+        if self.weekdays.is_empty() {
+            return date.checked_add_days(Days::new(7 * u64::from(self.interval)));
+        }
+
+        // Later days in the same week come first, then the first matching day
+        // `interval` weeks on.
+        let monday = date.week(Weekday::Mon).first_day();
+        let later_this_week = (date.weekday().num_days_from_monday() + 1..7)
+            .filter_map(|offset| monday.checked_add_days(Days::new(offset.into())))
+            .find(|day| self.weekdays.contains(&day.weekday()));
+        if later_this_week.is_some() {
+            return later_this_week;
+        }
+
+        let next_monday = monday.checked_add_days(Days::new(7 * u64::from(self.interval)))?;
+        (0..7)
+            .filter_map(|offset| next_monday.checked_add_days(Days::new(offset)))
+            .find(|day| self.weekdays.contains(&day.weekday()))
+    }
+
+    fn next_monthly(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let month_days = if self.month_days.is_empty() {
+            vec![date.day() as i32]
+        } else {
+            self.month_days.clone()
+        };
+
+        let mut month = date.with_day(1)?;
+        for step in 0..MAX_CANDIDATES {
+            if step > 0 {
+                month = month.checked_add_months(Months::new(self.interval))?;
+            }
+            let mut days = month_days
+                .iter()
+                .filter_map(|day| day_of_month(month, *day))
+                .filter(|day| *day > date)
+                .collect::<Vec<_>>();
+            days.sort_unstable();
+            if let Some(day) = days.first() {
+                return Some(*day);
+            }
+        }
+        None
+    }
+
+    fn next_yearly(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let interval = i32::try_from(self.interval).ok()?;
+        // Leap days only come around in leap years.
+        (1..MAX_CANDIDATES as i32)
+            .map_while(|step| date.year().checked_add(step.checked_mul(interval)?))
+            .find_map(|year| NaiveDate::from_ymd_opt(year, date.month(), date.day()))
+    }
+
+    /// A short human readable summary, e.g. "Every 2 weeks on Mon, Fri".
+    pub fn describe(&self) -> String {
+        let unit = match self.frequency {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+            Frequency::Yearly => "year",
+        };
+        let mut description = if self.interval == 1 {
+            format!("Every {unit}")
+        } else {
+            format!("Every {} {unit}s", self.interval)
+        };
+        if !self.weekdays.is_empty() {
+            let weekdays = self
+                .weekdays
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            description.push_str(&format!(" on {}", weekdays.join(", ")));
+        }
+        if !self.month_days.is_empty() {
+            let days = self
+                .month_days
+                .iter()
+                .map(|day| match day {
+                    -1 => "the last day".to_string(),
+                    day => format!("day {day}"),
+                })
+                .collect::<Vec<_>>();
+            description.push_str(&format!(" on {}", days.join(", ")));
+        }
+        if let Some(until) = self.until {
+            description.push_str(&format!(" until {}", until.format("%Y-%m-%d")));
+        }
+        description
+    }
+}
+
+/// Resolves `BYMONTHDAY` style day numbers, skipping days the month doesn't have.
+fn day_of_month(month: NaiveDate, day: i32) -> Option<NaiveDate> {
+    if day > 0 {
+        month.with_day(day as u32)
+    } else {
+        let last = month
+            .checked_add_months(Months::new(1))?
+            .pred_opt()?;
+        let from_end = u64::from(day.unsigned_abs() - 1);
+        last.checked_sub_days(Days::new(from_end))
+            .filter(|date| date.month() == month.month())
+    }
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_weekday(code: &str) -> Result<Weekday, String> {
+    Ok(match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return Err(format!("Unsupported BYDAY value {code}.")),
+    })
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut recurrence = Recurrence {
+            frequency: Frequency::Daily,
+            interval: 1,
+            weekdays: vec![],
+            month_days: vec![],
+            until: None,
+        };
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Expected NAME=VALUE, got {part}."))?;
+            let value = value.to_ascii_uppercase();
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("Unsupported FREQ {value}.")),
+                    })
+                }
+                "INTERVAL" => {
+                    recurrence.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| (1..=MAX_INTERVAL).contains(interval))
+                        .ok_or_else(|| {
+                            format!("INTERVAL must be a number from 1 to {MAX_INTERVAL}, got {value}.")
+                        })?
+                }
+                "BYDAY" => {
+                    recurrence.weekdays = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    recurrence.month_days = value
+                        .split(',')
+                        .map(|day| {
+                            day.parse::<i32>()
+                                .ok()
+                                .filter(|day| *day != 0 && (-31..=31).contains(day))
+                                .ok_or_else(|| format!("Unsupported BYMONTHDAY value {day}."))
+                        })
+                        .collect::<Result<_, _>>()?
+                }
+                "UNTIL" => {
+                    let date = value.get(..8).unwrap_or(&value);
+                    recurrence.until = Some(
+                        NaiveDate::parse_from_str(date, "%Y%m%d")
+                            .map_err(|_| format!("UNTIL must be a date like 20240131, got {value}."))?,
+                    )
+                }
+                key => return Err(format!("Unsupported rule part {key}.")),
+            }
+        }
+
+        recurrence.frequency = frequency.ok_or("FREQ is required.")?;
+        if !recurrence.weekdays.is_empty()
+            && !matches!(recurrence.frequency, Frequency::Daily | Frequency::Weekly)
+        {
+            return Err("BYDAY is only supported for daily and weekly rules.".into());
+        }
+        if !recurrence.month_days.is_empty() && recurrence.frequency != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported for monthly rules.".into());
+        }
+        recurrence.weekdays.sort_by_key(Weekday::num_days_from_monday);
+        recurrence.weekdays.dedup();
+
+        Ok(recurrence)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let weekdays = self
+                .weekdays
+                .iter()
+                .map(|weekday| weekday_code(*weekday))
+                .collect::<Vec<_>>();
+            write!(f, ";BYDAY={}", weekdays.join(","))?;
+        }
+        if !self.month_days.is_empty() {
+            let days = self
+                .month_days
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::Recurrence;
+    use crate::todo_app::audit::{ssr::*, AuditAction};
+    use crate::todo_app::ssr::SqlTodo;
+    use crate::todo_app::statuses::ssr::OPEN_STATUS;
+    use chrono::Local;
+    use sqlx::{Sqlite, Transaction};
+
+    /// Creates the occurrence after `todo`, which has just been completed.
+    /// Overdue todos skip ahead to the first occurrence from today on.
+    pub async fn schedule_next(
+        todo: &SqlTodo,
+        recurrence: &Recurrence,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<Option<u32>, sqlx::Error> {
+        let today = Local::now().date_naive();
+        let due = todo.due_at.unwrap_or_else(|| today.and_time(Default::default()));
+
+        let mut next = recurrence.next_after(due.date());
+        while let Some(date) = next.filter(|date| *date < today) {
+            next = recurrence.next_after(date);
+        }
+        let Some(next) = next else {
+            return Ok(None);
+        };
+
+        let due_at = next.and_time(due.time());
+        // Reminders keep their distance to the due date.
+        let remind_at = todo.remind_at.map(|remind| due_at + (remind - due));
+
+        let id = sqlx::query(&format!(
+            "INSERT INTO todos (title, user_id, completed, due_at, remind_at, list_id, parent_id, recurrence, priority, workspace_id, status_id)
+            VALUES (?, ?, false, ?, ?, ?, ?, ?, ?, ?, {OPEN_STATUS})"
+        ))
+        .bind(&todo.title)
+        .bind(todo.user_id)
+        .bind(due_at)
+        .bind(remind_at)
+        .bind(todo.list_id)
+        .bind(todo.parent_id)
+        .bind(recurrence.to_string())
+        .bind(todo.priority)
+        .bind(todo.workspace_id)
+        .bind(todo.list_id)
+        .execute(&mut **tx)
+        .await?
+        .last_insert_rowid();
+
+        sqlx::query("INSERT INTO todo_tags (todo_id, tag_id) SELECT ?, tag_id FROM todo_tags WHERE todo_id = ?")
+            .bind(id)
+            .bind(todo.id)
+            .execute(&mut **tx)
+            .await?;
+
+        // Only the newest occurrence repeats, so completing this one again
+        // doesn't schedule a second copy.
+        sqlx::query("UPDATE todos SET recurrence = NULL WHERE id = ?")
+            .bind(todo.id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(Some(id as u32))
+    }
+
+    /// Completing an occurrence of a recurring todo schedules the next one.
+    /// Call this once `todo` has just been completed, with how it looked before.
+    pub async fn repeat_completed(
+        todo: &SqlTodo,
+        actor_id: i64,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), sqlx::Error> {
+        let Some(recurrence) = todo
+            .recurrence
+            .as_ref()
+            .and_then(|rule| rule.parse::<Recurrence>().ok())
+        else {
+            return Ok(());
+        };
+        if let Some(next) = schedule_next(todo, &recurrence, tx).await? {
+            let created = current_todo_snapshot(next, &mut **tx).await?;
+            record(&mut **tx, Some(actor_id), AuditAction::TodoCreated, AuditTarget::todo(next), None, created).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Sets or clears how the todo `id` repeats. `rule` is an `RRULE` like `FREQ=WEEKLY;BYDAY=MO`.
+#[server(SetRecurrence, "/api")]
+pub async fn set_recurrence(
+    id: u32,
+    rule: Option<String>,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let rule = match rule.filter(|rule| !rule.trim().is_empty()) {
+        Some(rule) => Some(
+            rule.parse::<Recurrence>()
+                .map_err(|error| ServerFnError::ServerError(format!("Invalid recurrence: {error}")))?
+                .to_string(),
+        ),
+        None => None,
+    };
+
+    let todo = accessible_todo(id, &user, ListRole::Editor, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todos SET recurrence = ? WHERE id = ?")
+        .bind(rule)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoUpdated, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Picks one of the common schedules. Anything else goes through `set_recurrence`.
+#[component]
+pub fn RecurrenceSelect(value: RwSignal<String>) -> impl IntoView {
+    view! {
+        <thaw::Select value>
+            <option value="">"Does not repeat"</option>
+            <option value="FREQ=DAILY">"Daily"</option>
+            <option value="FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR">"Every weekday"</option>
+            <option value="FREQ=WEEKLY">"Weekly"</option>
+            <option value="FREQ=WEEKLY;INTERVAL=2">"Every 2 weeks"</option>
+            <option value="FREQ=MONTHLY">"Monthly"</option>
+            <option value="FREQ=MONTHLY;BYMONTHDAY=-1">"Last day of the month"</option>
+            <option value="FREQ=YEARLY">"Yearly"</option>
+        </thaw::Select>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn rule(rule: &str) -> Recurrence {
+        rule.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trips_rules() {
+        for text in [
+            "FREQ=DAILY",
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR",
+            "FREQ=MONTHLY;BYMONTHDAY=1,-1",
+            "FREQ=YEARLY;UNTIL=20301231",
+        ] {
+            assert_eq!(rule(text).to_string(), text);
+        }
+        assert_eq!(rule("RRULE:freq=weekly;byday=fr,mo,fr").to_string(), "FREQ=WEEKLY;BYDAY=MO,FR");
+    }
+
+    #[test]
+    fn rejects_unsupported_rules() {
+        for text in [
+            "",
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;INTERVAL=1001",
+            "FREQ=YEARLY;INTERVAL=4294967295",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=MONTHLY;BYDAY=MO",
+            "FREQ=WEEKLY;BYMONTHDAY=1",
+            "FREQ=DAILY;COUNT=3",
+        ] {
+            assert!(text.parse::<Recurrence>().is_err(), "{text} was accepted");
+        }
+    }
+
+    #[test]
+    fn steps_daily() {
+        assert_eq!(rule("FREQ=DAILY;INTERVAL=3").next_after(date(2024, 2, 28)), Some(date(2024, 3, 2)));
+        // Weekdays only: Friday is followed by Monday.
+        let weekdays = rule("FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR");
+        assert_eq!(weekdays.next_after(date(2024, 1, 5)), Some(date(2024, 1, 8)));
+    }
+
+    #[test]
+    fn rolls_weekly_byday_over_into_the_next_period() {
+        let rule = rule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR");
+        // Monday 2024-01-01, then Friday the same week.
+        assert_eq!(rule.next_after(date(2024, 1, 1)), Some(date(2024, 1, 5)));
+        // From Friday, on to Monday two weeks later.
+        assert_eq!(rule.next_after(date(2024, 1, 5)), Some(date(2024, 1, 15)));
+        // From Sunday, the week it belongs to is over as well.
+        assert_eq!(rule.next_after(date(2024, 1, 7)), Some(date(2024, 1, 15)));
+    }
+
+    #[test]
+    fn skips_months_without_the_day() {
+        let monthly = rule("FREQ=MONTHLY");
+        assert_eq!(monthly.next_after(date(2024, 1, 31)), Some(date(2024, 3, 31)));
+        assert_eq!(monthly.next_after(date(2024, 3, 31)), Some(date(2024, 5, 31)));
+
+        let last_day = rule("FREQ=MONTHLY;BYMONTHDAY=-1");
+        assert_eq!(last_day.next_after(date(2024, 1, 31)), Some(date(2024, 2, 29)));
+        assert_eq!(last_day.next_after(date(2023, 1, 31)), Some(date(2023, 2, 28)));
+    }
+
+    #[test]
+    fn keeps_leap_days_in_leap_years() {
+        let yearly = rule("FREQ=YEARLY");
+        assert_eq!(yearly.next_after(date(2024, 2, 29)), Some(date(2028, 2, 29)));
+        assert_eq!(yearly.next_after(date(2024, 3, 1)), Some(date(2025, 3, 1)));
+        // Every third year only lands on a leap year every twelve years.
+        assert_eq!(rule("FREQ=YEARLY;INTERVAL=3").next_after(date(2024, 2, 29)), Some(date(2036, 2, 29)));
+    }
+
+    #[test]
+    fn large_intervals_end_instead_of_overflowing() {
+        assert_eq!(rule("FREQ=YEARLY;INTERVAL=1000").next_after(date(2024, 6, 1)), Some(date(3024, 6, 1)));
+        assert_eq!(
+            rule("FREQ=MONTHLY;INTERVAL=1000;BYMONTHDAY=31").next_after(date(2024, 1, 31)),
+            Some(date(2107, 5, 31))
+        );
+        assert_eq!(rule("FREQ=DAILY;INTERVAL=1000").next_after(NaiveDate::MAX), None);
+
+        // Rules stored before INTERVAL was bounded.
+        for interval in [i32::MAX as u32, u32::MAX] {
+            let rule = Recurrence {
+                interval,
+                ..rule("FREQ=YEARLY")
+            };
+            assert_eq!(rule.next_after(date(2024, 2, 29)), None);
+        }
+    }
+
+    #[test]
+    fn stops_at_until() {
+        let rule = rule("FREQ=DAILY;UNTIL=20240102");
+        assert_eq!(rule.next_after(date(2024, 1, 1)), Some(date(2024, 1, 2)));
+        assert_eq!(rule.next_after(date(2024, 1, 2)), None);
+    }
+}
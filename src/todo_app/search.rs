@@ -0,0 +1,253 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use super::Todo;
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+/// Most results a single search returns.
+pub const SEARCH_LIMIT: i64 = 50;
+
+/// A run of snippet text, `highlighted` where it matched the search terms.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoSearchResult {
+    pub todo: Todo,
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::SnippetPart;
+    use crate::todo_app::ssr::SqlTodo;
+
+    /// Marks where matches start and end in a snippet, which keeps it free of
+    /// HTML. Todo text can contain any character, so the markers carry a random
+    /// nonce that nobody can put into a todo ahead of a search.
+    pub struct SnippetMarkers {
+        pub start: String,
+        pub end: String,
+    }
+
+    impl SnippetMarkers {
+        pub fn random() -> Self {
+            let nonce = rand::random::<u64>();
+            Self {
+                start: format!("\u{2}{nonce:016x}\u{2}"),
+                end: format!("\u{3}{nonce:016x}\u{3}"),
+            }
+        }
+    }
+
+    #[derive(sqlx::FromRow)]
+    pub struct SqlSearchRow {
+        #[sqlx(flatten)]
+        pub todo: SqlTodo,
+        pub snippet: String,
+    }
+
+    /// Turns free text into an FTS5 query: every word has to match, each as a
+    /// prefix. Every word is quoted, with its own quotes doubled, so whatever
+    /// the input contains FTS5 only ever sees strings.
+    pub fn match_expression(text: &str) -> Option<String> {
+        let terms = text
+            .split_whitespace()
+            .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+            .collect::<Vec<_>>();
+        (!terms.is_empty()).then(|| terms.join(" "))
+    }
+
+    pub fn split_snippet(snippet: &str, markers: &SnippetMarkers) -> Vec<SnippetPart> {
+        let mut parts = Vec::new();
+        let mut rest = snippet;
+        let mut highlighted = false;
+        loop {
+            let marker = if highlighted { &markers.end } else { &markers.start };
+            let (text, next) = match rest.split_once(marker.as_str()) {
+                Some((text, next)) => (text, Some(next)),
+                None => (rest, None),
+            };
+            if !text.is_empty() {
+                parts.push(SnippetPart { text: text.to_string(), highlighted });
+            }
+            match next {
+                Some(next) => {
+                    rest = next;
+                    highlighted = !highlighted;
+                }
+                None => return parts,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn quotes_every_word() {
+            assert_eq!(match_expression("  "), None);
+            assert_eq!(match_expression("buy milk").as_deref(), Some("\"buy\"* \"milk\"*"));
+            assert_eq!(
+                match_expression("a\"b OR NEAR(c)").as_deref(),
+                Some("\"a\"\"b\"* \"OR\"* \"NEAR(c)\"*")
+            );
+        }
+
+        #[test]
+        fn splits_on_the_markers_only() {
+            let markers = SnippetMarkers::random();
+            let snippet = format!("\u{2}x\u{3} {}milk{} and {}more", markers.start, markers.end, markers.start);
+            assert_eq!(
+                split_snippet(&snippet, &markers),
+                [
+                    SnippetPart { text: "\u{2}x\u{3} ".to_string(), highlighted: false },
+                    SnippetPart { text: "milk".to_string(), highlighted: true },
+                    SnippetPart { text: " and ".to_string(), highlighted: false },
+                    SnippetPart { text: "more".to_string(), highlighted: true },
+                ]
+            );
+        }
+    }
+}
+
+/// The current user's todos matching `text`, best match first.
+#[server(SearchTodos, "/api")]
+pub async fn search_todos(
+    text: String,
+) -> Result<Vec<TodoSearchResult>, ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+    use futures::future::join_all;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(vec![]);
+    };
+    let Some(expression) = match_expression(&text) else {
+        return Ok(vec![]);
+    };
+
+    let markers = SnippetMarkers::random();
+    let rows = sqlx::query_as::<_, SqlSearchRow>(
+        "SELECT todos.*, snippet(todos_fts, -1, ?, ?, '…', 16) AS snippet
+        FROM todos_fts JOIN todos ON todos.id = todos_fts.rowid
+        WHERE todos_fts MATCH ? AND todos.deleted_at IS NULL AND (?
+            OR (todos.list_id IS NULL AND todos.user_id = ?)
+            OR todos.list_id IN (SELECT list_id FROM list_members WHERE user_id = ?))
+        AND todos.workspace_id = ?
+        ORDER BY rank LIMIT ?",
+    )
+    .bind(&markers.start)
+    .bind(&markers.end)
+    .bind(expression)
+    .bind(is_todos_admin(&user, &pool).await)
+    .bind(user.id)
+    .bind(user.id)
+    .bind(current_workspace(&user, &pool).await?)
+    .bind(SEARCH_LIMIT)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    let (pool, markers) = (&pool, &markers);
+    Ok(join_all(rows.into_iter().map(|row| async move {
+        TodoSearchResult {
+            snippet: split_snippet(&row.snippet, markers),
+            todo: row.todo.into_todo(pool).await,
+        }
+    }))
+    .await)
+}
+
+#[component]
+pub fn SearchResults(#[prop(into)] text: Signal<String>) -> impl IntoView {
+    let results = Resource::new(move || text.get(), search_todos);
+
+    view! {
+        <Transition fallback=move || view! { <p>"Searching..."</p> }>
+            <ErrorBoundary fallback=|errors| {
+                view! { <ErrorTemplate errors=errors/> }
+            }>
+                <Card>
+                    <Table>
+                        <TableHeader>
+                            <TableRow>
+                                <TableHeaderCell resizable=true min_width=200.0>"Todo"</TableHeaderCell>
+                                <TableHeaderCell max_width=80.0>"Status"</TableHeaderCell>
+                                <TableHeaderCell max_width=120.0>"Due"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"Creator"</TableHeaderCell>
+                                <TableHeaderCell max_width=80.0>"List"</TableHeaderCell>
+                            </TableRow>
+                        </TableHeader>
+                        <TableBody>
+                            {move || {
+                                results.get().map(|results| match results {
+                                    Err(e) => {
+                                        view! {
+                                            <pre class="error">"Server Error: " {e.to_string()}</pre>
+                                        }
+                                            .into_any()
+                                    }
+                                    Ok(results) => {
+                                        if results.is_empty() {
+                                            view! { <p>"No matching todos."</p> }.into_any()
+                                        } else {
+                                            results
+                                                .into_iter()
+                                                .map(|TodoSearchResult { todo, snippet }| {
+                                                    view! {
+                                                        <TableRow>
+                                                            <TableCell>
+                                                                <TableCellLayout truncate=true>
+                                                                    {snippet
+                                                                        .into_iter()
+                                                                        .map(|part| {
+                                                                            if part.highlighted {
+                                                                                view! { <mark>{part.text}</mark> }.into_any()
+                                                                            } else {
+                                                                                part.text.into_any()
+                                                                            }
+                                                                        })
+                                                                        .collect_view()}
+                                                                </TableCellLayout>
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                {if todo.completed { "Done" } else { "Open" }}
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                {todo.due_at.map(|due| due.format("%Y-%m-%d").to_string())}
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <TableCellLayout truncate=true>
+                                                                    {todo.user.unwrap_or_default().username}
+                                                                </TableCellLayout>
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                {todo.list_id.map(|list_id| view! {
+                                                                    <a href=format!("/lists/{list_id}")>"Open"</a>
+                                                                })}
+                                                            </TableCell>
+                                                        </TableRow>
+                                                    }
+                                                })
+                                                .collect_view()
+                                                .into_any()
+                                        }
+                                    }
+                                })
+                            }}
+                        </TableBody>
+                    </Table>
+                </Card>
+            </ErrorBoundary>
+        </Transition>
+    }
+}
@@ -0,0 +1,335 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::TodoAppError;
+
+/// A column of a list's board. Todos in a status that `is_done` count as completed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoStatus {
+    pub id: i64,
+    pub list_id: i64,
+    pub name: String,
+    pub is_done: bool,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::TodoStatus;
+    use crate::auth::User;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::members::{ssr::require_list_role, ListRole};
+    use leptos::prelude::*;
+    use sqlx::{Sqlite, SqlitePool, Transaction};
+
+    /// The statuses every new list starts out with.
+    pub const DEFAULT_STATUSES: [(&str, bool); 4] = [
+        ("Backlog", false),
+        ("In Progress", false),
+        ("Review", false),
+        ("Done", true),
+    ];
+
+    /// The first open status of a list, for todos added to it. Binds the list's id.
+    pub const OPEN_STATUS: &str =
+        "(SELECT id FROM list_statuses WHERE list_id = ? AND NOT is_done ORDER BY position LIMIT 1)";
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlTodoStatus {
+        pub id: i64,
+        pub list_id: i64,
+        pub name: String,
+        pub position: i64,
+        pub is_done: bool,
+    }
+
+    impl SqlTodoStatus {
+        pub fn into_status(self) -> TodoStatus {
+            TodoStatus {
+                id: self.id,
+                list_id: self.list_id,
+                name: self.name,
+                is_done: self.is_done,
+            }
+        }
+    }
+
+    pub async fn create_default_statuses(
+        list_id: i64,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<(), sqlx::Error> {
+        for (position, (name, is_done)) in DEFAULT_STATUSES.into_iter().enumerate() {
+            sqlx::query("INSERT INTO list_statuses (list_id, name, position, is_done) VALUES (?, ?, ?, ?)")
+                .bind(list_id)
+                .bind(name)
+                .bind(position as i64)
+                .bind(is_done)
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_statuses(list_id: i64, pool: &SqlitePool) -> Result<Vec<SqlTodoStatus>, sqlx::Error> {
+        sqlx::query_as::<_, SqlTodoStatus>("SELECT * FROM list_statuses WHERE list_id = ? ORDER BY position")
+            .bind(list_id)
+            .fetch_all(pool)
+            .await
+    }
+
+    /// Loads a status, failing with `Forbidden` unless `user` has at least `role` in its list.
+    pub async fn accessible_status(
+        id: i64,
+        user: &User,
+        role: ListRole,
+        pool: &SqlitePool,
+    ) -> Result<SqlTodoStatus, ServerFnError<TodoAppError>> {
+        let status = sqlx::query_as::<_, SqlTodoStatus>("SELECT * FROM list_statuses WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .ok_or(TodoAppError::NotFound)?;
+
+        require_list_role(status.list_id, user, role, pool).await?;
+        Ok(status)
+    }
+
+    pub fn validate_name(name: &str) -> Result<(), ServerFnError<TodoAppError>> {
+        if name.trim().is_empty() {
+            return Err(ServerFnError::ServerError(
+                "Status name cannot be empty.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reports a name that is already taken in the list, which the
+    /// `UNIQUE (list_id, name)` constraint catches, and passes anything else on.
+    pub fn name_error(error: sqlx::Error, name: &str) -> ServerFnError<TodoAppError> {
+        match &error {
+            sqlx::Error::Database(database) if database.is_unique_violation() => {
+                ServerFnError::ServerError(format!("There already is a status called \"{name}\"."))
+            }
+            _ => TodoAppError::from(error).into(),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::todo_app::ssr::test_pool;
+
+        async fn add_status(list_id: i64, name: &str, pool: &SqlitePool) -> Result<(), ServerFnError<TodoAppError>> {
+            sqlx::query("INSERT INTO list_statuses (list_id, name, position) VALUES (?, ?, 0)")
+                .bind(list_id)
+                .bind(name)
+                .execute(pool)
+                .await
+                .map(|_| ())
+                .map_err(|error| name_error(error, name))
+        }
+
+        #[tokio::test]
+        async fn only_reports_taken_names_as_such() {
+            let pool = test_pool().await;
+            let list_id: i64 = sqlx::query_scalar("INSERT INTO lists (user_id, name) VALUES (1, 'List') RETURNING id")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+
+            assert!(add_status(list_id, "Waiting", &pool).await.is_ok());
+            assert!(matches!(
+                add_status(list_id, "Waiting", &pool).await,
+                Err(ServerFnError::ServerError(message)) if message == "There already is a status called \"Waiting\"."
+            ));
+            // There is no such list, which is no reason to blame the name.
+            assert!(matches!(
+                add_status(list_id + 1, "Waiting", &pool).await,
+                Err(ServerFnError::WrappedServerError(TodoAppError::InternalServerError))
+            ));
+        }
+    }
+}
+
+/// Adds a status to the right end of a list's board.
+#[server(AddStatus, "/api")]
+pub async fn add_status(
+    list_id: i64,
+    name: String,
+    #[server(default)] is_done: bool,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::{name_error, validate_name};
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::members::ssr::require_list_role;
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    validate_name(&name)?;
+    require_list_role(list_id, &user, ListRole::Owner, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query(
+        "INSERT INTO list_statuses (list_id, name, position, is_done)
+        VALUES (?1, ?2, (SELECT COALESCE(MAX(position) + 1, 0) FROM list_statuses WHERE list_id = ?1), ?3)",
+    )
+    .bind(list_id)
+    .bind(name.trim())
+    .bind(is_done)
+    .execute(&mut *tx)
+    .await
+    .map_err(|error| name_error(error, name.trim()))?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::StatusCreated,
+        AuditTarget::list(list_id),
+        None,
+        Some(serde_json::json!({ "status": name.trim(), "is_done": is_done })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(RenameStatus, "/api")]
+pub async fn rename_status(id: i64, name: String) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    validate_name(&name)?;
+    let status = accessible_status(id, &user, ListRole::Owner, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE list_statuses SET name = ? WHERE id = ?")
+        .bind(name.trim())
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|error| name_error(error, name.trim()))?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::StatusUpdated,
+        AuditTarget::list(status.list_id),
+        Some(serde_json::json!({ "status": status.name })),
+        Some(serde_json::json!({ "status": name.trim() })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Swaps a status with its neighbor on the board, to the left or the right.
+#[server(MoveStatus, "/api")]
+pub async fn move_status(id: i64, left: bool) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let status = accessible_status(id, &user, ListRole::Owner, &pool).await?;
+
+    let neighbor = sqlx::query_as::<_, SqlTodoStatus>(if left {
+        "SELECT * FROM list_statuses WHERE list_id = ? AND position < ? ORDER BY position DESC LIMIT 1"
+    } else {
+        "SELECT * FROM list_statuses WHERE list_id = ? AND position > ? ORDER BY position LIMIT 1"
+    })
+    .bind(status.list_id)
+    .bind(status.position)
+    .fetch_optional(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    // Already at that end of the board.
+    let Some(neighbor) = neighbor else {
+        return Ok(());
+    };
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    for (id, position) in [(status.id, neighbor.position), (neighbor.id, status.position)] {
+        sqlx::query("UPDATE list_statuses SET position = ? WHERE id = ?")
+            .bind(position)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(TodoAppError::from)?;
+    }
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::StatusUpdated,
+        AuditTarget::list(status.list_id),
+        Some(serde_json::json!({ "status": status.name, "position": status.position })),
+        Some(serde_json::json!({ "status": status.name, "position": neighbor.position })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Deletes a status, moving its todos to the first other status that is
+/// just as done. Every list keeps at least one open and one done status.
+#[server(DeleteStatus, "/api")]
+pub async fn delete_status(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let status = accessible_status(id, &user, ListRole::Owner, &pool).await?;
+
+    let replacement = sqlx::query_scalar::<_, i64>(
+        "SELECT id FROM list_statuses WHERE list_id = ? AND is_done = ? AND id != ? ORDER BY position LIMIT 1",
+    )
+    .bind(status.list_id)
+    .bind(status.is_done)
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .ok_or_else(|| {
+        ServerFnError::ServerError(format!(
+            "A list needs at least one {} status.",
+            if status.is_done { "done" } else { "open" }
+        ))
+    })?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE todos SET status_id = ? WHERE status_id = ?")
+        .bind(replacement)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    sqlx::query("DELETE FROM list_statuses WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::StatusDeleted,
+        AuditTarget::list(status.list_id),
+        Some(serde_json::json!({ "status": status.name })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
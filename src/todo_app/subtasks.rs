@@ -0,0 +1,243 @@
+use leptos::prelude::*;
+use leptos::reactive::wrappers::write::SignalSetter;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use super::dependencies::{set_completed, DependencyBadges};
+use super::live::use_todo_changes;
+use super::trash::{trash_with_undo, RestoreTodo};
+use super::{AddTodo, DeleteTodo, Todo, UpdateTodo};
+use crate::errors::TodoAppError;
+
+/// How many of the subtasks below a todo are done, counted over the whole tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubtaskProgress {
+    pub done: u32,
+    pub total: u32,
+}
+
+impl SubtaskProgress {
+    pub fn is_done(&self) -> bool {
+        self.done == self.total
+    }
+}
+
+impl std::fmt::Display for SubtaskProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{} done", self.done, self.total)
+    }
+}
+
+/// A todo somewhere below the expanded parent. `depth` is 1 for direct children.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subtask {
+    pub todo: Todo,
+    pub depth: u32,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::SubtaskProgress;
+    use crate::todo_app::ssr::SqlTodo;
+    use sqlx::SqlitePool;
+
+    /// Every todo below `parent_id`, parents before their children and siblings
+    /// oldest first.
+    const SUBTASK_TREE: &str = "WITH RECURSIVE tree (id, depth, path) AS (
+            SELECT id, 1, printf('%010d', id) FROM todos WHERE parent_id = ? AND deleted_at IS NULL
+            UNION ALL
+            SELECT todos.id, tree.depth + 1, tree.path || '/' || printf('%010d', todos.id)
+            FROM todos JOIN tree ON todos.parent_id = tree.id
+            WHERE todos.deleted_at IS NULL
+        )";
+
+    #[derive(sqlx::FromRow)]
+    pub struct SqlSubtask {
+        #[sqlx(flatten)]
+        pub todo: SqlTodo,
+        pub depth: u32,
+    }
+
+    pub async fn subtask_tree(
+        parent_id: u32,
+        pool: &SqlitePool,
+    ) -> Result<Vec<SqlSubtask>, sqlx::Error> {
+        sqlx::query_as::<_, SqlSubtask>(&format!(
+            "{SUBTASK_TREE} SELECT todos.*, tree.depth FROM tree JOIN todos ON todos.id = tree.id ORDER BY tree.path"
+        ))
+        .bind(parent_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn subtask_progress(parent_id: u32, pool: &SqlitePool) -> Option<SubtaskProgress> {
+        let (done, total) = sqlx::query_as::<_, (u32, u32)>(&format!(
+            "{SUBTASK_TREE} SELECT COALESCE(SUM(COALESCE(todos.completed, false)), 0), COUNT(*)
+            FROM tree JOIN todos ON todos.id = tree.id"
+        ))
+        .bind(parent_id)
+        .fetch_one(pool)
+        .await
+        .ok()?;
+
+        (total > 0).then_some(SubtaskProgress { done, total })
+    }
+}
+
+/// The whole tree of subtasks below the todo `id`.
+#[server(GetSubtasks, "/api")]
+pub async fn get_subtasks(id: u32) -> Result<Vec<Subtask>, ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::ssr::*;
+    use futures::future::join_all;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    // Subtasks are shared along with their parent.
+    accessible_todo(id, &user, ListRole::Viewer, &pool).await?;
+
+    let rows = subtask_tree(id, &pool)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    let pool = &pool;
+    Ok(join_all(rows.into_iter().map(|row| async move {
+        Subtask {
+            depth: row.depth,
+            todo: row.todo.into_todo(pool).await,
+        }
+    }))
+    .await)
+}
+
+#[component]
+pub fn SubtaskBadge(progress: SubtaskProgress) -> impl IntoView {
+    view! {
+        <Badge
+            appearance=BadgeAppearance::Tint
+            color=if progress.is_done() { BadgeColor::Success } else { BadgeColor::Informative }
+        >
+            {progress.to_string()}
+        </Badge>
+    }
+}
+
+/// Table rows for the subtasks of an expanded parent, plus a row to add more.
+/// They share the actions of the surrounding `Todos` table so it refreshes too.
+#[component]
+pub fn SubtaskRows(
+    parent_id: u32,
+    add_subtask: ServerAction<AddTodo>,
+    update_todo: ServerAction<UpdateTodo>,
+    delete_todo: ServerAction<DeleteTodo>,
+    restore_todo: ServerAction<RestoreTodo>,
+) -> impl IntoView {
+    let changes = use_todo_changes();
+    let subtasks = Resource::new(
+        move || {
+            (
+                add_subtask.version().get(),
+                update_todo.version().get(),
+                delete_todo.version().get(),
+                restore_todo.version().get(),
+                changes.get(),
+            )
+        },
+        move |_| get_subtasks(parent_id),
+    );
+    let title = RwSignal::new(String::new());
+
+    view! {
+        {move || {
+            subtasks
+                .get()
+                .and_then(Result::ok)
+                .map(|subtasks| {
+                    subtasks
+                        .into_iter()
+                        .map(|Subtask { todo, depth }| {
+                            let id = todo.id;
+                            let completed = todo.completed;
+                            let dependencies = todo.dependencies;
+                            view! {
+                                <TableRow class="todo-subtask">
+                                    <TableCell>
+                                        <Checkbox checked=(
+                                            Signal::derive(move || completed),
+                                            SignalSetter::map(move |completed| {
+                                                set_completed(id, completed, dependencies, update_todo);
+                                            }),
+                                        ) />
+                                    </TableCell>
+                                    <TableCell>
+                                        <TableCellLayout truncate=true>
+                                            <span style=format!("padding-left: {}em", depth * 2)>
+                                                {todo.title}
+                                            </span>
+                                            {todo.progress.map(|progress| view! { <SubtaskBadge progress /> })}
+                                            <DependencyBadges dependencies=todo.dependencies />
+                                        </TableCellLayout>
+                                    </TableCell>
+                                    <TableCell>
+                                        {todo.due_at.map(|due| due.format("%Y-%m-%d").to_string())}
+                                    </TableCell>
+                                    <TableCell />
+                                    <TableCell />
+                                    <TableCell>
+                                        <TableCellLayout truncate=true>
+                                            {todo.user.unwrap_or_default().username}
+                                        </TableCellLayout>
+                                    </TableCell>
+                                    <TableCell>
+                                        <TableCellLayout truncate=true>
+                                            {todo.assignee.map(|assignee| assignee.username)}
+                                        </TableCellLayout>
+                                    </TableCell>
+                                    <TableCell>
+                                        <Button
+                                            on_click=move |_| {
+                                                trash_with_undo(id, delete_todo, restore_todo);
+                                            }
+                                            icon=icondata::AiCloseCircleOutlined
+                                        />
+                                    </TableCell>
+                                </TableRow>
+                            }
+                        })
+                        .collect_view()
+                })
+        }}
+        <TableRow class="todo-subtask">
+            <TableCell />
+            <TableCell>
+                <Flex align=FlexAlign::Center gap=FlexGap::Small style="padding-left: 2em">
+                    <Input value=title placeholder="Add a subtask" />
+                    <Button
+                        appearance=ButtonAppearance::Subtle
+                        icon=icondata::LuPlus
+                        on_click=move |_| {
+                            if !title.get_untracked().trim().is_empty() {
+                                add_subtask.dispatch(AddTodo {
+                                    title: title.get_untracked(),
+                                    due_at: None,
+                                    remind_at: None,
+                                    list_id: None,
+                                    parent_id: Some(parent_id),
+                                    recurrence: None,
+                                });
+                                title.set(String::new());
+                            }
+                        }
+                    />
+                </Flex>
+            </TableCell>
+            <TableCell />
+            <TableCell />
+            <TableCell />
+            <TableCell />
+            <TableCell />
+            <TableCell />
+        </TableRow>
+    }
+}
@@ -0,0 +1,536 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use thaw::*;
+
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+/// A colored label. Named `TodoTag` so it doesn't shadow Thaw's `Tag` component.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoTag {
+    pub id: i64,
+    pub name: String,
+    pub color: String,
+}
+
+impl TodoTag {
+    pub fn style(&self) -> String {
+        format!("background-color: {}", self.color)
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::TodoTag;
+    use crate::auth::User;
+    use crate::errors::TodoAppError;
+    use crate::todo_app::ssr::is_todos_admin;
+    use leptos::prelude::*;
+    use sqlx::SqlitePool;
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlTodoTag {
+        pub id: i64,
+        pub user_id: i64,
+        pub name: String,
+        pub color: String,
+    }
+
+    impl SqlTodoTag {
+        pub fn into_tag(self) -> TodoTag {
+            TodoTag {
+                id: self.id,
+                name: self.name,
+                color: self.color,
+            }
+        }
+    }
+
+    /// Loads a tag, failing with `Forbidden` unless `user` owns it or is an admin.
+    pub async fn owned_tag(
+        id: i64,
+        user: &User,
+        pool: &SqlitePool,
+    ) -> Result<SqlTodoTag, ServerFnError<TodoAppError>> {
+        let tag = sqlx::query_as::<_, SqlTodoTag>("SELECT * FROM tags WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(TodoAppError::from)?
+            .ok_or(TodoAppError::NotFound)?;
+
+        if tag.user_id == user.id || is_todos_admin(user, pool).await {
+            Ok(tag)
+        } else {
+            Err(TodoAppError::Forbidden.into())
+        }
+    }
+
+    pub async fn tags_for_todo(todo_id: u32, pool: &SqlitePool) -> Vec<TodoTag> {
+        sqlx::query_as::<_, SqlTodoTag>(
+            "SELECT tags.* FROM tags JOIN todo_tags ON todo_tags.tag_id = tags.id WHERE todo_tags.todo_id = ? ORDER BY tags.name",
+        )
+        .bind(todo_id)
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(SqlTodoTag::into_tag)
+        .collect()
+    }
+
+    /// Accepts `#rrggbb` colors only, since the value ends up in a `style` attribute.
+    pub fn validate_color(color: &str) -> Result<(), ServerFnError<TodoAppError>> {
+        let valid = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+        if valid {
+            Ok(())
+        } else {
+            Err(ServerFnError::ServerError(format!("Invalid color {color:?}.")))
+        }
+    }
+}
+
+#[server(GetTags, "/api")]
+pub async fn get_tags() -> Result<Vec<TodoTag>, ServerFnError<TodoAppError>> {
+    use self::ssr::SqlTodoTag;
+    use crate::todo_app::ssr::*;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(Vec::new());
+    };
+
+    Ok(sqlx::query_as::<_, SqlTodoTag>(
+        "SELECT * FROM tags WHERE user_id = ? ORDER BY name",
+    )
+    .bind(user.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .into_iter()
+    .map(SqlTodoTag::into_tag)
+    .collect())
+}
+
+#[server(AddTag, "/api")]
+pub async fn add_tag(
+    name: String,
+    color: String,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::validate_color;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Tag name cannot be empty.".to_string(),
+        ));
+    }
+    validate_color(&color)?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let id = sqlx::query("INSERT INTO tags (user_id, name, color) VALUES (?, ?, ?)")
+        .bind(user.id)
+        .bind(name.trim())
+        .bind(&color)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?
+        .last_insert_rowid();
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::TagCreated,
+        AuditTarget::tag(id),
+        None,
+        Some(serde_json::json!({ "name": name.trim(), "color": color })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(RenameTag, "/api")]
+pub async fn rename_tag(
+    id: i64,
+    name: String,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::owned_tag;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Tag name cannot be empty.".to_string(),
+        ));
+    }
+
+    let tag = owned_tag(id, &user, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE tags SET name = ? WHERE id = ?")
+        .bind(name.trim())
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::TagUpdated,
+        AuditTarget::tag(id),
+        Some(serde_json::json!({ "name": tag.name })),
+        Some(serde_json::json!({ "name": name.trim() })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(RecolorTag, "/api")]
+pub async fn recolor_tag(
+    id: i64,
+    color: String,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::{owned_tag, validate_color};
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    validate_color(&color)?;
+    let tag = owned_tag(id, &user, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("UPDATE tags SET color = ? WHERE id = ?")
+        .bind(&color)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::TagUpdated,
+        AuditTarget::tag(id),
+        Some(serde_json::json!({ "color": tag.color })),
+        Some(serde_json::json!({ "color": color })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(DeleteTag, "/api")]
+pub async fn delete_tag(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::owned_tag;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let tag = owned_tag(id, &user, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM tags WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::TagDeleted,
+        AuditTarget::tag(id),
+        Some(serde_json::json!({ "name": tag.name, "color": tag.color })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(AttachTag, "/api")]
+pub async fn attach_tag(
+    todo_id: u32,
+    tag_id: i64,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::owned_tag;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(todo_id, &user, ListRole::Editor, &pool).await?;
+    let tag = owned_tag(tag_id, &user, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO todo_tags (todo_id, tag_id) VALUES (?, ?)",
+    )
+    .bind(todo_id)
+    .bind(tag_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::TagAttached,
+        AuditTarget::todo(todo_id),
+        None,
+        Some(serde_json::json!({ "tag": tag.name })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[server(DetachTag, "/api")]
+pub async fn detach_tag(
+    todo_id: u32,
+    tag_id: i64,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    accessible_todo(todo_id, &user, ListRole::Editor, &pool).await?;
+
+    let tag = sqlx::query_scalar::<_, String>("SELECT name FROM tags WHERE id = ?")
+        .bind(tag_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM todo_tags WHERE todo_id = ? AND tag_id = ?")
+        .bind(todo_id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::TagDetached,
+        AuditTarget::todo(todo_id),
+        Some(serde_json::json!({ "tag": tag })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+#[component]
+pub fn Tags() -> impl IntoView {
+    let add_tag = ServerAction::<AddTag>::new();
+    let rename_tag = ServerAction::<RenameTag>::new();
+    let recolor_tag = ServerAction::<RecolorTag>::new();
+    let delete_tag = ServerAction::<DeleteTag>::new();
+
+    let tags = Resource::new(
+        move || {
+            (
+                add_tag.version().get(),
+                rename_tag.version().get(),
+                recolor_tag.version().get(),
+                delete_tag.version().get(),
+            )
+        },
+        move |_| get_tags(),
+    );
+
+    let name = RwSignal::new(String::from(""));
+    let color = RwSignal::new(String::from("#8a8886"));
+
+    view! {
+        <Flex vertical=true>
+            <Flex justify=FlexJustify::Center gap=FlexGap::Small>
+                <Text>"New Tag"</Text>
+                <Input value=name />
+                <input type="color" bind:value=color />
+                <Button appearance=ButtonAppearance::Primary on_click=move |_| {
+                    add_tag.dispatch(AddTag { name: name.get(), color: color.get() });
+                    name.set(String::from(""));
+                }>"Add"</Button>
+            </Flex>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                <ErrorBoundary fallback=|errors| {
+                    view! { <ErrorTemplate errors=errors/> }
+                }>
+                    <Card>
+                        <Table>
+                            <TableHeader>
+                                <TableRow>
+                                    <TableHeaderCell resizable=true min_width=100.0>"Name"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Color"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Preview"</TableHeaderCell>
+                                    <TableHeaderCell max_width=100.0>"Delete"</TableHeaderCell>
+                                </TableRow>
+                            </TableHeader>
+                            <TableBody>
+                                {move || {
+                                    tags.get().map(move |tags| match tags {
+                                        Err(e) => {
+                                            view! {
+                                                <pre class="error">"Server Error: " {e.to_string()}</pre>
+                                            }
+                                                .into_any()
+                                        },
+                                        Ok(tags) => {
+                                            if tags.is_empty() {
+                                                view! { <p>"No tags were found."</p> }.into_any()
+                                            } else {
+                                                tags
+                                                    .into_iter()
+                                                    .map(move |tag| {
+                                                        let id = tag.id;
+                                                        let name = RwSignal::new(tag.name.clone());
+                                                        let saved_name = tag.name.clone();
+                                                        let style = tag.style();
+                                                        view! {
+                                                            <TableRow>
+                                                                <TableCell>
+                                                                    <TableCellLayout truncate=true>
+                                                                        <Input
+                                                                            value=name
+                                                                            on_blur=move |_| {
+                                                                                let new_name = name.get_untracked();
+                                                                                if new_name != saved_name {
+                                                                                    rename_tag.dispatch(RenameTag { id, name: new_name });
+                                                                                }
+                                                                            }
+                                                                        />
+                                                                    </TableCellLayout>
+                                                                </TableCell>
+                                                                <TableCell>
+                                                                    <input
+                                                                        type="color"
+                                                                        value=tag.color
+                                                                        on:change=move |ev| {
+                                                                            recolor_tag.dispatch(RecolorTag { id, color: event_target_value(&ev) });
+                                                                        }
+                                                                    />
+                                                                </TableCell>
+                                                                <TableCell>
+                                                                    <Tag attr:style=style>{tag.name}</Tag>
+                                                                </TableCell>
+                                                                <TableCell>
+                                                                    <Button
+                                                                        on_click=move |_| {
+                                                                            delete_tag.dispatch(DeleteTag { id });
+                                                                        }
+                                                                        icon=icondata::AiCloseCircleOutlined
+                                                                    />
+                                                                </TableCell>
+                                                            </TableRow>
+                                                        }
+                                                    })
+                                                    .collect_view()
+                                                    .into_any()
+                                            }
+                                        }
+                                    })
+                                }}
+                            </TableBody>
+                        </Table>
+                    </Card>
+                </ErrorBoundary>
+            </Transition>
+        </Flex>
+    }
+}
+
+/// The tags on a single todo, with a menu to attach any of the user's other tags.
+#[component]
+pub fn TagChips(
+    todo_id: u32,
+    tags: Vec<TodoTag>,
+    all_tags: Vec<TodoTag>,
+    attach_tag: ServerAction<AttachTag>,
+    detach_tag: ServerAction<DetachTag>,
+) -> impl IntoView {
+    let attachable = all_tags
+        .into_iter()
+        .filter(|tag| !tags.contains(tag))
+        .collect::<Vec<_>>();
+
+    view! {
+        <Flex align=FlexAlign::Center gap=FlexGap::Small>
+            <TagGroup
+                size=TagSize::ExtraSmall
+                dismissible=true
+                on_dismiss=move |value: String| {
+                    if let Ok(tag_id) = value.parse() {
+                        detach_tag.dispatch(DetachTag { todo_id, tag_id });
+                    }
+                }
+            >
+                {tags
+                    .into_iter()
+                    .map(|tag| {
+                        let style = tag.style();
+                        view! { <Tag value=tag.id.to_string() attr:style=style>{tag.name}</Tag> }
+                    })
+                    .collect_view()}
+            </TagGroup>
+            {(!attachable.is_empty()).then(|| view! {
+                <Menu on_select=move |value: String| {
+                    if let Ok(tag_id) = value.parse() {
+                        attach_tag.dispatch(AttachTag { todo_id, tag_id });
+                    }
+                }>
+                    <MenuTrigger slot>
+                        <Button appearance=ButtonAppearance::Subtle size=ButtonSize::Small icon=icondata::LuTag />
+                    </MenuTrigger>
+                    {attachable
+                        .into_iter()
+                        .map(|tag| view! { <MenuItem value=tag.id.to_string()>{tag.name}</MenuItem> })
+                        .collect_view()}
+                </Menu>
+            })}
+        </Flex>
+    }
+}
+
+/// Tag checkboxes that narrow the todos table to todos carrying every selected tag.
+#[component]
+pub fn TagFilterBar(
+    tags: Vec<TodoTag>,
+    selected: RwSignal<HashSet<String>>,
+) -> impl IntoView {
+    view! {
+        <Flex justify=FlexJustify::Center align=FlexAlign::Center gap=FlexGap::Small>
+            <Text>"Filter by tags"</Text>
+            <CheckboxGroup value=selected>
+                {tags
+                    .into_iter()
+                    .map(|tag| view! { <Checkbox value=tag.id.to_string() label=tag.name /> })
+                    .collect_view()}
+            </CheckboxGroup>
+        </Flex>
+    }
+}
@@ -0,0 +1,318 @@
+use leptos::prelude::*;
+use thaw::*;
+
+use super::live::{provide_todo_changes, use_todo_changes};
+use super::{DeleteTodo, Todo};
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+/// Days a todo stays in the trash before it's purged, unless
+/// `TODO_TRASH_RETENTION_DAYS` says otherwise.
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::DEFAULT_RETENTION_DAYS;
+    use crate::todo_app::attachments::ssr::{remove_orphaned_files, AttachmentConfig};
+    use crate::todo_app::audit::{ssr::{record, todo_snapshot, AuditTarget}, AuditAction};
+    use crate::todo_app::live::{ssr::ChangeHub, TodoChange};
+    use crate::todo_app::ssr::SqlTodo;
+    use sqlx::SqlitePool;
+    use std::time::Duration;
+
+    /// How often the purge task looks for expired todos.
+    const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+    /// The todo `id` and everything below it. Deleting and restoring always
+    /// moves a whole subtree, so subtasks don't outlive their parents.
+    pub const TODO_SUBTREE: &str = "WITH RECURSIVE tree (id) AS (
+            SELECT ?
+            UNION ALL
+            SELECT todos.id FROM todos JOIN tree ON todos.parent_id = tree.id
+        )";
+
+    pub fn retention_days() -> u32 {
+        std::env::var("TODO_TRASH_RETENTION_DAYS")
+            .ok()
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(DEFAULT_RETENTION_DAYS)
+    }
+
+    pub async fn purge_expired(pool: &SqlitePool, retention_days: u32) -> Result<Vec<SqlTodo>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let purged = sqlx::query_as::<_, SqlTodo>(
+            "DELETE FROM todos WHERE deleted_at < datetime('now', ?) RETURNING *",
+        )
+        .bind(format!("-{retention_days} days"))
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for todo in &purged {
+            record(
+                &mut *tx,
+                None,
+                AuditAction::TodoPurged,
+                AuditTarget::todo(todo.id),
+                todo_snapshot(todo),
+                None,
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(purged)
+    }
+
+    /// Periodically deletes todos that have been in the trash longer than
+    /// `retention_days`, along with the files attached to them.
+    pub fn spawn_purge_task(
+        pool: SqlitePool,
+        retention_days: u32,
+        attachments: AttachmentConfig,
+        changes: ChangeHub,
+    ) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PURGE_INTERVAL);
+            loop {
+                interval.tick().await;
+                match purge_expired(&pool, retention_days).await {
+                    Ok(purged) if purged.is_empty() => {}
+                    Ok(purged) => {
+                        log::info!("Purged {} todos from the trash", purged.len());
+                        for todo in purged {
+                            changes.publish(TodoChange {
+                                action: AuditAction::TodoPurged,
+                                target_type: "todo".to_string(),
+                                target_id: todo.id.into(),
+                                todo_id: Some(todo.id),
+                                owner_id: Some(todo.user_id),
+                                list_id: todo.list_id,
+                                workspace_id: todo.workspace_id,
+                            });
+                        }
+                    }
+                    Err(error) => log::error!("Purging the trash failed: {error:?}"),
+                }
+                match remove_orphaned_files(&pool, &attachments).await {
+                    Ok(0) => {}
+                    Ok(removed) => log::info!("Removed {removed} orphaned attachment files"),
+                    Err(error) => log::error!("Removing attachment files failed: {error:?}"),
+                }
+            }
+        });
+    }
+}
+
+/// The deleted todos the current user can restore, their own and those in
+/// lists they own. Subtasks deleted along with their parent are left out, they
+/// come back when the parent is restored.
+#[server(GetTrash, "/api")]
+pub async fn get_trash() -> Result<Vec<Todo>, ServerFnError<TodoAppError>> {
+    use crate::todo_app::ssr::*;
+    use crate::todo_app::workspaces::ssr::current_workspace;
+    use futures::future::join_all;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(vec![]);
+    };
+
+    let todos = sqlx::query_as::<_, SqlTodo>(
+        "SELECT * FROM todos
+        WHERE deleted_at IS NOT NULL AND (?
+            OR (list_id IS NULL AND user_id = ?)
+            OR list_id IN (SELECT list_id FROM list_members WHERE user_id = ? AND role = 'owner'))
+        AND workspace_id = ?
+        AND (parent_id IS NULL OR parent_id NOT IN (SELECT id FROM todos WHERE deleted_at IS NOT NULL))
+        ORDER BY deleted_at DESC",
+    )
+    .bind(is_todos_admin(&user, &pool).await)
+    .bind(user.id)
+    .bind(user.id)
+    .bind(current_workspace(&user, &pool).await?)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    Ok(join_all(todos.into_iter().map(|todo| todo.into_todo(&pool))).await)
+}
+
+/// Takes a todo, and the subtasks deleted with it, back out of the trash.
+#[server(RestoreTodo, "/api")]
+pub async fn restore_todo(id: u32) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::TODO_SUBTREE;
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let todo = accessible_todo(id, &user, ListRole::Owner, &pool).await?;
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query(&format!(
+        "{TODO_SUBTREE} UPDATE todos SET deleted_at = NULL
+        WHERE id IN (SELECT id FROM tree) AND deleted_at = (SELECT deleted_at FROM todos WHERE id = ?)"
+    ))
+    .bind(id)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoRestored, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Deletes a todo in the trash for good.
+#[server(PurgeTodo, "/api")]
+pub async fn purge_todo(id: u32) -> Result<(), ServerFnError<TodoAppError>> {
+    use crate::todo_app::attachments::ssr::remove_orphaned_files_logged;
+    use crate::todo_app::audit::{ssr::record_todo, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let todo = accessible_todo(id, &user, ListRole::Owner, &pool).await?;
+    if todo.deleted_at.is_none() {
+        return Err(ServerFnError::ServerError(
+            "Only todos in the trash can be deleted permanently.".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM todos WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+
+    record_todo(&mut tx, Some(user.id), AuditAction::TodoPurged, id, Some(&todo))
+        .await
+        .map_err(TodoAppError::from)?;
+    tx.commit().await.map_err(TodoAppError::from)?;
+
+    remove_orphaned_files_logged(&pool).await;
+    Ok(())
+}
+
+/// Moves the todo `id` to the trash and offers to undo that in a toast.
+pub fn trash_with_undo(
+    id: u32,
+    delete_todo: ServerAction<DeleteTodo>,
+    restore_todo: ServerAction<RestoreTodo>,
+) {
+    delete_todo.dispatch(DeleteTodo { id });
+    ToasterInjection::expect_context().dispatch_toast(
+        move || {
+            view! {
+                <Toast>
+                    <ToastTitle>
+                        "Moved to trash"
+                        <ToastTitleAction slot>
+                            <Link on:click=move |_| {
+                                restore_todo.dispatch(RestoreTodo { id });
+                            }>"Undo"</Link>
+                        </ToastTitleAction>
+                    </ToastTitle>
+                </Toast>
+            }
+        },
+        Default::default(),
+    );
+}
+
+#[component]
+pub fn Trash() -> impl IntoView {
+    let restore_todo = ServerAction::<RestoreTodo>::new();
+    let purge_todo = ServerAction::<PurgeTodo>::new();
+
+    provide_todo_changes();
+    let changes = use_todo_changes();
+
+    let trash = Resource::new(
+        move || (restore_todo.version().get(), purge_todo.version().get(), changes.get()),
+        move |_| get_trash(),
+    );
+
+    view! {
+        <Transition fallback=move || view! { <p>"Loading..."</p> }>
+            <ErrorBoundary fallback=|errors| {
+                view! { <ErrorTemplate errors=errors/> }
+            }>
+                <Card>
+                    <Table>
+                        <TableHeader>
+                            <TableRow>
+                                <TableHeaderCell resizable=true min_width=200.0>"Title"</TableHeaderCell>
+                                <TableHeaderCell max_width=160.0>"Deleted"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"Creator"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"Restore"</TableHeaderCell>
+                                <TableHeaderCell max_width=100.0>"Delete"</TableHeaderCell>
+                            </TableRow>
+                        </TableHeader>
+                        <TableBody>
+                            {move || {
+                                trash.get().map(|todos| match todos {
+                                    Err(e) => {
+                                        view! {
+                                            <pre class="error">"Server Error: " {e.to_string()}</pre>
+                                        }
+                                            .into_any()
+                                    }
+                                    Ok(todos) => {
+                                        if todos.is_empty() {
+                                            view! { <p>"The trash is empty."</p> }.into_any()
+                                        } else {
+                                            todos
+                                                .into_iter()
+                                                .map(|todo| {
+                                                    let id = todo.id;
+                                                    view! {
+                                                        <TableRow>
+                                                            <TableCell>
+                                                                <TableCellLayout truncate=true>{todo.title}</TableCellLayout>
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                {todo.deleted_at.map(|deleted| deleted.format("%Y-%m-%d %H:%M").to_string())}
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <TableCellLayout truncate=true>
+                                                                    {todo.user.unwrap_or_default().username}
+                                                                </TableCellLayout>
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <Button
+                                                                    on_click=move |_| {
+                                                                        restore_todo.dispatch(RestoreTodo { id });
+                                                                    }
+                                                                    icon=icondata::LuUndo2
+                                                                />
+                                                            </TableCell>
+                                                            <TableCell>
+                                                                <Button
+                                                                    on_click=move |_| {
+                                                                        purge_todo.dispatch(PurgeTodo { id });
+                                                                    }
+                                                                    icon=icondata::LuTrash2
+                                                                />
+                                                            </TableCell>
+                                                        </TableRow>
+                                                    }
+                                                })
+                                                .collect_view()
+                                                .into_any()
+                                        }
+                                    }
+                                })
+                            }}
+                        </TableBody>
+                    </Table>
+                </Card>
+            </ErrorBoundary>
+        </Transition>
+    }
+}
@@ -0,0 +1,611 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thaw::*;
+
+use crate::{error_template::ErrorTemplate, errors::TodoAppError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type))]
+#[cfg_attr(feature = "ssr", sqlx(type_name = "TEXT", rename_all = "lowercase"))]
+pub enum WorkspaceRole {
+    /// Works with the lists and todos in the workspace.
+    Member,
+    /// Also invites and removes members.
+    Admin,
+}
+
+impl WorkspaceRole {
+    pub const ALL: [WorkspaceRole; 2] = [WorkspaceRole::Member, WorkspaceRole::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Member => "member",
+            WorkspaceRole::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        WorkspaceRole::ALL.into_iter().find(|role| role.as_str() == value)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Member => "Member",
+            WorkspaceRole::Admin => "Admin",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    /// What the current user can do in the workspace.
+    pub role: WorkspaceRole,
+    /// Whether this is the workspace the current user is working in.
+    pub is_current: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceMember {
+    pub user_id: i64,
+    pub username: String,
+    pub role: WorkspaceRole,
+}
+
+#[cfg(feature = "ssr")]
+pub mod ssr {
+    use super::{WorkspaceMember, WorkspaceRole};
+    use crate::auth::{ssr::AuthSession, User};
+    use crate::errors::TodoAppError;
+    use crate::todo_app::ssr::auth;
+    use leptos::prelude::*;
+    use sqlx::{Executor, Sqlite, SqlitePool, Transaction};
+
+    /// Where the current workspace is kept in the session.
+    const WORKSPACE_SESSION_KEY: &str = "workspace_id";
+
+    #[derive(sqlx::FromRow, Clone)]
+    pub struct SqlWorkspaceMember {
+        pub user_id: i64,
+        pub username: String,
+        pub role: WorkspaceRole,
+    }
+
+    impl SqlWorkspaceMember {
+        pub fn into_member(self) -> WorkspaceMember {
+            WorkspaceMember {
+                user_id: self.user_id,
+                username: self.username,
+                role: self.role,
+            }
+        }
+    }
+
+    /// The role `user` has in the workspace, `None` if they aren't a member.
+    /// Being a todo admin doesn't get anyone into other tenants' workspaces.
+    pub async fn workspace_role(
+        workspace_id: i64,
+        user: &User,
+        pool: &SqlitePool,
+    ) -> Result<Option<WorkspaceRole>, sqlx::Error> {
+        sqlx::query_scalar::<_, WorkspaceRole>(
+            "SELECT role FROM workspace_members WHERE workspace_id = ? AND user_id = ?",
+        )
+        .bind(workspace_id)
+        .bind(user.id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Fails with `Forbidden` unless `user` has at least `role` in the workspace.
+    pub async fn require_workspace_role(
+        workspace_id: i64,
+        user: &User,
+        role: WorkspaceRole,
+        pool: &SqlitePool,
+    ) -> Result<WorkspaceRole, ServerFnError<TodoAppError>> {
+        match workspace_role(workspace_id, user, pool).await.map_err(TodoAppError::from)? {
+            Some(actual) if actual >= role => Ok(actual),
+            _ => Err(TodoAppError::Forbidden.into()),
+        }
+    }
+
+    /// The workspace the current user is working in, as kept in the session.
+    /// Falls back to the first one they are a member of, and remembers that.
+    pub async fn current_workspace(
+        user: &User,
+        pool: &SqlitePool,
+    ) -> Result<i64, ServerFnError<TodoAppError>> {
+        session_workspace(&auth()?, user, pool).await
+    }
+
+    /// [`current_workspace`] for handlers that aren't server functions.
+    pub async fn session_workspace(
+        auth: &AuthSession,
+        user: &User,
+        pool: &SqlitePool,
+    ) -> Result<i64, ServerFnError<TodoAppError>> {
+        if let Some(id) = auth.session.get::<i64>(WORKSPACE_SESSION_KEY) {
+            if workspace_role(id, user, pool).await.map_err(TodoAppError::from)?.is_some() {
+                return Ok(id);
+            }
+        }
+
+        let id = sqlx::query_scalar::<_, i64>(
+            "SELECT workspace_id FROM workspace_members WHERE user_id = ? ORDER BY workspace_id LIMIT 1",
+        )
+        .bind(user.id)
+        .fetch_optional(pool)
+        .await
+        .map_err(TodoAppError::from)?
+        .ok_or(TodoAppError::Forbidden)?;
+        auth.session.set(WORKSPACE_SESSION_KEY, id);
+        Ok(id)
+    }
+
+    pub fn set_current_workspace(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+        auth()?.session.set(WORKSPACE_SESSION_KEY, id);
+        Ok(())
+    }
+
+    /// Creates a workspace with `user_id` as its admin.
+    pub async fn create_workspace<'c, E>(
+        name: &str,
+        user_id: i64,
+        executor: E,
+    ) -> Result<i64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Sqlite>,
+    {
+        sqlx::query_scalar::<_, i64>("INSERT INTO workspaces (name, created_by) VALUES (?, ?) RETURNING id")
+            .bind(name)
+            .bind(user_id)
+            .fetch_one(executor)
+            .await
+    }
+
+    pub async fn add_member<'c, E>(
+        workspace_id: i64,
+        user_id: i64,
+        role: WorkspaceRole,
+        executor: E,
+    ) -> Result<u64, sqlx::Error>
+    where
+        E: Executor<'c, Database = Sqlite>,
+    {
+        Ok(sqlx::query(
+            "INSERT OR IGNORE INTO workspace_members (workspace_id, user_id, role) VALUES (?, ?, ?)",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(executor)
+        .await?
+        .rows_affected())
+    }
+
+    /// Where new users keep their todos until they join a team.
+    pub async fn create_personal_workspace(
+        user_id: i64,
+        username: &str,
+        tx: &mut Transaction<'_, Sqlite>,
+    ) -> Result<i64, sqlx::Error> {
+        let id = create_workspace(&format!("{username}'s workspace"), user_id, &mut **tx).await?;
+        add_member(id, user_id, WorkspaceRole::Admin, &mut **tx).await?;
+        Ok(id)
+    }
+}
+
+/// The workspaces the current user is a member of.
+#[server(GetWorkspaces, "/api")]
+pub async fn get_workspaces() -> Result<Vec<Workspace>, ServerFnError<TodoAppError>> {
+    use self::ssr::current_workspace;
+    use crate::todo_app::ssr::*;
+
+    let pool = pool()?;
+
+    let Some(user) = auth()?.current_user else {
+        return Ok(Vec::new());
+    };
+    let current = current_workspace(&user, &pool).await?;
+
+    Ok(sqlx::query_as::<_, (i64, String, WorkspaceRole)>(
+        "SELECT workspaces.id, workspaces.name, workspace_members.role FROM workspaces
+        JOIN workspace_members ON workspace_members.workspace_id = workspaces.id
+        WHERE workspace_members.user_id = ? ORDER BY workspaces.name",
+    )
+    .bind(user.id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .into_iter()
+    .map(|(id, name, role)| Workspace {
+        id,
+        name,
+        role,
+        is_current: id == current,
+    })
+    .collect())
+}
+
+#[server(SwitchWorkspace, "/api")]
+pub async fn switch_workspace(id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    require_workspace_role(id, &user, WorkspaceRole::Member, &pool).await?;
+    set_current_workspace(id)
+}
+
+/// Creates a workspace for a new team and switches to it.
+#[server(AddWorkspace, "/api")]
+pub async fn add_workspace(name: String) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    if name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Workspace name cannot be empty.".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let id = create_workspace(name.trim(), user.id, &mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    add_member(id, user.id, WorkspaceRole::Admin, &mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::WorkspaceCreated,
+        AuditTarget::workspace(id),
+        None,
+        Some(serde_json::json!({ "name": name.trim() })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    tx.commit().await.map_err(TodoAppError::from)?;
+
+    set_current_workspace(id)
+}
+
+/// Everyone in the current workspace, admins first.
+#[server(GetWorkspaceMembers, "/api")]
+pub async fn get_workspace_members() -> Result<Vec<WorkspaceMember>, ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let workspace_id = current_workspace(&user, &pool).await?;
+
+    Ok(sqlx::query_as::<_, SqlWorkspaceMember>(
+        "SELECT workspace_members.user_id, users.username, workspace_members.role
+        FROM workspace_members JOIN users ON users.id = workspace_members.user_id
+        WHERE workspace_members.workspace_id = ?
+        ORDER BY workspace_members.role = 'admin' DESC, users.username",
+    )
+    .bind(workspace_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .into_iter()
+    .map(SqlWorkspaceMember::into_member)
+    .collect())
+}
+
+/// Adds a registered user to the current workspace.
+#[server(AddWorkspaceMember, "/api")]
+pub async fn add_workspace_member(
+    username: String,
+    role: WorkspaceRole,
+) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::auth::User;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let workspace_id = current_workspace(&user, &pool).await?;
+    require_workspace_role(workspace_id, &user, WorkspaceRole::Admin, &pool).await?;
+
+    let username = username.trim().trim_start_matches('@');
+    let Some(member) = User::get_from_username(username.to_string(), &pool).await else {
+        return Err(ServerFnError::ServerError(format!(
+            "There is no user named {username}."
+        )));
+    };
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    let added = add_member(workspace_id, member.id, role, &mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    if added == 0 {
+        return Err(ServerFnError::ServerError(format!(
+            "{username} is already a member of this workspace."
+        )));
+    }
+
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::WorkspaceMemberAdded,
+        AuditTarget::workspace(workspace_id),
+        None,
+        Some(serde_json::json!({ member.username: role.as_str() })),
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Admins can remove anyone, and every member can leave on their own. They
+/// also lose access to the lists in the workspace that were shared with them.
+#[server(RemoveWorkspaceMember, "/api")]
+pub async fn remove_workspace_member(user_id: i64) -> Result<(), ServerFnError<TodoAppError>> {
+    use self::ssr::*;
+    use crate::todo_app::audit::{ssr::{record, AuditTarget}, AuditAction};
+    use crate::todo_app::ssr::*;
+
+    let user = current_user()?;
+    let pool = pool()?;
+
+    let workspace_id = current_workspace(&user, &pool).await?;
+    if user_id != user.id {
+        require_workspace_role(workspace_id, &user, WorkspaceRole::Admin, &pool).await?;
+    }
+
+    let member = sqlx::query_as::<_, SqlWorkspaceMember>(
+        "SELECT workspace_members.user_id, users.username, workspace_members.role
+        FROM workspace_members JOIN users ON users.id = workspace_members.user_id
+        WHERE workspace_members.workspace_id = ? AND workspace_members.user_id = ?",
+    )
+    .bind(workspace_id)
+    .bind(user_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(TodoAppError::from)?
+    .ok_or(TodoAppError::NotFound)?;
+
+    if member.role == WorkspaceRole::Admin {
+        let other_admins = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM workspace_members WHERE workspace_id = ? AND user_id != ? AND role = 'admin'",
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(TodoAppError::from)?;
+        if other_admins == 0 {
+            return Err(ServerFnError::ServerError(
+                "A workspace needs at least one admin.".to_string(),
+            ));
+        }
+    }
+
+    let mut tx = pool.begin().await.map_err(TodoAppError::from)?;
+    sqlx::query("DELETE FROM workspace_members WHERE workspace_id = ? AND user_id = ?")
+        .bind(workspace_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(TodoAppError::from)?;
+    sqlx::query(
+        "DELETE FROM list_members WHERE user_id = ?
+        AND list_id IN (SELECT id FROM lists WHERE workspace_id = ?)",
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(TodoAppError::from)?;
+    record(
+        &mut *tx,
+        Some(user.id),
+        AuditAction::WorkspaceMemberRemoved,
+        AuditTarget::workspace(workspace_id),
+        Some(serde_json::json!({ member.username: member.role.as_str() })),
+        None,
+    )
+    .await
+    .map_err(TodoAppError::from)?;
+    Ok(tx.commit().await.map_err(TodoAppError::from)?)
+}
+
+/// Switches to the workspace `id`. Everything on the page belongs to the old
+/// one, so it is loaded again from scratch.
+pub fn change_workspace(id: i64) {
+    leptos::task::spawn_local(async move {
+        if switch_workspace(id).await.is_ok() {
+            let _ = window().location().reload();
+        }
+    });
+}
+
+/// Menu items for the `TitleBar` menu, one per workspace the user is in.
+/// Their values are `workspace:<id>`, see [`change_workspace`].
+#[component]
+pub fn WorkspaceMenuItems() -> impl IntoView {
+    // The menu only opens in the browser, so there is nothing to render on the server.
+    let workspaces = LocalResource::new(get_workspaces);
+
+    move || {
+        workspaces
+            .get()
+            .and_then(|workspaces| workspaces.take().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|workspace| {
+                let icon = if workspace.is_current { icondata::LuCheck } else { icondata::LuLayers };
+                view! {
+                    <MenuItem icon value=format!("workspace:{}", workspace.id)>
+                        {workspace.name}
+                    </MenuItem>
+                }
+            })
+            .collect_view()
+    }
+}
+
+/// The members of the current workspace, and a way to start a new one.
+#[component]
+pub fn WorkspaceSettings() -> impl IntoView {
+    let add_workspace = ServerAction::<AddWorkspace>::new();
+    let add_member = ServerAction::<AddWorkspaceMember>::new();
+    let remove_member = ServerAction::<RemoveWorkspaceMember>::new();
+
+    let workspaces = Resource::new(move || add_workspace.version().get(), |_| get_workspaces());
+    let members = Resource::new(
+        move || {
+            (
+                add_workspace.version().get(),
+                add_member.version().get(),
+                remove_member.version().get(),
+            )
+        },
+        |_| get_workspace_members(),
+    );
+
+    // Creating a workspace switches to it, which changes everything else on the page.
+    Effect::watch(
+        move || add_workspace.version().get(),
+        move |_, _, _| {
+            if matches!(add_workspace.value().get_untracked(), Some(Ok(()))) {
+                let _ = window().location().reload();
+            }
+        },
+        false,
+    );
+
+    let username = RwSignal::new(String::new());
+    let role = RwSignal::new(WorkspaceRole::Member.as_str().to_string());
+    let name = RwSignal::new(String::new());
+
+    let error = move || {
+        [
+            add_workspace.value().get().and_then(Result::err),
+            add_member.value().get().and_then(Result::err),
+            remove_member.value().get().and_then(Result::err),
+        ]
+        .into_iter()
+        .flatten()
+        .next()
+        .map(|e| view! { <Text class="error">{e.to_string()}</Text> })
+    };
+
+    view! {
+        <Flex vertical=true>
+            <Transition fallback=move || view! { <p>"Loading..."</p> }>
+                <ErrorBoundary fallback=|errors| {
+                    view! { <ErrorTemplate errors=errors/> }
+                }>
+                    {move || {
+                        workspaces.get().map(|workspaces| {
+                            workspaces.map(|workspaces| {
+                                let current = workspaces.into_iter().find(|workspace| workspace.is_current);
+                                let is_admin = current.as_ref().is_some_and(|workspace| workspace.role == WorkspaceRole::Admin);
+                                let title = current.map(|workspace| workspace.name).unwrap_or_default();
+                                view! {
+                                    <Caption1Strong>{title}</Caption1Strong>
+                                    <Card>
+                                        <Table>
+                                            <TableHeader>
+                                                <TableRow>
+                                                    <TableHeaderCell resizable=true min_width=100.0>"Member"</TableHeaderCell>
+                                                    <TableHeaderCell max_width=100.0>"Role"</TableHeaderCell>
+                                                    <TableHeaderCell max_width=100.0>"Remove"</TableHeaderCell>
+                                                </TableRow>
+                                            </TableHeader>
+                                            <TableBody>
+                                                {move || {
+                                                    members.get().map(|members| match members {
+                                                        Err(e) => view! { <pre class="error">"Server Error: " {e.to_string()}</pre> }.into_any(),
+                                                        Ok(members) => {
+                                                            members
+                                                                .into_iter()
+                                                                .map(|member| {
+                                                                    let user_id = member.user_id;
+                                                                    view! {
+                                                                        <TableRow>
+                                                                            <TableCell>
+                                                                                <TableCellLayout truncate=true>{member.username}</TableCellLayout>
+                                                                            </TableCell>
+                                                                            <TableCell>{member.role.label()}</TableCell>
+                                                                            <TableCell>
+                                                                                <Show when=move || is_admin>
+                                                                                    <Button
+                                                                                        appearance=ButtonAppearance::Subtle
+                                                                                        icon=icondata::AiCloseCircleOutlined
+                                                                                        on_click=move |_| {
+                                                                                            remove_member.dispatch(RemoveWorkspaceMember { user_id });
+                                                                                        }
+                                                                                    />
+                                                                                </Show>
+                                                                            </TableCell>
+                                                                        </TableRow>
+                                                                    }
+                                                                })
+                                                                .collect_view()
+                                                                .into_any()
+                                                        }
+                                                    })
+                                                }}
+                                            </TableBody>
+                                        </Table>
+                                    </Card>
+                                    <Show when=move || is_admin>
+                                        <Flex align=FlexAlign::Center gap=FlexGap::Small>
+                                            <Input value=username placeholder="Username" />
+                                            <Select value=role>
+                                                {WorkspaceRole::ALL
+                                                    .into_iter()
+                                                    .map(|role| view! { <option value=role.as_str()>{role.label()}</option> })
+                                                    .collect_view()}
+                                            </Select>
+                                            <Button
+                                                appearance=ButtonAppearance::Primary
+                                                icon=icondata::LuUserPlus
+                                                on_click=move |_| {
+                                                    let role = WorkspaceRole::parse(&role.get_untracked()).unwrap_or(WorkspaceRole::Member);
+                                                    if !username.get_untracked().trim().is_empty() {
+                                                        add_member.dispatch(AddWorkspaceMember { username: username.get_untracked(), role });
+                                                        username.set(String::new());
+                                                    }
+                                                }
+                                            >
+                                                "Add member"
+                                            </Button>
+                                        </Flex>
+                                    </Show>
+                                }
+                            })
+                        })
+                    }}
+                </ErrorBoundary>
+            </Transition>
+            {error}
+            <Divider />
+            <Flex align=FlexAlign::Center gap=FlexGap::Small>
+                <Text>"New Workspace"</Text>
+                <Input value=name />
+                <Button appearance=ButtonAppearance::Primary on_click=move |_| {
+                    add_workspace.dispatch(AddWorkspace { name: name.get() });
+                    name.set(String::new());
+                }>"Create"</Button>
+            </Flex>
+        </Flex>
+    }
+}